@@ -0,0 +1,168 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Write;
+
+use crate::indices::{outcome_indices, OutcomeIndices};
+use crate::simulation::{simulate_election, ElectoralSystem};
+use crate::ElectionResult;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartyOutcome {
+    pub party: String,
+    pub votes: u32,
+    pub vote_share: f64, // Fraction of the national vote
+    pub seats: u32,
+    pub seat_share: f64, // Fraction of the seats
+}
+
+// How one electoral system turns an election's votes into seats
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemComparison {
+    pub system: ElectoralSystem,
+    pub total_seats: u32,
+    pub majority: u32,                  // Seats needed for a majority
+    pub largest_party: Option<String>,  // None if the largest parties tie
+    pub majority_party: Option<String>, // None for a hung parliament
+    pub indices: OutcomeIndices,
+    pub parties: Vec<PartyOutcome>, // By votes, largest first
+}
+
+/// Summarises the seats won under `system` against the national vote in
+/// `election_result.overall_result`.
+pub fn summarise_outcome(
+    election_result: &ElectionResult,
+    system: &ElectoralSystem,
+    seats: &HashMap<String, u32>,
+) -> SystemComparison {
+    let votes = &election_result.overall_result;
+    let total_votes: u32 = votes.values().sum();
+    let total_seats: u32 = seats.values().sum();
+    let majority = total_seats / 2 + 1;
+    let fraction = |part: u32, total: u32| {
+        if total == 0 {
+            0.0
+        } else {
+            part as f64 / total as f64
+        }
+    };
+
+    let mut parties: Vec<PartyOutcome> = votes
+        .keys()
+        .chain(seats.keys().filter(|party| !votes.contains_key(*party)))
+        .map(|party| {
+            let party_votes = votes.get(party).copied().unwrap_or(0);
+            let party_seats = seats.get(party).copied().unwrap_or(0);
+            PartyOutcome {
+                party: party.clone(),
+                votes: party_votes,
+                vote_share: fraction(party_votes, total_votes),
+                seats: party_seats,
+                seat_share: fraction(party_seats, total_seats),
+            }
+        })
+        .collect();
+    parties.sort_by(|a, b| {
+        b.votes
+            .cmp(&a.votes)
+            .then(b.seats.cmp(&a.seats))
+            .then(a.party.cmp(&b.party))
+    });
+
+    let most_seats = seats.values().copied().max().unwrap_or(0);
+    let leaders: Vec<&String> = seats
+        .iter()
+        .filter(|(_, &s)| s == most_seats && s > 0)
+        .map(|(party, _)| party)
+        .collect();
+    let largest_party = match leaders[..] {
+        [party] => Some(party.clone()),
+        _ => None,
+    };
+    let majority_party = largest_party
+        .clone()
+        .filter(|party| seats[party] >= majority);
+
+    SystemComparison {
+        system: system.clone(),
+        total_seats,
+        majority,
+        largest_party,
+        majority_party,
+        indices: outcome_indices(votes, seats),
+        parties,
+    }
+}
+
+/// Runs every system in `systems` over the same election. Fails if a system
+/// that transfers votes is asked for without preference flows.
+pub fn compare_systems(
+    election_result: &ElectionResult,
+    systems: &[ElectoralSystem],
+    preference_flows: Option<&HashMap<String, HashMap<String, f32>>>,
+) -> Result<Vec<SystemComparison>, Box<dyn Error>> {
+    systems
+        .iter()
+        .map(|system| {
+            if system.needs_preference_flows() && preference_flows.is_none() {
+                return Err(format!("{} needs preference flows", system.name()).into());
+            }
+            let seats = simulate_election(election_result, system, preference_flows.cloned())?;
+            Ok(summarise_outcome(election_result, system, &seats))
+        })
+        .collect()
+}
+
+/// Writes a comparison as CSV with one row per system and party. The
+/// system-wide columns repeat on each of a system's rows.
+pub fn write_comparison_csv<W: Write>(
+    writer: W,
+    comparisons: &[SystemComparison],
+) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record([
+        "system",
+        "party",
+        "votes",
+        "vote_share",
+        "seats",
+        "seat_share",
+        "largest_party",
+        "majority_party",
+        "gallagher",
+        "loosemore_hanby",
+        "sainte_lague",
+        "dhondt",
+        "effective_parties_by_votes",
+        "effective_parties_by_seats",
+        "golosov_by_votes",
+        "golosov_by_seats",
+        "wasted_vote_share",
+    ])?;
+    for comparison in comparisons {
+        let indices = &comparison.indices;
+        for party in &comparison.parties {
+            csv_writer.write_record([
+                comparison.system.name(),
+                &party.party,
+                &party.votes.to_string(),
+                &format!("{:.4}", party.vote_share),
+                &party.seats.to_string(),
+                &format!("{:.4}", party.seat_share),
+                comparison.largest_party.as_deref().unwrap_or(""),
+                comparison.majority_party.as_deref().unwrap_or(""),
+                &format!("{:.2}", indices.gallagher),
+                &format!("{:.2}", indices.loosemore_hanby),
+                &format!("{:.2}", indices.sainte_lague),
+                &format!("{:.3}", indices.dhondt),
+                &format!("{:.2}", indices.effective_parties_by_votes),
+                &format!("{:.2}", indices.effective_parties_by_seats),
+                &format!("{:.2}", indices.golosov_by_votes),
+                &format!("{:.2}", indices.golosov_by_seats),
+                &format!("{:.4}", indices.wasted_vote_share),
+            ])?;
+        }
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
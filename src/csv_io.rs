@@ -0,0 +1,339 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::{Candidate, Constituency, ConstituencyResult, ElectionResult, Party, Subdivision};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CsvLayout {
+    Wide, // One row per constituency, one column per party
+    Long, // One row per candidate: constituency, candidate, party, votes
+}
+
+// Subdivision used when a CSV has no subdivision column
+pub const DEFAULT_SUBDIVISION: &str = "All";
+
+/// Describes how the columns of a results spreadsheet map onto an `ElectionResult`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    pub layout: CsvLayout,
+    pub constituency: String,
+    #[serde(default)]
+    pub subdivision: Option<String>,
+    // Wide layout: column heading -> party id. When empty, every column not
+    // named elsewhere in the mapping or in `ignore` is read as a party.
+    #[serde(default)]
+    pub parties: HashMap<String, String>,
+    #[serde(default)]
+    pub ignore: Vec<String>,
+    // Long layout columns
+    #[serde(default)]
+    pub candidate: Option<String>,
+    #[serde(default)]
+    pub party: Option<String>,
+    #[serde(default)]
+    pub votes: Option<String>,
+}
+
+impl ColumnMapping {
+    pub fn wide(constituency: &str, subdivision: Option<&str>) -> Self {
+        ColumnMapping {
+            layout: CsvLayout::Wide,
+            constituency: constituency.to_string(),
+            subdivision: subdivision.map(str::to_string),
+            parties: HashMap::new(),
+            ignore: Vec::new(),
+            candidate: None,
+            party: None,
+            votes: None,
+        }
+    }
+
+    pub fn long(constituency: &str, subdivision: Option<&str>) -> Self {
+        ColumnMapping {
+            layout: CsvLayout::Long,
+            candidate: Some("candidate".to_string()),
+            party: Some("party".to_string()),
+            votes: Some("votes".to_string()),
+            ..ColumnMapping::wide(constituency, subdivision)
+        }
+    }
+}
+
+pub fn load_column_mapping<P: AsRef<Path>>(path: P) -> Result<ColumnMapping, Box<dyn Error>> {
+    let file_content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&file_content)?)
+}
+
+pub fn parse_votes(field: &str) -> Result<u32, std::num::ParseIntError> {
+    let field = field.trim().replace(',', "");
+    if field.is_empty() {
+        return Ok(0);
+    }
+    field.parse()
+}
+
+fn column_index(headers: &csv::StringRecord, name: &str) -> Result<usize, Box<dyn Error>> {
+    headers
+        .iter()
+        .position(|h| h.trim() == name)
+        .ok_or_else(|| format!("results file has no '{}' column", name).into())
+}
+
+fn vote_count(record: &csv::StringRecord, index: usize, row: usize) -> Result<u32, Box<dyn Error>> {
+    parse_votes(&record[index])
+        .map_err(|_| format!("row {}: '{}' is not a vote count", row, &record[index]).into())
+}
+
+/// Reads a results spreadsheet into an `ElectionResult` using `mapping`.
+pub fn read_election_csv<R: Read>(
+    reader: R,
+    mapping: &ColumnMapping,
+    datetime: DateTime<Utc>,
+) -> Result<ElectionResult, Box<dyn Error>> {
+    let mut csv_reader = csv::Reader::from_reader(reader);
+    let headers = csv_reader.headers()?.clone();
+    let name_column = column_index(&headers, &mapping.constituency)?;
+    let subdivision_column = mapping
+        .subdivision
+        .as_ref()
+        .map(|s| column_index(&headers, s))
+        .transpose()?;
+
+    // Constituencies are kept in the order they first appear
+    let mut constituencies: Vec<ConstituencyResult> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut constituency_for = |record: &csv::StringRecord| {
+        let name = record[name_column].trim().to_string();
+        let position = *positions.entry(name.clone()).or_insert_with(|| {
+            let subdivision = subdivision_column
+                .map(|i| record[i].trim().to_string())
+                .unwrap_or_else(|| DEFAULT_SUBDIVISION.to_string());
+            constituencies.push(ConstituencyResult {
+                constituency: Constituency {
+                    name,
+                    subdivision: Subdivision { name: subdivision },
+                    candidates: Vec::new(),
+                },
+                results: HashMap::new(),
+            });
+            constituencies.len() - 1
+        });
+        position
+    };
+
+    let mut rows = Vec::new();
+    match mapping.layout {
+        CsvLayout::Wide => {
+            let party_columns: Vec<(usize, String)> = headers
+                .iter()
+                .enumerate()
+                .filter_map(|(i, h)| {
+                    let h = h.trim();
+                    if mapping.parties.is_empty() {
+                        let named = i == name_column
+                            || Some(i) == subdivision_column
+                            || mapping.ignore.iter().any(|c| c == h);
+                        (!named).then(|| (i, h.to_string()))
+                    } else {
+                        mapping.parties.get(h).map(|party| (i, party.clone()))
+                    }
+                })
+                .collect();
+            if party_columns.is_empty() {
+                return Err("results file has no party vote columns".into());
+            }
+
+            for (row, record) in csv_reader.records().enumerate() {
+                let record = record?;
+                let position = constituency_for(&record);
+                for (i, party) in &party_columns {
+                    let votes = vote_count(&record, *i, row + 2)?;
+                    if votes > 0 {
+                        rows.push((position, None, party.clone(), votes));
+                    }
+                }
+            }
+        }
+        CsvLayout::Long => {
+            let required = |column: &Option<String>, what: &str| {
+                column
+                    .as_ref()
+                    .ok_or_else(|| format!("long layout mapping needs a '{}' column", what))
+                    .and_then(|c| column_index(&headers, c).map_err(|e| e.to_string()))
+            };
+            let party_column = required(&mapping.party, "party")?;
+            let votes_column = required(&mapping.votes, "votes")?;
+            let candidate_column = mapping
+                .candidate
+                .as_ref()
+                .map(|c| column_index(&headers, c))
+                .transpose()?;
+
+            for (row, record) in csv_reader.records().enumerate() {
+                let record = record?;
+                let position = constituency_for(&record);
+                let party = record[party_column].trim();
+                let party = mapping
+                    .parties
+                    .get(party)
+                    .cloned()
+                    .unwrap_or_else(|| party.to_string());
+                let candidate = candidate_column.map(|i| record[i].trim().to_string());
+                let votes = vote_count(&record, votes_column, row + 2)?;
+                rows.push((position, candidate, party, votes));
+            }
+        }
+    }
+
+    for (position, candidate, party, votes) in rows {
+        let constituency_result = &mut constituencies[position];
+        // Several candidates for one party (usually independents) are kept
+        // apart as IND, IND2, IND3, ...
+        let mut party_name = party.clone();
+        let mut count = 1;
+        while constituency_result.results.contains_key(&party_name) {
+            count += 1;
+            party_name = format!("{}{}", party, count);
+        }
+
+        let candidates = &mut constituency_result.constituency.candidates;
+        candidates.push(Candidate {
+            name: candidate.unwrap_or_else(|| format!("Candidate{}", candidates.len() + 1)),
+            party: Party {
+                name: party_name.clone(),
+            },
+        });
+        constituency_result.results.insert(party_name, votes);
+    }
+
+    // Built from the constituencies so that IND2, IND3, ... match up
+    let mut election_result = ElectionResult::new(datetime, constituencies, HashMap::new());
+    election_result.recompute_overall_result();
+    election_result.metadata.election_date = Some(datetime.date_naive());
+    election_result.metadata.retrieved_at = Some(Utc::now());
+    Ok(election_result)
+}
+
+pub fn import_election_csv<P: AsRef<Path>>(
+    path: P,
+    mapping: &ColumnMapping,
+    datetime: DateTime<Utc>,
+) -> Result<ElectionResult, Box<dyn Error>> {
+    let mut election_result = read_election_csv(File::open(&path)?, mapping, datetime)?;
+    election_result.metadata.source = Some(path.as_ref().display().to_string());
+    Ok(election_result)
+}
+
+/// Writes an `ElectionResult` as CSV. The wide layout has a column for every
+/// party standing anywhere; the long layout has one row per candidate.
+pub fn write_election_csv<W: Write>(
+    writer: W,
+    election_result: &ElectionResult,
+    layout: CsvLayout,
+) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    match layout {
+        CsvLayout::Wide => {
+            let mut parties: Vec<&String> = election_result
+                .constituencies
+                .iter()
+                .flat_map(|c| c.results.keys())
+                .collect();
+            parties.sort();
+            parties.dedup();
+
+            let mut header = vec!["constituency", "subdivision"];
+            header.extend(parties.iter().map(|p| p.as_str()));
+            csv_writer.write_record(&header)?;
+            for constituency_result in &election_result.constituencies {
+                let mut record = vec![
+                    constituency_result.constituency.name.clone(),
+                    constituency_result.constituency.subdivision.name.clone(),
+                ];
+                record.extend(parties.iter().map(|p| {
+                    constituency_result
+                        .results
+                        .get(*p)
+                        .copied()
+                        .unwrap_or(0)
+                        .to_string()
+                }));
+                csv_writer.write_record(&record)?;
+            }
+        }
+        CsvLayout::Long => {
+            csv_writer.write_record([
+                "constituency",
+                "subdivision",
+                "candidate",
+                "party",
+                "votes",
+            ])?;
+            for constituency_result in &election_result.constituencies {
+                let constituency = &constituency_result.constituency;
+                for candidate in &constituency.candidates {
+                    let votes = constituency_result
+                        .results
+                        .get(&candidate.party.name)
+                        .copied()
+                        .unwrap_or(0);
+                    csv_writer.write_record([
+                        constituency.name.as_str(),
+                        constituency.subdivision.name.as_str(),
+                        candidate.name.as_str(),
+                        candidate.party.name.as_str(),
+                        &votes.to_string(),
+                    ])?;
+                }
+            }
+        }
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// Writes a simulated seat count as CSV, alongside each party's national vote
+/// and the resulting seat and vote shares.
+pub fn write_seats_csv<W: Write>(
+    writer: W,
+    election_result: &ElectionResult,
+    seats: &HashMap<String, u32>,
+) -> Result<(), Box<dyn Error>> {
+    let total_seats: u32 = seats.values().sum();
+    let total_votes: u32 = election_result.overall_result.values().sum();
+    let mut parties: BTreeMap<&String, (u32, u32)> = BTreeMap::new();
+    for (party, &party_seats) in seats {
+        parties.entry(party).or_default().0 = party_seats;
+    }
+    for (party, &votes) in &election_result.overall_result {
+        parties.entry(party).or_default().1 = votes;
+    }
+
+    let share = |part: u32, total: u32| {
+        if total == 0 {
+            0.0
+        } else {
+            part as f64 / total as f64
+        }
+    };
+
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(["party", "seats", "seat_share", "votes", "vote_share"])?;
+    for (party, (party_seats, votes)) in parties {
+        csv_writer.write_record([
+            party.clone(),
+            party_seats.to_string(),
+            format!("{:.4}", share(party_seats, total_seats)),
+            votes.to_string(),
+            format!("{:.4}", share(votes, total_votes)),
+        ])?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::registry::{load_party_registry, PartyRegistry};
+use crate::schema::load_election_data;
+use crate::ElectionResult;
+
+// data/elections.json lists the election datasets bundled with the tool
+pub const DATASET_INDEX: &str = "data/elections.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dataset {
+    pub id: String,         // Election id, also the key into the party registry
+    pub name: String,       // Label shown in menus
+    pub file: PathBuf,      // Election data file, relative to the index
+    pub boundaries: String, // Boundary set the results were fought on
+    // Provenance for data files that predate the metadata block
+    #[serde(default)]
+    pub election_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub chamber: Option<String>,
+    #[serde(default)]
+    pub registry: Option<PathBuf>, // Party registry file, relative to the index
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetIndex {
+    pub datasets: Vec<Dataset>,
+    #[serde(skip)]
+    root: PathBuf,
+}
+
+pub fn load_dataset_index<P: AsRef<Path>>(path: P) -> Result<DatasetIndex, Box<dyn Error>> {
+    let file_content = fs::read_to_string(&path)?;
+    let mut index: DatasetIndex = serde_json::from_str(&file_content)?;
+    index.root = path
+        .as_ref()
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    Ok(index)
+}
+
+impl DatasetIndex {
+    pub fn dataset(&self, id: &str) -> Option<&Dataset> {
+        self.datasets.iter().find(|d| d.id == id)
+    }
+
+    pub fn file_path(&self, dataset: &Dataset) -> PathBuf {
+        self.root.join(&dataset.file)
+    }
+
+    pub fn registry(&self, dataset: &Dataset) -> Result<Option<PartyRegistry>, Box<dyn Error>> {
+        dataset
+            .registry
+            .as_ref()
+            .map(|registry| load_party_registry(self.root.join(registry), &dataset.id))
+            .transpose()
+    }
+
+    /// Loads a dataset, normalising party labels through its registry if it has
+    /// one, and filling in provenance the data file itself lacks.
+    pub fn load(&self, dataset: &Dataset) -> Result<ElectionResult, Box<dyn Error>> {
+        let mut election_result = load_election_data(self.file_path(dataset))?;
+        election_result.verify_content_hash()?;
+
+        if election_result.metadata.election_date.is_none() {
+            if let Some(election_date) = dataset.election_date {
+                election_result.metadata.election_date = Some(election_date);
+                election_result.datetime = election_date.and_hms_opt(0, 0, 0).unwrap().and_utc();
+            }
+        }
+        let metadata = &mut election_result.metadata;
+        metadata.country = metadata.country.take().or_else(|| dataset.country.clone());
+        metadata.chamber = metadata.chamber.take().or_else(|| dataset.chamber.clone());
+        metadata.source = metadata
+            .source
+            .take()
+            .or_else(|| Some(self.file_path(dataset).display().to_string()));
+
+        if let Some(registry) = self.registry(dataset)? {
+            registry.normalise_election(&mut election_result);
+        }
+        Ok(election_result)
+    }
+
+    /// The dataset whose data file is at `path`, if any.
+    pub fn dataset_for_file<P: AsRef<Path>>(&self, path: P) -> Option<&Dataset> {
+        let path = fs::canonicalize(path).ok()?;
+        self.datasets
+            .iter()
+            .find(|d| fs::canonicalize(self.file_path(d)).is_ok_and(|p| p == path))
+    }
+}
+
+/// Loads an election data file along with the party registry that anything
+/// read alongside it (preference flows, polls) should be normalised through.
+/// Files in the bundled index are loaded through it, normalising their party
+/// labels; any other file is loaded as it is, without a registry.
+pub fn load_election<P: AsRef<Path>>(
+    path: P,
+) -> Result<(ElectionResult, Option<PartyRegistry>), Box<dyn Error>> {
+    if Path::new(DATASET_INDEX).exists() {
+        let index = load_dataset_index(DATASET_INDEX)?;
+        if let Some(dataset) = index.dataset_for_file(&path) {
+            return Ok((index.load(dataset)?, index.registry(dataset)?));
+        }
+    }
+    Ok((load_election_data(path)?, None))
+}
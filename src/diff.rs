@@ -0,0 +1,178 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::io::Write;
+
+use crate::simulation::{constituency_winners, ElectoralSystem};
+use crate::swing::{seat_changes, SeatChange};
+use crate::ElectionResult;
+
+// Seats passing from one party to another
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeatFlow {
+    pub from: String,
+    pub to: String,
+    pub seats: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeatDiff {
+    pub changes: Vec<SeatChange>,
+    pub flows: Vec<SeatFlow>, // Most seats first
+    pub net: BTreeMap<String, i64>,
+    // Winner before -> winner after -> seats, with holds on the diagonal
+    pub matrix: BTreeMap<String, BTreeMap<String, u32>>,
+}
+
+impl SeatDiff {
+    /// Seats `party` took from others, and others took from it.
+    pub fn gains_and_losses(&self, party: &str) -> (u32, u32) {
+        let gains = self.flows.iter().filter(|f| f.to == party).map(|f| f.seats);
+        let losses = self
+            .flows
+            .iter()
+            .filter(|f| f.from == party)
+            .map(|f| f.seats);
+        (gains.sum(), losses.sum())
+    }
+}
+
+/// Diffs two sets of winners for `election_result`'s constituencies, in
+/// their order. Constituencies without a winner on either side are skipped.
+pub fn diff_winners(
+    election_result: &ElectionResult,
+    before: &[Option<String>],
+    after: &[Option<String>],
+) -> SeatDiff {
+    let mut matrix: BTreeMap<String, BTreeMap<String, u32>> = BTreeMap::new();
+    for (from, to) in before.iter().zip(after) {
+        if let (Some(from), Some(to)) = (from, to) {
+            *matrix
+                .entry(from.clone())
+                .or_default()
+                .entry(to.clone())
+                .or_default() += 1;
+        }
+    }
+
+    let mut flows = Vec::new();
+    let mut net: BTreeMap<String, i64> = BTreeMap::new();
+    for (from, row) in &matrix {
+        net.entry(from.clone()).or_default();
+        for (to, &seats) in row {
+            net.entry(to.clone()).or_default();
+            if from != to {
+                *net.get_mut(from).unwrap() -= seats as i64;
+                *net.get_mut(to).unwrap() += seats as i64;
+                flows.push(SeatFlow {
+                    from: from.clone(),
+                    to: to.clone(),
+                    seats,
+                });
+            }
+        }
+    }
+    flows.sort_by_key(|flow| std::cmp::Reverse(flow.seats));
+
+    SeatDiff {
+        changes: seat_changes(election_result, before, after),
+        flows,
+        net,
+        matrix,
+    }
+}
+
+fn winners_or_err(
+    election_result: &ElectionResult,
+    electoral_system: &ElectoralSystem,
+    preference_flows: Option<&HashMap<String, HashMap<String, f32>>>,
+) -> Result<Vec<Option<String>>, Box<dyn Error>> {
+    if electoral_system.needs_preference_flows() && preference_flows.is_none() {
+        return Err(format!("{} needs preference flows", electoral_system.name()).into());
+    }
+    constituency_winners(election_result, electoral_system, preference_flows).ok_or_else(|| {
+        format!(
+            "{} doesn't elect one member per constituency",
+            electoral_system.name()
+        )
+        .into()
+    })
+}
+
+/// Diffs the constituency winners of two outcomes, such as one election under
+/// FPTP and AV, or a projection and its baseline. Constituencies are matched
+/// by name, so both elections must have the same constituencies.
+pub fn diff_outcomes(
+    before: &ElectionResult,
+    before_system: &ElectoralSystem,
+    after: &ElectionResult,
+    after_system: &ElectoralSystem,
+    preference_flows: Option<&HashMap<String, HashMap<String, f32>>>,
+) -> Result<SeatDiff, Box<dyn Error>> {
+    let before_winners = winners_or_err(before, before_system, preference_flows)?;
+    let after_winners: HashMap<&str, Option<String>> = after
+        .constituencies
+        .iter()
+        .map(|c| c.constituency.name.as_str())
+        .zip(winners_or_err(after, after_system, preference_flows)?)
+        .collect();
+    if after_winners.len() != before.constituencies.len() {
+        return Err("the two elections have different numbers of constituencies".into());
+    }
+    let after_winners = before
+        .constituencies
+        .iter()
+        .map(|c| {
+            let name = c.constituency.name.as_str();
+            after_winners
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("no constituency called '{}' in the second election", name))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(diff_winners(before, &before_winners, &after_winners))
+}
+
+pub fn write_seat_flows_csv<W: Write>(writer: W, diff: &SeatDiff) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(["from", "to", "seats"])?;
+    for flow in &diff.flows {
+        csv_writer.write_record([&flow.from, &flow.to, &flow.seats.to_string()])?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// Writes the gains and losses matrix with a row for each party's seats
+/// before and a column for each party's seats after.
+pub fn write_seat_matrix_csv<W: Write>(writer: W, diff: &SeatDiff) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    let parties: Vec<&String> = diff.net.keys().collect();
+    csv_writer.write_record(std::iter::once("from").chain(parties.iter().map(|p| p.as_str())))?;
+    for from in &parties {
+        let row = diff.matrix.get(*from);
+        let mut record = vec![from.to_string()];
+        for to in &parties {
+            let seats = row.and_then(|row| row.get(*to)).copied().unwrap_or(0);
+            record.push(seats.to_string());
+        }
+        csv_writer.write_record(&record)?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+pub fn write_seat_changes_csv<W: Write>(writer: W, diff: &SeatDiff) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(["constituency", "subdivision", "from", "to"])?;
+    for change in &diff.changes {
+        csv_writer.write_record([
+            &change.constituency,
+            &change.subdivision,
+            &change.from,
+            &change.to,
+        ])?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::csv_io::parse_votes;
+use crate::{Candidate, Constituency, ConstituencyResult, ElectionResult, Party, Subdivision};
+
+// Columns in the House of Commons Library constituency results files that are
+// not party vote counts. Headings are matched in snake case, so both the
+// Library's "Constituency name" and "constituency_name" forms are accepted.
+const METADATA_COLUMNS: [&str; 18] = [
+    "ons_id",
+    "ons_region_id",
+    "constituency_name",
+    "county_name",
+    "region_name",
+    "country_name",
+    "constituency_type",
+    "declaration_time",
+    "member_first_name",
+    "member_surname",
+    "member_gender",
+    "result",
+    "first_party",
+    "second_party",
+    "electorate",
+    "valid_votes",
+    "invalid_votes",
+    "majority",
+];
+
+// "Of which other winner" is a breakdown of the Other column, not extra votes
+const IGNORED_COLUMNS: [&str; 1] = ["of_which_other_winner"];
+
+// Library column headings (and first party abbreviations) and the party ids
+// used in this repo's data files
+const PARTY_COLUMNS: [(&str, &str); 19] = [
+    ("con", "CON"),
+    ("lab", "LAB"),
+    ("ld", "LD"),
+    ("ruk", "REF"),
+    ("reform", "REF"),
+    ("brx", "BRX"),
+    ("ukip", "UKIP"),
+    ("green", "GRN"),
+    ("snp", "SNP"),
+    ("pc", "PC"),
+    ("dup", "DUP"),
+    ("sf", "SF"),
+    ("sdlp", "SDLP"),
+    ("uup", "UUP"),
+    ("apni", "APNI"),
+    ("alliance", "APNI"),
+    ("tuv", "TUV"),
+    ("other", "OTH"),
+    ("all_other_candidates", "OTH"),
+];
+
+// "Constituency name", "constituency_name" and "Constituency-name" all give
+// "constituency_name"
+fn snake_case(heading: &str) -> String {
+    heading.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+fn party_id(column: &str) -> Option<&'static str> {
+    let column = snake_case(column);
+    PARTY_COLUMNS
+        .iter()
+        .find(|(heading, _)| *heading == column)
+        .map(|(_, id)| *id)
+}
+
+/// Reads a House of Commons Library style constituency results CSV (one row per
+/// constituency, one column per party) into an `ElectionResult`. Columns that
+/// are neither Library metadata nor a known party are an error.
+pub fn read_hoc_csv<R: Read>(
+    reader: R,
+    datetime: DateTime<Utc>,
+) -> Result<ElectionResult, Box<dyn Error>> {
+    let mut csv_reader = csv::Reader::from_reader(reader);
+    let headers = csv_reader.headers()?.clone();
+
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| snake_case(h) == name)
+            .ok_or_else(|| format!("results file has no '{}' column", name))
+    };
+    let name_column = column("constituency_name")?;
+    let country_column = column("country_name")?;
+    let first_party_column = column("first_party").ok();
+    let member_columns = column("member_first_name")
+        .and_then(|first| Ok((first, column("member_surname")?)))
+        .ok();
+
+    let mut party_columns: Vec<(usize, String)> = Vec::new();
+    let mut unknown = Vec::new();
+    for (i, heading) in headers.iter().enumerate() {
+        let key = snake_case(heading);
+        if METADATA_COLUMNS.contains(&key.as_str()) || IGNORED_COLUMNS.contains(&key.as_str()) {
+            continue;
+        }
+        match party_id(heading) {
+            Some(party) => party_columns.push((i, party.to_string())),
+            None => unknown.push(heading.trim()),
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(format!(
+            "results file has unrecognised columns: {}",
+            unknown.join(", ")
+        )
+        .into());
+    }
+    if party_columns.is_empty() {
+        return Err("results file has no party vote columns".into());
+    }
+
+    let mut constituencies = Vec::new();
+    let mut overall_result = HashMap::new();
+
+    for (row_number, record) in csv_reader.records().enumerate() {
+        let record = record?;
+        let constituency_name = record[name_column].trim().to_string();
+        let winning_party = first_party_column.and_then(|i| party_id(&record[i]));
+
+        let mut candidates = Vec::new();
+        let mut results = HashMap::new();
+        for (i, party_name) in &party_columns {
+            let votes = parse_votes(&record[*i]).map_err(|_| {
+                format!(
+                    "row {}: '{}' is not a vote count for {}",
+                    row_number + 2,
+                    &record[*i],
+                    headers[*i].trim()
+                )
+            })?;
+            if votes == 0 {
+                continue;
+            }
+
+            // The Library files only name the winning member
+            let candidate_name = match (member_columns, &winning_party) {
+                (Some((first, surname)), Some(winner)) if *winner == party_name.as_str() => {
+                    format!("{} {}", record[first].trim(), record[surname].trim())
+                }
+                _ => format!("Candidate{}", candidates.len() + 1),
+            };
+            candidates.push(Candidate {
+                name: candidate_name,
+                party: Party {
+                    name: party_name.clone(),
+                },
+            });
+            *overall_result.entry(party_name.clone()).or_insert(0) += votes;
+            results.insert(party_name.clone(), votes);
+        }
+
+        constituencies.push(ConstituencyResult {
+            constituency: Constituency {
+                name: constituency_name,
+                subdivision: Subdivision {
+                    name: record[country_column].trim().to_string(),
+                },
+                candidates,
+            },
+            results,
+        });
+    }
+
+    let mut election_result = ElectionResult::new(datetime, constituencies, overall_result);
+    let metadata = &mut election_result.metadata;
+    metadata.election_date = Some(datetime.date_naive());
+    metadata.country = Some("United Kingdom".to_string());
+    metadata.chamber = Some("House of Commons".to_string());
+    metadata.retrieved_at = Some(Utc::now());
+    Ok(election_result)
+}
+
+pub fn import_hoc_csv<P: AsRef<Path>>(
+    path: P,
+    datetime: DateTime<Utc>,
+) -> Result<ElectionResult, Box<dyn Error>> {
+    let mut election_result = read_hoc_csv(File::open(&path)?, datetime)?;
+    election_result.metadata.source = Some(path.as_ref().display().to_string());
+    Ok(election_result)
+}
@@ -0,0 +1,153 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+// How proportional and how fragmented an outcome is. Disproportionality
+// indices are in percentage points, except D'Hondt's, which is a ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct OutcomeIndices {
+    pub gallagher: f64,
+    pub loosemore_hanby: f64,
+    pub sainte_lague: f64,
+    pub dhondt: f64, // The largest ratio of seat share to vote share
+    pub effective_parties_by_votes: f64, // Laakso–Taagepera
+    pub effective_parties_by_seats: f64,
+    pub golosov_by_votes: f64,
+    pub golosov_by_seats: f64,
+    pub wasted_vote_share: f64, // Fraction of votes for parties winning no seats
+}
+
+// Each party's share of the votes and of the seats, as fractions, in party
+// order so that sums of them come out the same on every run
+fn shares(votes: &HashMap<String, u32>, seats: &HashMap<String, u32>) -> Vec<(f64, f64)> {
+    let total_votes: u32 = votes.values().sum();
+    let total_seats: u32 = seats.values().sum();
+    let share = |count: Option<&u32>, total: u32| {
+        if total == 0 {
+            0.0
+        } else {
+            count.copied().unwrap_or(0) as f64 / total as f64
+        }
+    };
+    let parties: BTreeSet<&String> = votes.keys().chain(seats.keys()).collect();
+    parties
+        .into_iter()
+        .map(|party| {
+            (
+                share(votes.get(party), total_votes),
+                share(seats.get(party), total_seats),
+            )
+        })
+        .collect()
+}
+
+/// Gallagher's least-squares index of disproportionality, in percentage
+/// points: the square root of half the sum of squared differences between
+/// each party's vote and seat shares.
+pub fn gallagher_index(votes: &HashMap<String, u32>, seats: &HashMap<String, u32>) -> f64 {
+    let sum_of_squares: f64 = shares(votes, seats)
+        .iter()
+        .map(|(vote_share, seat_share)| ((vote_share - seat_share) * 100.0).powi(2))
+        .sum();
+    (sum_of_squares / 2.0).sqrt()
+}
+
+/// The Loosemore–Hanby index, in percentage points: half the sum of the
+/// absolute differences between vote and seat shares.
+pub fn loosemore_hanby_index(votes: &HashMap<String, u32>, seats: &HashMap<String, u32>) -> f64 {
+    let sum: f64 = shares(votes, seats)
+        .iter()
+        .map(|(vote_share, seat_share)| (vote_share - seat_share).abs() * 100.0)
+        .sum();
+    sum / 2.0
+}
+
+/// The Sainte-Laguë index: each party's squared difference between seat and
+/// vote share, divided by its vote share, summed, with shares in percentage
+/// points. Parties without votes are left out.
+pub fn sainte_lague_index(votes: &HashMap<String, u32>, seats: &HashMap<String, u32>) -> f64 {
+    shares(votes, seats)
+        .iter()
+        .filter(|(vote_share, _)| *vote_share > 0.0)
+        .map(|(vote_share, seat_share)| {
+            ((seat_share - vote_share) * 100.0).powi(2) / (vote_share * 100.0)
+        })
+        .sum()
+}
+
+/// The D'Hondt index: the largest ratio of a party's seat share to its vote
+/// share, so 1 is perfectly proportional for the most over-represented party.
+pub fn dhondt_index(votes: &HashMap<String, u32>, seats: &HashMap<String, u32>) -> f64 {
+    shares(votes, seats)
+        .iter()
+        .filter(|(vote_share, _)| *vote_share > 0.0)
+        .map(|(vote_share, seat_share)| seat_share / vote_share)
+        .fold(0.0, f64::max)
+}
+
+fn fractions(counts: &HashMap<String, u32>) -> Vec<f64> {
+    let total: u32 = counts.values().sum();
+    if total == 0 {
+        return Vec::new();
+    }
+    let mut counts: Vec<(&String, &u32)> = counts.iter().filter(|(_, &c)| c > 0).collect();
+    counts.sort();
+    counts
+        .into_iter()
+        .map(|(_, &count)| count as f64 / total as f64)
+        .collect()
+}
+
+/// The Laakso–Taagepera effective number of parties: one over the sum of
+/// the squared shares of votes or seats.
+pub fn effective_number_of_parties(counts: &HashMap<String, u32>) -> f64 {
+    let sum_of_squares: f64 = fractions(counts).iter().map(|share| share * share).sum();
+    if sum_of_squares == 0.0 {
+        0.0
+    } else {
+        1.0 / sum_of_squares
+    }
+}
+
+/// Golosov's effective number of parties, which weighs each party against
+/// the largest one and so is less dominated by it than Laakso–Taagepera's.
+pub fn golosov_number_of_parties(counts: &HashMap<String, u32>) -> f64 {
+    let shares = fractions(counts);
+    let largest = shares.iter().copied().fold(0.0, f64::max);
+    shares
+        .iter()
+        .map(|share| share / (share + largest * largest - share * share))
+        .sum()
+}
+
+/// The fraction of the votes cast for parties that won no seats.
+pub fn wasted_vote_share(votes: &HashMap<String, u32>, seats: &HashMap<String, u32>) -> f64 {
+    let total_votes: u32 = votes.values().sum();
+    if total_votes == 0 {
+        return 0.0;
+    }
+    let wasted: u32 = votes
+        .iter()
+        .filter(|(party, _)| seats.get(*party).copied().unwrap_or(0) == 0)
+        .map(|(_, &party_votes)| party_votes)
+        .sum();
+    wasted as f64 / total_votes as f64
+}
+
+/// Computes every index for the seats won from `votes`, which is normally an
+/// election's `overall_result`.
+pub fn outcome_indices(
+    votes: &HashMap<String, u32>,
+    seats: &HashMap<String, u32>,
+) -> OutcomeIndices {
+    OutcomeIndices {
+        gallagher: gallagher_index(votes, seats),
+        loosemore_hanby: loosemore_hanby_index(votes, seats),
+        sainte_lague: sainte_lague_index(votes, seats),
+        dhondt: dhondt_index(votes, seats),
+        effective_parties_by_votes: effective_number_of_parties(votes),
+        effective_parties_by_seats: effective_number_of_parties(seats),
+        golosov_by_votes: golosov_number_of_parties(votes),
+        golosov_by_seats: golosov_number_of_parties(seats),
+        wasted_vote_share: wasted_vote_share(votes, seats),
+    }
+}
@@ -0,0 +1,28 @@
+pub mod comparison;
+pub mod csv_io;
+pub mod datasets;
+pub mod diff;
+pub mod hoc;
+pub mod indices;
+pub mod marginals;
+pub mod model;
+pub mod montecarlo;
+pub mod pacts;
+pub mod patch;
+pub mod polls;
+pub mod registry;
+pub mod scenario;
+pub mod schema;
+pub mod scrape;
+pub mod seats_votes;
+pub mod simulation;
+pub mod swing;
+pub mod tactical;
+pub mod transition;
+pub mod validation;
+pub mod wasted;
+
+pub use model::*;
+pub use simulation::{
+    constituency_winners, get_preference_flows, simulate_election, ElectoralSystem,
+};
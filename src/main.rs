@@ -1,162 +1,123 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use dialoguer::{theme::ColorfulTheme, Select};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::fs;
-use std::path::Path;
+use psephulator::comparison::{compare_systems, write_comparison_csv};
+use psephulator::csv_io::{
+    import_election_csv, load_column_mapping, write_election_csv, write_seats_csv, CsvLayout,
+};
+use psephulator::datasets::{load_dataset_index, load_election, DATASET_INDEX};
+use psephulator::diff::{
+    diff_outcomes, write_seat_changes_csv, write_seat_flows_csv, write_seat_matrix_csv,
+};
+use psephulator::hoc::import_hoc_csv;
+use psephulator::indices::{outcome_indices, OutcomeIndices};
+use psephulator::marginals::{
+    marginality, target_list, write_marginality_csv, write_target_list_csv,
+};
+use psephulator::montecarlo::{
+    run_monte_carlo, MonteCarloSettings, NationalError, PartyCorrelation,
+};
+use psephulator::pacts::{apply_pact, load_pact, withdrawn_seats};
+use psephulator::patch::apply_patch_path;
+use psephulator::polls::{append_nowcast, load_polls, nowcast, PollAverageSettings};
+use psephulator::registry::PartyRegistry;
+use psephulator::scenario::{load_scenario, run_scenario, write_scenario_outputs, ScenarioOutput};
+use psephulator::schema::{self, load_election_data, save_election_data};
+use psephulator::seats_votes::{
+    default_majority, write_curve_csv, write_thresholds_csv, SeatTarget, SwingCalculator,
+};
+use psephulator::simulation::load_preference_flows;
+use psephulator::swing::{apply_swing, compare_with_baseline, ProjectionModel, SwingModel, Swings};
+use psephulator::tactical::{apply_tactical_voting, load_tactical_voting};
+use psephulator::transition::{
+    apply_transitions, load_transition_matrix, DEFAULT_BASELINE_TURNOUT,
+};
+use psephulator::validation::{validate_election_file, Severity, ValidationIssue};
+use psephulator::wasted::{analyse_wasted_votes, write_wasted_votes_csv, PartyWastedVotes};
+use psephulator::{
+    simulate_election, Candidate, Constituency, ConstituencyResult, ElectionResult,
+    ElectoralSystem, Party, Subdivision,
+};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io;
+use std::process;
+use std::str::FromStr;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ElectionResult {
-    pub datetime: DateTime<Utc>,
-    pub constituencies: Vec<ConstituencyResult>,
-    pub overall_result: HashMap<String, u32>, // Overall result by party
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ConstituencyResult {
-    pub constituency: Constituency,
-    pub results: HashMap<String, u32>, // Results by party
-}
+const USAGE: &str = "Usage: psephulator <command> [arguments]
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Constituency {
-    pub name: String,
-    pub candidates: Vec<Candidate>,
-}
+Commands:
+  load <election json> [--format text|json]
+  simulate <election json> --system <system> [--flows <json>] [--format text|json|csv]
+  compare <election json> [--systems fptp,av,...] [--flows <json>] [--format text|json|csv]
+  project <election json> --system <system> [--swing PARTY=points,...] [options]
+  diff <election json> [<other election json>] [--before <system>] [--after <system>] [options]
+  marginals <election json> [--party <party>] [--majority <seats>] [--limit <n>] [--format text|json|csv]
+  seats-votes <election json> [--party <party>] [--system fptp|av] [options]
+  validate <election json>... [--format text|json]
+  wasted <election json> [--system fptp|av] [--by-subdivision] [--flows <json>] [--format text|json|csv]
+  export <election json> <output> [--format json|csv] [--layout wide|long]
+  run <scenario json>...
+  nowcast <election json> <system> <polls csv> [options]
+  montecarlo <election json> <system> [options]
+  migrate <election json>...
+  import-hoc <results csv> <election date YYYY-MM-DD> <output json>
+  import-csv <results csv> <column mapping json> <election date YYYY-MM-DD> <output json>
+  export-csv <election json> <wide|long> <output csv> (as export --format csv)
+  patch <election json> <patch json>...
+  interactive
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
-pub struct Party {
-    pub name: String,
-}
+Systems: fptp, av, pr (national list PR), regional_pr, stv, mmp
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Candidate {
-    pub name: String,
-    pub party: Party,
-}
+Exit status is 0 on success, 1 if a command fails or finds invalid data, and 2
+for usage errors.";
 
-#[derive(Debug, Clone)]
-pub enum ElectoralSystem {
-    FirstPastThePost,
-    ProportionalRepresentation,
-    AlternativeVote, // Added for AV system
-}
+// Used for AV and pacts when no --flows file is given
+const DEFAULT_PREFERENCE_FLOWS: &str = "data/preference_flows_england.json";
 
-pub fn simulate_election(
-    election_result: &ElectionResult,
-    electoral_system: &ElectoralSystem,
-    preference_flows: Option<HashMap<String, HashMap<String, f32>>>,
-) -> HashMap<String, u32> {
-    match electoral_system {
-        ElectoralSystem::FirstPastThePost => simulate_first_past_the_post(election_result),
-        ElectoralSystem::ProportionalRepresentation => {
-            simulate_proportional_representation(election_result)
-        }
-        ElectoralSystem::AlternativeVote => {
-            simulate_alternative_vote(election_result, preference_flows.unwrap())
-        }
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Csv,
 }
 
-fn simulate_first_past_the_post(election_result: &ElectionResult) -> HashMap<String, u32> {
-    let mut seat_wins: HashMap<String, u32> = HashMap::new();
-
-    for constituency_result in &election_result.constituencies {
-        if let Some((winning_party, _)) = constituency_result
-            .results
-            .iter()
-            .max_by_key(|&(_, votes)| votes)
-        {
-            *seat_wins.entry(winning_party.clone()).or_insert(0) += 1;
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let rest = args.get(2..).unwrap_or_default();
+    match args.get(1).map(String::as_str) {
+        Some("load") => load_results(rest),
+        Some("simulate") => simulate_results(rest),
+        Some("compare") => compare_results(rest),
+        Some("project") => project_results(rest),
+        Some("diff") => diff_results(rest),
+        Some("marginals") => marginal_seats(rest),
+        Some("seats-votes") => seats_votes(rest),
+        Some("validate") => validate_results(rest),
+        Some("wasted") => wasted_votes(rest),
+        Some("export") => export_results(rest),
+        Some("run") => run_scenarios(rest),
+        Some("nowcast") => nowcast_results(rest),
+        Some("montecarlo") => montecarlo_results(rest),
+        Some("migrate") => migrate_files(rest),
+        Some("import-hoc") => import_hoc_results(rest),
+        Some("import-csv") => import_csv_results(rest),
+        Some("export-csv") => export_csv_results(rest),
+        Some("patch") => patch_results(rest),
+        Some("interactive") => interactive(),
+        Some("help" | "--help" | "-h") => println!("{}", USAGE),
+        Some(command) => {
+            eprintln!("Unknown command '{}'\n{}", command, USAGE);
+            process::exit(2);
         }
-    }
-
-    seat_wins
-}
-
-fn simulate_proportional_representation(_election_result: &ElectionResult) -> HashMap<String, u32> {
-    // Implement Proportional Representation logic here
-    HashMap::new()
-}
-
-fn simulate_alternative_vote(
-    election_result: &ElectionResult,
-    preference_flows: HashMap<String, HashMap<String, f32>>,
-) -> HashMap<String, u32> {
-    let mut seat_wins: HashMap<String, u32> = HashMap::new();
-
-    for constituency_result in &election_result.constituencies {
-        let mut votes = constituency_result.results.clone();
-        let mut eliminated = Vec::new();
-        while votes.len() > 2 {
-            // Find the party with the minimum votes and remove it
-            let (min_party, min_votes) = votes
-                .iter()
-                .min_by_key(|&(_, &votes)| votes)
-                .map(|(party, &votes)| (party.clone(), votes))
-                .unwrap();
-
-            votes.remove(&min_party);
-            eliminated.push(min_party.clone());
-
-            let remaining_parties: Vec<_> = votes.keys().cloned().collect();
-            for (party, &party_votes) in preference_flows.get(&min_party).unwrap_or(&HashMap::new())
-            {
-                if remaining_parties.contains(party) {
-                    let additional_votes = ((min_votes as f32) * party_votes).round() as u32;
-                    // println!("Party {} gets {} additional votes", party, additional_votes);
-                    *votes.get_mut(party).unwrap() += additional_votes;
-                }
-            }
-
-            // Redistribute votes for the eliminated party proportionally if it was previously allocated to eliminated parties
-            let mut redistributed_votes = 0;
-            if !eliminated.is_empty() {
-                for (party, &percentage) in
-                    preference_flows.get(&min_party).unwrap_or(&HashMap::new())
-                {
-                    if eliminated.contains(&party) {
-                        let redistributed = ((min_votes as f32) * percentage).round() as u32; 
-                        // println!("Redistributed {} for {}", redistributed, party);
-                        redistributed_votes += redistributed;
-                    }
-                }
-
-                let total_remaining_percentage: f32 = remaining_parties
-                    .iter()
-                    .map(|p| {
-                        preference_flows
-                            .get(&min_party)
-                            .unwrap_or(&HashMap::new())
-                            .get(p)
-                            .cloned()
-                            .unwrap_or(0.0)
-                    })
-                    .sum();
-
-                for party in &remaining_parties {
-                    if let Some(&party_votes) = preference_flows
-                        .get(&min_party)
-                        .unwrap_or(&HashMap::new())
-                        .get(party)
-                    {
-                        let proportional_share =
-                            (party_votes / total_remaining_percentage) * redistributed_votes as f32;
-                        *votes.get_mut(party).unwrap() += proportional_share.round() as u32;
-                    }
-                }
-            }
+        None => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
         }
-
-        // Determine the winner among the last two remaining parties
-        let (winner, _) = votes.iter().max_by_key(|&(_, &votes)| votes).unwrap();
-        *seat_wins.entry(winner.clone()).or_insert(0) += 1;
     }
-
-    return seat_wins
 }
 
-fn main() {
+fn interactive() {
     println!("Welcome to Psephulator");
     println!("----- v 0.1.0 --------");
 
@@ -180,7 +141,8 @@ fn main() {
 }
 
 fn load_election_results() {
-    let options = &["2024 UK Election"];
+    let index = load_dataset_index(DATASET_INDEX).expect("Unable to load dataset index");
+    let options: Vec<&str> = index.datasets.iter().map(|d| d.name.as_str()).collect();
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Choose an election to load")
         .default(0)
@@ -188,19 +150,22 @@ fn load_election_results() {
         .interact()
         .unwrap();
 
-    let file_path = match selection {
-        0 => "data/uk_2024.json",
-        _ => unreachable!(),
-    };
-
-    let election_result = load_election_data(file_path);
-    println!("Loaded Election Result");
+    let dataset = &index.datasets[selection];
+    let registry = index
+        .registry(dataset)
+        .expect("Unable to load party registry")
+        .unwrap_or_default();
+    let election_result = index.load(dataset).expect("Unable to load election data");
+    println!("Loaded Election Result: {}", election_result.citation());
 
     // Now the user can simulate results in a different electoral system
     let electoral_systems = &[
         "First Past The Post",
         "Proportional Representation",
         "Alternative Vote",
+        "Regional Proportional Representation",
+        "Single Transferable Vote",
+        "Mixed Member Proportional",
     ];
     let system_selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("Choose an electoral system to simulate results")
@@ -213,26 +178,35 @@ fn load_election_results() {
         0 => ElectoralSystem::FirstPastThePost,
         1 => ElectoralSystem::ProportionalRepresentation,
         2 => ElectoralSystem::AlternativeVote,
+        3 => ElectoralSystem::RegionalProportionalRepresentation,
+        4 => ElectoralSystem::SingleTransferableVote,
+        5 => ElectoralSystem::MixedMemberProportional,
         _ => unreachable!(),
     };
 
-     if let ElectoralSystem::AlternativeVote = electoral_system {
+    let simulated_result = if electoral_system.needs_preference_flows() {
         let preference_flows_file = select_preference_flows_file();
-        let preference_flows = load_preference_flows(&preference_flows_file);
-        let simulated_result = simulate_election(&election_result, &electoral_system, Some(preference_flows));
-        println!(
-            "Simulated result: {:?}", simulated_result);    
+        let preference_flows = read_preference_flows(Some(&preference_flows_file), Some(&registry));
+        simulate_election(&election_result, &electoral_system, Some(preference_flows))
     } else {
-        let simulated_result = simulate_election(&election_result, &electoral_system, None);
-        println!(
-            "Simulated result: {:?}", simulated_result);    
-    };
-
+        simulate_election(&election_result, &electoral_system, None)
+    }
+    .unwrap();
+    println!("Simulated result: {:?}", simulated_result);
+    print_alliance_totals(&registry, &simulated_result);
 }
 
-fn load_election_data<P: AsRef<Path>>(path: P) -> ElectionResult {
-    let file_content = fs::read_to_string(path).expect("Unable to read file");
-    serde_json::from_str(&file_content).expect("JSON was not well-formatted")
+fn print_alliance_totals(registry: &PartyRegistry, seats: &HashMap<String, u32>) {
+    for alliance in &registry.alliances {
+        let members = registry.alliance_members(&alliance.id);
+        let alliance_seats: u32 = members.iter().filter_map(|p| seats.get(*p)).sum();
+        println!(
+            "{} ({}): {}",
+            alliance.name,
+            members.join("/"),
+            alliance_seats
+        );
+    }
 }
 
 fn simulate_an_election() {
@@ -246,11 +220,7 @@ fn simulate_an_election() {
 
     match selection {
         0 => {
-            let election_result = setup_two_party_fptp_election();
-            let electoral_systems = &[
-                "First Past The Post",
-                "Alternative Vote",
-            ];
+            let electoral_systems = &["First Past The Post", "Alternative Vote"];
             let system_selection = Select::with_theme(&ColorfulTheme::default())
                 .with_prompt("Choose an electoral system to simulate results")
                 .default(0)
@@ -274,23 +244,1598 @@ fn simulate_an_election() {
             match system_selection {
                 0 => {
                     let election_result = setup_two_party_fptp_election();
-                    let simulated_result = simulate_election(&election_result, &ElectoralSystem::FirstPastThePost, None);
+                    let simulated_result = simulate_election(
+                        &election_result,
+                        &ElectoralSystem::FirstPastThePost,
+                        None,
+                    )
+                    .unwrap();
                     println!("Simulated Result: {:?}", simulated_result);
                 }
                 1 => {
                     let election_result = setup_two_party_fptp_election();
                     let preference_flows_file = select_preference_flows_file();
-                    let preference_flows = load_preference_flows(&preference_flows_file);
-                    let simulated_result = simulate_election(&election_result, &ElectoralSystem::AlternativeVote, Some(preference_flows));
+                    let preference_flows =
+                        read_preference_flows(Some(&preference_flows_file), None);
+                    let simulated_result = simulate_election(
+                        &election_result,
+                        &ElectoralSystem::AlternativeVote,
+                        Some(preference_flows),
+                    )
+                    .unwrap();
                     println!("Simulated Result: {:?}", simulated_result);
-                },
-                _ => unreachable!(),       
+                }
+                _ => unreachable!(),
             }
         }
         _ => unreachable!(),
     }
 }
 
+fn load_results(args: &[String]) {
+    let usage = "Usage: psephulator load <election json> [--format text|json]";
+    let options = Options::parse(args, usage, &["--format"], &[]);
+    let [election_path] = options.positional() else {
+        options.usage_error();
+    };
+    let format = options.format(
+        OutputFormat::Text,
+        &[OutputFormat::Text, OutputFormat::Json],
+    );
+    let (election_result, _) = load_or_exit(election_path);
+
+    let mut subdivisions: BTreeMap<&str, usize> = BTreeMap::new();
+    for constituency_result in &election_result.constituencies {
+        *subdivisions
+            .entry(&constituency_result.constituency.subdivision.name)
+            .or_default() += 1;
+    }
+    if format == OutputFormat::Json {
+        print_json(&json!({
+            "citation": election_result.citation(),
+            "schema_version": election_result.schema_version,
+            "constituencies": election_result.constituencies.len(),
+            "subdivisions": subdivisions,
+            "votes": sorted(&election_result.overall_result),
+            "metadata": election_result.metadata,
+        }));
+        return;
+    }
+
+    let total_votes: u32 = election_result.overall_result.values().sum();
+    println!("Loaded {}", election_result.citation());
+    println!(
+        "{} constituencies in {} subdivision(s), {} votes",
+        election_result.constituencies.len(),
+        subdivisions.len(),
+        total_votes
+    );
+    for (party, votes) in by_votes(&election_result) {
+        let share = votes as f64 / total_votes.max(1) as f64 * 100.0;
+        println!("{}: {} ({:.1}%)", party, votes, share);
+    }
+}
+
+fn simulate_results(args: &[String]) {
+    let usage = "Usage: psephulator simulate <election json> --system <system> [--flows <json>] [--format text|json|csv]";
+    let options = Options::parse(args, usage, &["--system", "--flows", "--format"], &[]);
+    let [election_path] = options.positional() else {
+        options.usage_error();
+    };
+    let Some(electoral_system) = options.value("--system").map(parse_electoral_system) else {
+        options.usage_error();
+    };
+    let flows_path = options.value("--flows");
+    let format = options.format(
+        OutputFormat::Text,
+        &[OutputFormat::Text, OutputFormat::Json, OutputFormat::Csv],
+    );
+
+    let (election_result, registry) = load_or_exit(election_path);
+    let preference_flows = electoral_system
+        .needs_preference_flows()
+        .then(|| read_preference_flows(flows_path, registry.as_ref()));
+    let seats = match simulate_election(&election_result, &electoral_system, preference_flows) {
+        Ok(seats) => seats,
+        Err(err) => {
+            eprintln!("{}: {}", election_path, err);
+            process::exit(1);
+        }
+    };
+    let indices = outcome_indices(&election_result.overall_result, &seats);
+    match format {
+        OutputFormat::Json => print_json(&json!({
+            "system": electoral_system,
+            "seats": sorted(&seats),
+            "indices": indices,
+        })),
+        OutputFormat::Csv => {
+            if let Err(err) = write_seats_csv(io::stdout(), &election_result, &seats) {
+                eprintln!("{}: {}", election_path, err);
+                process::exit(1);
+            }
+        }
+        OutputFormat::Text => {
+            let mut seats: Vec<(&String, &u32)> = seats.iter().collect();
+            seats.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+            for (party, party_seats) in seats {
+                println!("{}: {}", party, party_seats);
+            }
+            print_indices(&indices);
+        }
+    }
+}
+
+fn compare_results(args: &[String]) {
+    let usage = "Usage: psephulator compare <election json> [--systems fptp,av,...] [--flows <json>] [--format text|json|csv]";
+    let options = Options::parse(args, usage, &["--systems", "--flows", "--format"], &[]);
+    let [election_path] = options.positional() else {
+        options.usage_error();
+    };
+    let systems = match options.value("--systems") {
+        Some(systems) => systems.split(',').map(parse_electoral_system).collect(),
+        None => ElectoralSystem::ALL.to_vec(),
+    };
+    let flows_path = options.value("--flows");
+    let format = options.format(
+        OutputFormat::Text,
+        &[OutputFormat::Text, OutputFormat::Json, OutputFormat::Csv],
+    );
+
+    let (election_result, registry) = load_or_exit(election_path);
+    let preference_flows = systems
+        .iter()
+        .any(ElectoralSystem::needs_preference_flows)
+        .then(|| read_preference_flows(flows_path, registry.as_ref()));
+    let comparisons = match compare_systems(&election_result, &systems, preference_flows.as_ref()) {
+        Ok(comparisons) => comparisons,
+        Err(err) => {
+            eprintln!("{}: {}", election_path, err);
+            process::exit(1);
+        }
+    };
+
+    match format {
+        OutputFormat::Json => print_json(&json!(comparisons)),
+        OutputFormat::Csv => {
+            if let Err(err) = write_comparison_csv(io::stdout(), &comparisons) {
+                eprintln!("{}: {}", election_path, err);
+                process::exit(1);
+            }
+        }
+        OutputFormat::Text => {
+            println!(
+                "{:<12} {:>6} {:<12} {:<16} {:>9} {:>6} {:>6} {:>7} {:>6} {:>6} {:>7}",
+                "System",
+                "Seats",
+                "Largest",
+                "Majority",
+                "Gallagher",
+                "LH",
+                "SL",
+                "D'Hondt",
+                "ENPv",
+                "ENPs",
+                "Wasted"
+            );
+            for comparison in &comparisons {
+                let majority = match &comparison.majority_party {
+                    Some(party) => format!("{} ({})", party, comparison.majority),
+                    None => format!("hung ({})", comparison.majority),
+                };
+                let indices = &comparison.indices;
+                println!(
+                    "{:<12} {:>6} {:<12} {:<16} {:>9.2} {:>6.2} {:>6.2} {:>7.3} {:>6.2} {:>6.2} {:>6.1}%",
+                    comparison.system.name(),
+                    comparison.total_seats,
+                    comparison.largest_party.as_deref().unwrap_or("tie"),
+                    majority,
+                    indices.gallagher,
+                    indices.loosemore_hanby,
+                    indices.sainte_lague,
+                    indices.dhondt,
+                    indices.effective_parties_by_votes,
+                    indices.effective_parties_by_seats,
+                    indices.wasted_vote_share * 100.0
+                );
+            }
+
+            // Seats and seat shares against each party's vote share
+            println!();
+            print!("{:<12} {:>10} {:>6}", "Party", "Votes", "Vote %");
+            for comparison in &comparisons {
+                print!(" {:>12}", comparison.system.name());
+            }
+            println!();
+            let Some(first) = comparisons.first() else {
+                return;
+            };
+            for party in &first.parties {
+                print!(
+                    "{:<12} {:>10} {:>6.1}",
+                    party.party,
+                    party.votes,
+                    party.vote_share * 100.0
+                );
+                for comparison in &comparisons {
+                    let outcome = comparison.parties.iter().find(|p| p.party == party.party);
+                    let (seats, seat_share) = outcome.map_or((0, 0.0), |p| (p.seats, p.seat_share));
+                    print!(" {:>5} {:>5.1}%", seats, seat_share * 100.0);
+                }
+                println!();
+            }
+        }
+    }
+}
+
+fn wasted_votes(args: &[String]) {
+    let usage = "Usage: psephulator wasted <election json> [--system fptp|av] [--by-subdivision] [--flows <json>] [--format text|json|csv]";
+    let options = Options::parse(
+        args,
+        usage,
+        &["--system", "--flows", "--format"],
+        &["--by-subdivision"],
+    );
+    let [election_path] = options.positional() else {
+        options.usage_error();
+    };
+    let electoral_system = options
+        .value("--system")
+        .map_or(ElectoralSystem::FirstPastThePost, parse_electoral_system);
+    let by_subdivision = options.switch("--by-subdivision");
+    let flows_path = options.value("--flows");
+    let format = options.format(
+        OutputFormat::Text,
+        &[OutputFormat::Text, OutputFormat::Json, OutputFormat::Csv],
+    );
+
+    let (election_result, registry) = load_or_exit(election_path);
+    let preference_flows = electoral_system
+        .needs_preference_flows()
+        .then(|| read_preference_flows(flows_path, registry.as_ref()));
+    let analysis = match analyse_wasted_votes(
+        &election_result,
+        &electoral_system,
+        preference_flows.as_ref(),
+    ) {
+        Ok(analysis) => analysis,
+        Err(err) => {
+            eprintln!("{}: {}", election_path, err);
+            process::exit(1);
+        }
+    };
+
+    match format {
+        OutputFormat::Json => print_json(&json!(analysis)),
+        OutputFormat::Csv => {
+            if let Err(err) = write_wasted_votes_csv(io::stdout(), &analysis) {
+                eprintln!("{}: {}", election_path, err);
+                process::exit(1);
+            }
+        }
+        OutputFormat::Text => {
+            println!("Wasted votes under {}", analysis.system.name());
+            print_wasted_votes(&analysis.national);
+            if by_subdivision {
+                for (subdivision, parties) in &analysis.subdivisions {
+                    println!();
+                    println!("{}", subdivision);
+                    print_wasted_votes(parties);
+                }
+            }
+        }
+    }
+}
+
+fn print_wasted_votes(parties: &[PartyWastedVotes]) {
+    println!(
+        "{:<12} {:>10} {:>6} {:>10} {:>10} {:>8} {:>8} {:>14}",
+        "Party", "Votes", "Seats", "Lost", "Surplus", "Wasted", "Gap", "Votes per seat"
+    );
+    for party in parties {
+        let votes_per_seat = party
+            .votes_per_seat
+            .map_or("-".to_string(), |v| format!("{:.0}", v));
+        println!(
+            "{:<12} {:>10} {:>6} {:>10} {:>10} {:>7.1}% {:>+7.1}% {:>14}",
+            party.party,
+            party.votes,
+            party.seats,
+            party.lost_votes,
+            party.surplus_votes,
+            party.wasted_share * 100.0,
+            party.efficiency_gap * 100.0,
+            votes_per_seat
+        );
+    }
+}
+
+fn validate_results(args: &[String]) {
+    let usage = "Usage: psephulator validate <election json>... [--format text|json]";
+    let options = Options::parse(args, usage, &["--format"], &[]);
+    let paths = options.positional();
+    if paths.is_empty() {
+        options.usage_error();
+    }
+    let format = options.format(
+        OutputFormat::Text,
+        &[OutputFormat::Text, OutputFormat::Json],
+    );
+
+    let mut valid = true;
+    let mut reports = Vec::new();
+    for path in paths {
+        let issues = validate_election_file(path).unwrap_or_else(|err| {
+            vec![ValidationIssue {
+                severity: Severity::Error,
+                constituency: None,
+                message: format!("could not be read: {}", err),
+            }]
+        });
+        let file_valid = issues.iter().all(|issue| issue.severity < Severity::Error);
+        valid &= file_valid;
+        if format == OutputFormat::Json {
+            reports.push(json!({ "path": path, "valid": file_valid, "issues": issues }));
+            continue;
+        }
+        if issues.is_empty() {
+            println!("{}: ok", path);
+        }
+        for issue in issues {
+            let severity = match issue.severity {
+                Severity::Warning => "warning",
+                Severity::Error => "error",
+            };
+            match issue.constituency {
+                Some(constituency) => {
+                    println!(
+                        "{}: {}: {}: {}",
+                        path, severity, constituency, issue.message
+                    )
+                }
+                None => println!("{}: {}: {}", path, severity, issue.message),
+            }
+        }
+    }
+    if format == OutputFormat::Json {
+        print_json(&json!(reports));
+    }
+    if !valid {
+        process::exit(1);
+    }
+}
+
+fn export_results(args: &[String]) {
+    let usage = "Usage: psephulator export <election json> <output> [--format json|csv] [--layout wide|long]";
+    let options = Options::parse(args, usage, &["--format", "--layout"], &[]);
+    let [election_path, output_path] = options.positional() else {
+        options.usage_error();
+    };
+
+    // Without --format, a .csv output is CSV and anything else JSON
+    let format = options.format(
+        if output_path.ends_with(".csv") {
+            OutputFormat::Csv
+        } else {
+            OutputFormat::Json
+        },
+        &[OutputFormat::Json, OutputFormat::Csv],
+    );
+    let layout = options
+        .value("--layout")
+        .map_or(CsvLayout::Wide, parse_csv_layout);
+
+    let (election_result, _) = load_or_exit(election_path);
+    let exported = match format {
+        OutputFormat::Csv => File::create(output_path)
+            .map_err(Into::into)
+            .and_then(|file| write_election_csv(file, &election_result, layout)),
+        _ => save_election_data(output_path, &election_result),
+    };
+    match exported {
+        Ok(()) => println!("Exported {} to {}", election_path, output_path),
+        Err(err) => {
+            eprintln!("{}: {}", output_path, err);
+            process::exit(1);
+        }
+    }
+}
+
+fn migrate_files(args: &[String]) {
+    let options = Options::parse(
+        args,
+        "Usage: psephulator migrate <election file>...",
+        &[],
+        &[],
+    );
+    let paths = options.positional();
+    if paths.is_empty() {
+        options.usage_error();
+    }
+
+    let mut failed = false;
+    for path in paths {
+        match schema::migrate_file(path) {
+            Ok(version) if version == schema::CURRENT_SCHEMA_VERSION => {
+                println!("{}: already at schema version {}", path, version)
+            }
+            Ok(version) => println!(
+                "{}: migrated from schema version {} to {}",
+                path,
+                version,
+                schema::CURRENT_SCHEMA_VERSION
+            ),
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                failed = true;
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn import_hoc_results(args: &[String]) {
+    let usage =
+        "Usage: psephulator import-hoc <results csv> <election date YYYY-MM-DD> <output json>";
+    let options = Options::parse(args, usage, &[], &[]);
+    let [csv_path, election_date, output_path] = options.positional() else {
+        options.usage_error();
+    };
+
+    let datetime = parse_election_date(election_date);
+    let imported = import_hoc_csv(csv_path, datetime)
+        .and_then(|election_result| save_election_data(output_path, &election_result));
+    match imported {
+        Ok(()) => println!("Imported {} into {}", csv_path, output_path),
+        Err(err) => {
+            eprintln!("{}: {}", csv_path, err);
+            process::exit(1);
+        }
+    }
+}
+
+fn import_csv_results(args: &[String]) {
+    let usage = "Usage: psephulator import-csv <results csv> <column mapping json> <election date YYYY-MM-DD> <output json>";
+    let options = Options::parse(args, usage, &[], &[]);
+    let [csv_path, mapping_path, election_date, output_path] = options.positional() else {
+        options.usage_error();
+    };
+
+    let datetime = parse_election_date(election_date);
+    let imported = load_column_mapping(mapping_path)
+        .and_then(|mapping| import_election_csv(csv_path, &mapping, datetime))
+        .and_then(|election_result| save_election_data(output_path, &election_result));
+    match imported {
+        Ok(()) => println!("Imported {} into {}", csv_path, output_path),
+        Err(err) => {
+            eprintln!("{}: {}", csv_path, err);
+            process::exit(1);
+        }
+    }
+}
+
+// Kept for older scripts: the same as export --format csv --layout <layout>
+fn export_csv_results(args: &[String]) {
+    let usage = "Usage: psephulator export-csv <election json> <wide|long> <output csv>";
+    let options = Options::parse(args, usage, &[], &[]);
+    let [election_path, layout, output_path] = options.positional() else {
+        options.usage_error();
+    };
+
+    let args = [
+        election_path,
+        output_path,
+        "--format",
+        "csv",
+        "--layout",
+        layout,
+    ];
+    export_results(&args.map(|arg| arg.to_string()));
+}
+
+fn patch_results(args: &[String]) {
+    let options = Options::parse(
+        args,
+        "Usage: psephulator patch <election json> <patch json>...",
+        &[],
+        &[],
+    );
+    let [election_path, patch_paths @ ..] = options.positional() else {
+        options.usage_error();
+    };
+    if patch_paths.is_empty() {
+        options.usage_error();
+    }
+
+    let patched = load_election_data(election_path).and_then(|mut election_result| {
+        for patch_path in patch_paths {
+            apply_patch_path(&mut election_result, patch_path)?;
+        }
+        save_election_data(election_path, &election_result)
+    });
+    match patched {
+        Ok(()) => println!(
+            "Applied {} patch file(s) to {}",
+            patch_paths.len(),
+            election_path
+        ),
+        Err(err) => {
+            eprintln!("{}: {}", election_path, err);
+            process::exit(1);
+        }
+    }
+}
+
+fn project_results(args: &[String]) {
+    let usage = "Usage: psephulator project <election json> --system <system> [--swing [SUBDIVISION:]PARTY=points,...] [--model uniform|proportional|strong_transition|hybrid] [--pact <json>] [--tactical <json>] [--flows <json>] [--format text|json]\n       psephulator project <election json> --system <system> --transitions <matrix json> [--turnout fraction] [--pact <json>] [--tactical <json>] [--flows <json>] [--format text|json]\nThe system and swings may also be given without flags, as in `project <election json> fptp LAB=-4 REF=+5`.";
+    let options = Options::parse(
+        args,
+        usage,
+        &[
+            "--system",
+            "--swing",
+            "--model",
+            "--transitions",
+            "--pact",
+            "--tactical",
+            "--flows",
+            "--format",
+            "--turnout",
+        ],
+        &[],
+    );
+    let [election_path, rest @ ..] = options.positional() else {
+        options.usage_error();
+    };
+
+    let mut electoral_system = options.value("--system").map(parse_electoral_system);
+    let mut swing_args: Vec<&str> = options
+        .values("--swing")
+        .flat_map(|swings| swings.split(','))
+        .filter(|s| !s.is_empty())
+        .collect();
+    for &arg in rest {
+        if electoral_system.is_none() && ElectoralSystem::ALL.iter().any(|s| s.name() == arg) {
+            electoral_system = Some(parse_electoral_system(arg));
+        } else {
+            swing_args.push(arg);
+        }
+    }
+    let Some(electoral_system) = electoral_system else {
+        options.usage_error();
+    };
+    let model = options
+        .value("--model")
+        .map_or(SwingModel::Uniform, parse_swing_model);
+    let transitions_path = options.value("--transitions");
+    let pact_path = options.value("--pact");
+    let tactical_path = options.value("--tactical");
+    let flows_path = options.value("--flows");
+    let format = options.format(
+        OutputFormat::Text,
+        &[OutputFormat::Text, OutputFormat::Json],
+    );
+    let baseline_turnout = options
+        .number("--turnout")
+        .unwrap_or(DEFAULT_BASELINE_TURNOUT);
+    if transitions_path.is_some() && !swing_args.is_empty() {
+        options.fail("Give either swings or a transition matrix, not both");
+    }
+
+    let swings = parse_swings(&swing_args);
+
+    let (election_result, registry) = load_or_exit(election_path);
+    // Pacts redistribute the votes of parties that stand down by the flows
+    let preference_flows = (electoral_system.needs_preference_flows() || pact_path.is_some())
+        .then(|| read_preference_flows(flows_path, registry.as_ref()));
+    let project = || -> Result<_, Box<dyn std::error::Error>> {
+        let (projected, model) = match transitions_path {
+            Some(transitions_path) => (
+                apply_transitions(
+                    &election_result,
+                    &load_transition_matrix(transitions_path)?,
+                    baseline_turnout,
+                )?,
+                ProjectionModel::TransitionMatrix,
+            ),
+            None => (
+                apply_swing(&election_result, model, &swings)?,
+                ProjectionModel::Swing(model),
+            ),
+        };
+        let projected = match pact_path {
+            Some(pact_path) => {
+                let pact = load_pact(pact_path)?;
+                if format == OutputFormat::Text {
+                    println!("Pact: {}", pact.name);
+                    for withdrawal in &pact.withdrawals {
+                        let seats = withdrawn_seats(&projected, withdrawal)?;
+                        println!(
+                            "  {} stands down in {} seat(s)",
+                            withdrawal.party,
+                            seats.len()
+                        );
+                    }
+                }
+                apply_pact(&projected, &pact, preference_flows.as_ref())?
+            }
+            None => projected,
+        };
+        let projected = match tactical_path {
+            Some(tactical_path) => {
+                apply_tactical_voting(&projected, &load_tactical_voting(tactical_path)?)?
+            }
+            None => projected,
+        };
+        compare_with_baseline(
+            &election_result,
+            projected,
+            model,
+            &electoral_system,
+            preference_flows.as_ref(),
+        )
+    };
+    let projection = match project() {
+        Ok(projection) => projection,
+        Err(err) => {
+            eprintln!("{}: {}", election_path, err);
+            process::exit(1);
+        }
+    };
+
+    let indices = outcome_indices(&projection.result.overall_result, &projection.seats);
+    if format == OutputFormat::Json {
+        print_json(&json!({
+            "system": electoral_system,
+            "model": projection.model,
+            "tactical_voting": tactical_path.is_some(),
+            "baseline_seats": sorted(&projection.baseline_seats),
+            "seats": sorted(&projection.seats),
+            "changes": projection.changes,
+            "indices": indices,
+        }));
+        return;
+    }
+    match tactical_path {
+        Some(_) => println!(
+            "Projection model: {}, with tactical voting",
+            projection.model.name()
+        ),
+        None => println!("Projection model: {}", projection.model.name()),
+    }
+    let mut parties: Vec<&String> = projection
+        .seats
+        .keys()
+        .chain(projection.baseline_seats.keys())
+        .collect();
+    parties.sort();
+    parties.dedup();
+    for party in parties {
+        let before = projection.baseline_seats.get(party).copied().unwrap_or(0);
+        let after = projection.seats.get(party).copied().unwrap_or(0);
+        println!(
+            "{}: {} -> {} ({:+})",
+            party,
+            before,
+            after,
+            after as i64 - before as i64
+        );
+    }
+    println!("{} seat(s) changed hands", projection.changes.len());
+    for change in &projection.changes {
+        println!(
+            "  {}: {} -> {}",
+            change.constituency, change.from, change.to
+        );
+    }
+    print_indices(&indices);
+}
+
+fn diff_results(args: &[String]) {
+    let usage = "Usage: psephulator diff <election json> [<other election json>] [--before <system>] [--after <system>] [--flows <json>] [--format text|json|csv] [--table changes|flows|matrix]";
+    let options = Options::parse(
+        args,
+        usage,
+        &["--before", "--after", "--flows", "--format", "--table"],
+        &[],
+    );
+    let (before_path, after_path) = match options.positional() {
+        [before] => (before, before),
+        [before, after] => (before, after),
+        _ => options.usage_error(),
+    };
+    let before_system = options
+        .value("--before")
+        .map_or(ElectoralSystem::FirstPastThePost, parse_electoral_system);
+    let after_system = options
+        .value("--after")
+        .map_or(ElectoralSystem::FirstPastThePost, parse_electoral_system);
+    let flows_path = options.value("--flows");
+    let format = options.format(
+        OutputFormat::Text,
+        &[OutputFormat::Text, OutputFormat::Json, OutputFormat::Csv],
+    );
+    let table = match options.value("--table") {
+        None => "changes",
+        Some(table) if ["changes", "flows", "matrix"].contains(&table) => table,
+        Some(table) => options.fail(&format!(
+            "Unknown table '{}', expected changes, flows or matrix",
+            table
+        )),
+    };
+
+    let (before, before_registry) = load_or_exit(before_path);
+    let (after, after_registry) = if after_path == before_path {
+        (before.clone(), None)
+    } else {
+        load_or_exit(after_path)
+    };
+    let registry = before_registry.or(after_registry);
+    let preference_flows = (before_system.needs_preference_flows()
+        || after_system.needs_preference_flows())
+    .then(|| read_preference_flows(flows_path, registry.as_ref()));
+    let diff = match diff_outcomes(
+        &before,
+        &before_system,
+        &after,
+        &after_system,
+        preference_flows.as_ref(),
+    ) {
+        Ok(diff) => diff,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    match format {
+        OutputFormat::Json => print_json(&json!({
+            "before": { "election": before_path, "system": before_system },
+            "after": { "election": after_path, "system": after_system },
+            "diff": diff,
+        })),
+        OutputFormat::Csv => {
+            let written = match table {
+                "flows" => write_seat_flows_csv(io::stdout(), &diff),
+                "matrix" => write_seat_matrix_csv(io::stdout(), &diff),
+                _ => write_seat_changes_csv(io::stdout(), &diff),
+            };
+            if let Err(err) = written {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+        OutputFormat::Text => {
+            println!("{} seat(s) changed hands", diff.changes.len());
+            for flow in &diff.flows {
+                println!("  {} -> {}: {}", flow.from, flow.to, flow.seats);
+            }
+            println!("Net change:");
+            for (party, net) in &diff.net {
+                let (gains, losses) = diff.gains_and_losses(party);
+                if gains + losses == 0 {
+                    continue;
+                }
+                println!("  {}: {:+} (+{} -{})", party, net, gains, losses);
+            }
+            println!("Seats:");
+            for change in &diff.changes {
+                println!(
+                    "  {}: {} -> {}",
+                    change.constituency, change.from, change.to
+                );
+            }
+        }
+    }
+}
+
+fn marginal_seats(args: &[String]) {
+    let usage = "Usage: psephulator marginals <election json> [--party <party>] [--majority <seats>] [--limit <n>] [--format text|json|csv]";
+    let options = Options::parse(
+        args,
+        usage,
+        &["--party", "--majority", "--limit", "--format"],
+        &[],
+    );
+    let [election_path] = options.positional() else {
+        options.usage_error();
+    };
+    let party = options.value("--party");
+    let majority = options.number("--majority");
+    let limit = options.number("--limit");
+    let format = options.format(
+        OutputFormat::Text,
+        &[OutputFormat::Text, OutputFormat::Json, OutputFormat::Csv],
+    );
+    // Text is for reading, so it stops at 20 seats unless told otherwise
+    let limit = match (limit, format) {
+        (Some(n), _) => n,
+        (None, OutputFormat::Text) => 20,
+        (None, _) => usize::MAX,
+    };
+
+    let (election_result, _) = load_or_exit(election_path);
+    let mut seats = marginality(&election_result);
+    let Some(party) = party else {
+        seats.truncate(limit);
+        match format {
+            OutputFormat::Json => print_json(&json!(seats)),
+            OutputFormat::Csv => {
+                if let Err(err) = write_marginality_csv(io::stdout(), &seats) {
+                    eprintln!("{}: {}", election_path, err);
+                    process::exit(1);
+                }
+            }
+            OutputFormat::Text => {
+                println!(
+                    "{:<40} {:<6} {:<9} {:>8} {:>7}",
+                    "Constituency", "Winner", "Runner-up", "Majority", "Points"
+                );
+                for seat in &seats {
+                    println!(
+                        "{:<40} {:<6} {:<9} {:>8} {:>7.2}",
+                        seat.constituency,
+                        seat.winner,
+                        seat.runner_up.as_deref().unwrap_or("-"),
+                        seat.majority_votes,
+                        seat.majority_points
+                    );
+                }
+            }
+        }
+        return;
+    };
+
+    let mut list = match target_list(&seats, party, majority) {
+        Ok(list) => list,
+        Err(err) => {
+            eprintln!("{}: {}", election_path, err);
+            process::exit(1);
+        }
+    };
+    list.targets.truncate(limit);
+    list.defences.truncate(limit);
+    match format {
+        OutputFormat::Json => print_json(&json!(list)),
+        OutputFormat::Csv => {
+            if let Err(err) = write_target_list_csv(io::stdout(), &list) {
+                eprintln!("{}: {}", election_path, err);
+                process::exit(1);
+            }
+        }
+        OutputFormat::Text => {
+            println!(
+                "{} holds {} seat(s); a majority is {}",
+                list.party, list.seats, list.majority
+            );
+            match list.swing_for_majority {
+                Some(swing) => println!(
+                    "It needs {} more, the last of them on a {:.2} point swing",
+                    list.seats_needed, swing
+                ),
+                None if list.seats_needed == 0 => println!("It has a majority"),
+                None => println!("It can't reach a majority from the seats it contested"),
+            }
+            println!();
+            println!("Targets:");
+            for (i, target) in list.targets.iter().enumerate() {
+                println!(
+                    "{:>4}. {:<40} from {:<6} {:>5.2} points ({} seats)",
+                    i + 1,
+                    target.constituency,
+                    target.held_by,
+                    target.swing_needed,
+                    target.seats_if_won
+                );
+            }
+            println!();
+            println!("Defences:");
+            for (i, defence) in list.defences.iter().enumerate() {
+                println!(
+                    "{:>4}. {:<40} to {:<6} {:>5.2} points ({} seats)",
+                    i + 1,
+                    defence.constituency,
+                    defence.challenger.as_deref().unwrap_or("-"),
+                    defence.swing_needed,
+                    defence.seats_if_lost
+                );
+            }
+        }
+    }
+}
+
+fn seats_votes(args: &[String]) {
+    let usage = "Usage: psephulator seats-votes <election json> [--party <party>] [--system fptp|av] [--model uniform|proportional|strong_transition|hybrid] [--majority <seats> | --abstaining PARTY,...] [--from <points>] [--to <points>] [--step <points>] [--flows <json>] [--format text|json|csv]";
+    let options = Options::parse(
+        args,
+        usage,
+        &[
+            "--party",
+            "--system",
+            "--model",
+            "--majority",
+            "--abstaining",
+            "--from",
+            "--to",
+            "--step",
+            "--flows",
+            "--format",
+        ],
+        &[],
+    );
+    let [election_path] = options.positional() else {
+        options.usage_error();
+    };
+    let party = options.value("--party");
+    let electoral_system = options
+        .value("--system")
+        .map_or(ElectoralSystem::FirstPastThePost, parse_electoral_system);
+    let model = options
+        .value("--model")
+        .map_or(SwingModel::Uniform, parse_swing_model);
+    let majority = match (options.number("--majority"), options.value("--abstaining")) {
+        (Some(0), _) => options.fail("--majority needs at least one seat"),
+        (Some(seats), None) => Some(SeatTarget::Seats(seats)),
+        (None, Some(parties)) => Some(SeatTarget::WorkingMajority(
+            parties.split(',').map(str::to_string).collect(),
+        )),
+        (None, None) => None,
+        (Some(_), Some(_)) => options.fail("Give either --majority or --abstaining, not both"),
+    };
+    let from = options.number("--from").unwrap_or(-10.0);
+    let to = options.number("--to").unwrap_or(10.0);
+    let step = options.number("--step").unwrap_or(1.0);
+    let flows_path = options.value("--flows");
+    let format = options.format(
+        OutputFormat::Text,
+        &[OutputFormat::Text, OutputFormat::Json, OutputFormat::Csv],
+    );
+
+    let (election_result, registry) = load_or_exit(election_path);
+    let majority = majority.unwrap_or_else(|| default_majority(&election_result));
+    let preference_flows = electoral_system
+        .needs_preference_flows()
+        .then(|| read_preference_flows(flows_path, registry.as_ref()));
+    let fail = |err: Box<dyn std::error::Error>| -> ! {
+        eprintln!("{}: {}", election_path, err);
+        process::exit(1);
+    };
+    let calculator = SwingCalculator::new(
+        &election_result,
+        &electoral_system,
+        preference_flows.as_ref(),
+        model,
+    )
+    .unwrap_or_else(|err| fail(err));
+
+    // Every party that stood, or just the one asked about
+    let parties: Vec<String> = match party {
+        Some(party) => vec![party.to_string()],
+        None => by_votes(&election_result)
+            .into_iter()
+            .filter(|(_, votes)| *votes > 0)
+            .map(|(party, _)| party.to_string())
+            .collect(),
+    };
+    let thresholds: Vec<_> = parties
+        .iter()
+        .map(|party| {
+            calculator
+                .thresholds(party, &majority)
+                .unwrap_or_else(|err| fail(err))
+        })
+        .collect();
+    let curve = match party {
+        Some(party) => calculator
+            .seats_votes_curve(party, from, to, step, &majority)
+            .unwrap_or_else(|err| fail(err)),
+        None => Vec::new(),
+    };
+
+    let points = |swing: Option<f64>| swing.map_or("-".to_string(), |s| format!("{:+.2}", s));
+    match format {
+        OutputFormat::Json => print_json(&json!({
+            "system": electoral_system,
+            "model": model,
+            "majority": majority,
+            "thresholds": thresholds,
+            "curve": party.map(|_| &curve),
+        })),
+        OutputFormat::Csv => {
+            let written = match party {
+                Some(party) => write_curve_csv(io::stdout(), party, &curve),
+                None => write_thresholds_csv(io::stdout(), &thresholds),
+            };
+            if let Err(err) = written {
+                fail(err);
+            }
+        }
+        OutputFormat::Text => {
+            println!(
+                "Swing needed under {} ({} swing)",
+                electoral_system.name(),
+                model.name()
+            );
+            println!(
+                "{:<12} {:>7} {:>6} {:>14} {:>14}",
+                "Party", "Vote %", "Seats", "Largest party", "Majority"
+            );
+            for party in &thresholds {
+                println!(
+                    "{:<12} {:>7.1} {:>6} {:>14} {:>14}",
+                    party.party,
+                    party.vote_share,
+                    party.seats,
+                    points(party.largest_party_swing),
+                    points(party.majority_swing)
+                );
+            }
+            if !curve.is_empty() {
+                println!();
+                println!(
+                    "{:>7} {:>7} {:>6} {:>7}",
+                    "Swing", "Vote %", "Seats", "Seat %"
+                );
+                for point in &curve {
+                    let status = match (point.majority, point.largest_party) {
+                        (true, _) => "majority",
+                        (false, true) => "largest party",
+                        _ => "",
+                    };
+                    println!(
+                        "{:>+7.1} {:>7.1} {:>6} {:>6.1}% {}",
+                        point.swing,
+                        point.vote_share,
+                        point.seats,
+                        point.seat_share * 100.0,
+                        status
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn nowcast_results(args: &[String]) {
+    let usage = "Usage: psephulator nowcast <election json> <system> <polls csv> [--model uniform|proportional|strong_transition|hybrid] [--as-of YYYY-MM-DD] [--history <jsonl file>] [--flows <json>] [--format text|json]";
+    let options = Options::parse(
+        args,
+        usage,
+        &["--model", "--as-of", "--history", "--flows", "--format"],
+        &[],
+    );
+    let [election_path, system, polls_path] = options.positional() else {
+        options.usage_error();
+    };
+
+    let electoral_system = parse_electoral_system(system);
+    let model = options
+        .value("--model")
+        .map_or(SwingModel::Uniform, parse_swing_model);
+    let as_of = options
+        .value("--as-of")
+        .map(|as_of| parse_election_date(as_of).date_naive());
+    let history_path = options.value("--history");
+    let flows_path = options.value("--flows");
+    let format = options.format(
+        OutputFormat::Text,
+        &[OutputFormat::Text, OutputFormat::Json],
+    );
+
+    let (baseline, registry) = load_or_exit(election_path);
+    let preference_flows = electoral_system
+        .needs_preference_flows()
+        .then(|| read_preference_flows(flows_path, registry.as_ref()));
+    let nowcast = load_polls(polls_path, registry.as_ref()).and_then(|polls| {
+        // Default to the day the latest poll finished fieldwork
+        let as_of = as_of
+            .or_else(|| polls.iter().map(|p| p.fieldwork_end).max())
+            .ok_or("polls file has no polls")?;
+        let nowcast = nowcast(
+            &baseline,
+            &polls,
+            as_of,
+            &PollAverageSettings::default(),
+            model,
+            &electoral_system,
+            preference_flows.as_ref(),
+        )?;
+        if let Some(history_path) = history_path {
+            append_nowcast(history_path, &nowcast)?;
+        }
+        Ok(nowcast)
+    });
+    let nowcast = match nowcast {
+        Ok(nowcast) => nowcast,
+        Err(err) => {
+            eprintln!("{}: {}", polls_path, err);
+            process::exit(1);
+        }
+    };
+
+    if format == OutputFormat::Json {
+        print_json(&json!(nowcast));
+        return;
+    }
+    println!(
+        "Nowcast as of {} from {} poll(s), {} swing",
+        nowcast.as_of,
+        nowcast.polls_used,
+        nowcast.model.name()
+    );
+    for (party, seats) in &nowcast.seats {
+        let share = nowcast.national.get(party).map(|s| format!(" ({:.1}%)", s));
+        println!("{}: {}{}", party, seats, share.unwrap_or_default());
+    }
+    print_indices(&nowcast.indices);
+}
+
+fn parse_swings(swing_args: &[&str]) -> Swings {
+    let mut swings = Swings::default();
+    for swing in swing_args {
+        // Scotland:SNP=+3 only applies in Scotland
+        let (subdivision, party_swing) = match swing.split_once(':') {
+            Some((subdivision, party_swing)) => (Some(subdivision), party_swing),
+            None => (None, *swing),
+        };
+        let (party, points) = match party_swing
+            .split_once('=')
+            .map(|(p, s)| (p, s.parse::<f64>()))
+        {
+            Some((party, Ok(points))) => (party.to_string(), points),
+            _ => {
+                eprintln!(
+                    "{}: expected a swing like LAB=-4, REF=+5 or Scotland:SNP=+3",
+                    swing
+                );
+                process::exit(2);
+            }
+        };
+        match subdivision {
+            Some(subdivision) => swings
+                .regional
+                .entry(subdivision.to_string())
+                .or_default()
+                .insert(party, points),
+            None => swings.national.insert(party, points),
+        };
+    }
+    swings
+}
+
+fn parse_swing_model(model: &str) -> SwingModel {
+    match SwingModel::ALL.iter().find(|m| m.name() == model) {
+        Some(&swing_model) => swing_model,
+        None => {
+            let names: Vec<&str> = SwingModel::ALL.iter().map(SwingModel::name).collect();
+            eprintln!(
+                "Unknown swing model '{}', expected one of {}",
+                model,
+                names.join(", ")
+            );
+            process::exit(2);
+        }
+    }
+}
+
+fn montecarlo_results(args: &[String]) {
+    let usage = "Usage: psephulator montecarlo <election json> <system> [--runs N] [--seed N] [--sd points | --dirichlet concentration] [--correlate PARTY:PARTY=r]... [--regional-sd points] [--constituency-sd points] [--majority seats] [--model uniform|proportional|strong_transition|hybrid] [--flows <json>] [--output <json>] [--format text|json] [--swing [SUBDIVISION:]PARTY=points,...] [SUBDIVISION:]PARTY=points...";
+    let options = Options::parse(
+        args,
+        usage,
+        &[
+            "--runs",
+            "--seed",
+            "--sd",
+            "--dirichlet",
+            "--correlate",
+            "--regional-sd",
+            "--constituency-sd",
+            "--majority",
+            "--model",
+            "--flows",
+            "--output",
+            "--format",
+            "--swing",
+        ],
+        &[],
+    );
+    let [election_path, system, rest @ ..] = options.positional() else {
+        options.usage_error();
+    };
+
+    let electoral_system = parse_electoral_system(system);
+    let mut settings = MonteCarloSettings::default();
+    if let Some(runs) = options.number("--runs") {
+        settings.runs = runs;
+    }
+    if let Some(seed) = options.number("--seed") {
+        settings.seed = seed;
+    }
+    match (options.number("--sd"), options.number("--dirichlet")) {
+        (Some(_), Some(_)) => options.fail("Give either --sd or --dirichlet, not both"),
+        (Some(sd), None) => {
+            settings.national_error = NationalError::Normal {
+                sd,
+                correlations: Vec::new(),
+            }
+        }
+        (None, Some(concentration)) => {
+            settings.national_error = NationalError::Dirichlet { concentration }
+        }
+        (None, None) => {}
+    }
+    if let Some(regional_sd) = options.number("--regional-sd") {
+        settings.regional_sd = regional_sd;
+    }
+    if let Some(constituency_sd) = options.number("--constituency-sd") {
+        settings.constituency_sd = constituency_sd;
+    }
+    settings.majority = options.number("--majority");
+    let mut correlations: Vec<PartyCorrelation> = options
+        .values("--correlate")
+        .map(|value| {
+            let Some(((a, b), correlation)) = value
+                .split_once('=')
+                .and_then(|(parties, r)| Some((parties.split_once(':')?, r)))
+            else {
+                options.fail(&format!("{}: expected PARTY:PARTY=r", value));
+            };
+            PartyCorrelation {
+                parties: (a.trim().to_string(), b.trim().to_string()),
+                correlation: options.parse_number("--correlate", correlation),
+            }
+        })
+        .collect();
+    let model = options
+        .value("--model")
+        .map_or(SwingModel::Uniform, parse_swing_model);
+    let output_path = options.value("--output");
+    let flows_path = options.value("--flows");
+    let format = options.format(
+        OutputFormat::Text,
+        &[OutputFormat::Text, OutputFormat::Json],
+    );
+    let swing_args: Vec<&str> = options
+        .values("--swing")
+        .flat_map(|swings| swings.split(','))
+        .filter(|s| !s.is_empty())
+        .chain(rest.iter().copied())
+        .collect();
+    let swings = parse_swings(&swing_args);
+
+    let (baseline, registry) = load_or_exit(election_path);
+    if !correlations.is_empty() {
+        let NationalError::Normal {
+            correlations: national_correlations,
+            ..
+        } = &mut settings.national_error
+        else {
+            options.fail("--correlate needs normal errors, not --dirichlet");
+        };
+        for correlation in &mut correlations {
+            if let Some(registry) = &registry {
+                let (a, b) = &mut correlation.parties;
+                *a = registry.normalise(a);
+                *b = registry.normalise(b);
+            }
+        }
+        *national_correlations = correlations;
+    }
+    let preference_flows = electoral_system
+        .needs_preference_flows()
+        .then(|| read_preference_flows(flows_path, registry.as_ref()));
+    let result = apply_swing(&baseline, model, &swings).and_then(|projected| {
+        let result = run_monte_carlo(
+            &projected,
+            &electoral_system,
+            preference_flows.as_ref(),
+            &settings,
+        )?;
+        if let Some(output_path) = output_path {
+            fs::write(output_path, serde_json::to_string_pretty(&result)?)?;
+        }
+        Ok(result)
+    });
+    let result = match result {
+        Ok(result) => result,
+        Err(err) => {
+            eprintln!("{}: {}", election_path, err);
+            process::exit(1);
+        }
+    };
+
+    if format == OutputFormat::Json {
+        print_json(&json!(result));
+        return;
+    }
+    println!(
+        "{} runs (seed {}), {} seats for a majority",
+        result.runs, result.seed, result.majority
+    );
+    for (party, seats) in &result.seats {
+        println!(
+            "{}: mean {:.1}, 5% {}, median {}, 95% {}, majority {:.1}%, largest party {:.1}%",
+            party,
+            seats.mean,
+            seats.p5,
+            seats.p50,
+            seats.p95,
+            seats.majority_probability * 100.0,
+            seats.largest_party_probability * 100.0
+        );
+    }
+    println!("Hung parliament: {:.1}%", result.hung_probability * 100.0);
+    println!("Mean over the runs:");
+    print_indices(&result.indices);
+}
+
+fn run_scenarios(args: &[String]) {
+    let options = Options::parse(args, "Usage: psephulator run <scenario json>...", &[], &[]);
+    let paths = options.positional();
+    if paths.is_empty() {
+        options.usage_error();
+    }
+
+    let mut failed = false;
+    for path in paths {
+        let run = load_scenario(path).and_then(|scenario| {
+            let report = run_scenario(&scenario)?;
+            write_scenario_outputs(&scenario, &report)?;
+            Ok((scenario, report))
+        });
+        let (scenario, report) = match run {
+            Ok(run) => run,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                failed = true;
+                continue;
+            }
+        };
+
+        let summary = scenario.outputs.is_empty()
+            || scenario
+                .outputs
+                .iter()
+                .any(|output| matches!(output, ScenarioOutput::Summary));
+        if !summary {
+            continue;
+        }
+        println!("Scenario: {}", report.name);
+        if let Some(model) = report.model {
+            println!("Projection model: {}", model.name());
+        }
+        for outcome in &report.outcomes {
+            println!("{}:", outcome.system.name());
+            let mut parties: Vec<&String> = outcome
+                .baseline_seats
+                .keys()
+                .chain(outcome.seats.keys())
+                .collect();
+            parties.sort();
+            parties.dedup();
+            for party in parties {
+                let before = outcome.baseline_seats.get(party).copied().unwrap_or(0);
+                let after = outcome.seats.get(party).copied().unwrap_or(0);
+                let change = after as i64 - before as i64;
+                println!("  {}: {} -> {} ({:+})", party, before, after, change);
+            }
+            println!("  {} seat(s) changed hands", outcome.changes.len());
+            println!(
+                "  Gallagher index {:.2}, {:.2} effective parties by seats",
+                outcome.indices.gallagher, outcome.indices.effective_parties_by_seats
+            );
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+impl OutputFormat {
+    fn name(&self) -> &'static str {
+        match self {
+            OutputFormat::Text => "text",
+            OutputFormat::Json => "json",
+            OutputFormat::Csv => "csv",
+        }
+    }
+}
+
+// A command's arguments, split into positional arguments, `--flag value`
+// options and bare `--switch`es. Anything the command doesn't take, a flag
+// without its value or a value that doesn't parse is a usage error.
+struct Options<'a> {
+    usage: &'static str,
+    positional: Vec<&'a str>,
+    values: Vec<(&'a str, &'a str)>,
+    switches: Vec<&'a str>,
+}
+
+impl<'a> Options<'a> {
+    fn parse(
+        args: &'a [String],
+        usage: &'static str,
+        flags: &[&str],
+        switches: &[&str],
+    ) -> Options<'a> {
+        let mut options = Options {
+            usage,
+            positional: Vec::new(),
+            values: Vec::new(),
+            switches: Vec::new(),
+        };
+        let mut args = args.iter().map(String::as_str);
+        while let Some(arg) = args.next() {
+            if switches.contains(&arg) {
+                options.switches.push(arg);
+            } else if flags.contains(&arg) {
+                let Some(value) = args.next() else {
+                    options.fail(&format!("{} needs a value", arg));
+                };
+                options.values.push((arg, value));
+            } else if arg.starts_with("--") {
+                options.fail(&format!("Unknown option '{}'", arg));
+            } else {
+                options.positional.push(arg);
+            }
+        }
+        options
+    }
+
+    fn positional(&self) -> &[&'a str] {
+        &self.positional
+    }
+
+    // The last value given for a flag
+    fn value(&self, flag: &str) -> Option<&'a str> {
+        self.values(flag).last()
+    }
+
+    // Every value given for a repeatable flag, in order
+    fn values<'s>(&'s self, flag: &'s str) -> impl Iterator<Item = &'a str> + 's {
+        self.values
+            .iter()
+            .filter(move |&&(f, _)| f == flag)
+            .map(|&(_, value)| value)
+    }
+
+    fn switch(&self, switch: &str) -> bool {
+        self.switches.contains(&switch)
+    }
+
+    fn number<T: FromStr>(&self, flag: &str) -> Option<T> {
+        self.value(flag).map(|value| self.parse_number(flag, value))
+    }
+
+    fn parse_number<T: FromStr>(&self, flag: &str, value: &str) -> T {
+        value
+            .parse()
+            .unwrap_or_else(|_| self.fail(&format!("Invalid value '{}' for {}", value, flag)))
+    }
+
+    fn format(&self, default: OutputFormat, supported: &[OutputFormat]) -> OutputFormat {
+        self.value("--format")
+            .map_or(default, |format| parse_output_format(format, supported))
+    }
+
+    fn fail(&self, message: &str) -> ! {
+        eprintln!("{}\n{}", message, self.usage);
+        process::exit(2);
+    }
+
+    fn usage_error(&self) -> ! {
+        eprintln!("{}", self.usage);
+        process::exit(2);
+    }
+}
+
+fn parse_output_format(format: &str, supported: &[OutputFormat]) -> OutputFormat {
+    match supported.iter().find(|f| f.name() == format) {
+        Some(&format) => format,
+        None => {
+            let names: Vec<&str> = supported.iter().map(OutputFormat::name).collect();
+            eprintln!(
+                "Unknown output format '{}', expected {}",
+                format,
+                names.join(" or ")
+            );
+            process::exit(2);
+        }
+    }
+}
+
+fn parse_csv_layout(layout: &str) -> CsvLayout {
+    match layout {
+        "wide" => CsvLayout::Wide,
+        "long" => CsvLayout::Long,
+        _ => {
+            eprintln!("Unknown CSV layout '{}', expected wide or long", layout);
+            process::exit(2);
+        }
+    }
+}
+
+// Loads an election with the registry to normalise its flows and polls by
+fn load_or_exit(election_path: &str) -> (ElectionResult, Option<PartyRegistry>) {
+    load_election(election_path).unwrap_or_else(|err| {
+        eprintln!("{}: {}", election_path, err);
+        process::exit(1);
+    })
+}
+
+fn print_indices(indices: &OutcomeIndices) {
+    println!(
+        "Disproportionality: Gallagher {:.2}, Loosemore-Hanby {:.2}, Sainte-Lague {:.2}, D'Hondt {:.3}",
+        indices.gallagher, indices.loosemore_hanby, indices.sainte_lague, indices.dhondt
+    );
+    println!(
+        "Effective parties: {:.2} by votes, {:.2} by seats (Golosov {:.2} and {:.2})",
+        indices.effective_parties_by_votes,
+        indices.effective_parties_by_seats,
+        indices.golosov_by_votes,
+        indices.golosov_by_seats
+    );
+    println!(
+        "Votes for parties winning no seats: {:.1}%",
+        indices.wasted_vote_share * 100.0
+    );
+}
+
+fn print_json(value: &serde_json::Value) {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).expect("JSON values always serialise")
+    );
+}
+
+// Seat or vote counts in a stable order for output
+fn sorted(counts: &HashMap<String, u32>) -> BTreeMap<&String, u32> {
+    counts
+        .iter()
+        .map(|(party, &count)| (party, count))
+        .collect()
+}
+
+// Parties by national vote, largest first
+fn by_votes(election_result: &ElectionResult) -> Vec<(&String, u32)> {
+    let mut parties: Vec<(&String, u32)> = election_result
+        .overall_result
+        .iter()
+        .map(|(party, &votes)| (party, votes))
+        .collect();
+    parties.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    parties
+}
+
+fn parse_electoral_system(system: &str) -> ElectoralSystem {
+    match ElectoralSystem::ALL.iter().find(|s| s.name() == system) {
+        Some(electoral_system) => electoral_system.clone(),
+        None => {
+            let names: Vec<&str> = ElectoralSystem::ALL.iter().map(|s| s.name()).collect();
+            eprintln!(
+                "Unknown electoral system '{}', expected one of {}",
+                system,
+                names.join(", ")
+            );
+            process::exit(2);
+        }
+    }
+}
+
+fn parse_election_date(election_date: &str) -> DateTime<Utc> {
+    match NaiveDate::parse_from_str(election_date, "%Y-%m-%d") {
+        Ok(date) => date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+        Err(err) => {
+            eprintln!("{}: {}", election_date, err);
+            process::exit(2);
+        }
+    }
+}
+
 fn select_preference_flows_file() -> String {
     let options = &["preference_flows_england.json"]; // Add more files as needed
     let selection = Select::with_theme(&ColorfulTheme::default())
@@ -300,13 +1845,18 @@ fn select_preference_flows_file() -> String {
         .interact()
         .unwrap();
 
-    options[selection].to_string()
+    format!("data/{}", options[selection])
 }
 
-fn load_preference_flows(file_name: &str) -> HashMap<String, HashMap<String, f32>> {
-    let file_path = format!("data/{}", file_name); // Adjust the path as necessary
-    let file_content = fs::read_to_string(&file_path).expect("Unable to read file");
-    serde_json::from_str(&file_content).expect("JSON was not well-formatted")
+fn read_preference_flows(
+    path: Option<&str>,
+    registry: Option<&PartyRegistry>,
+) -> HashMap<String, HashMap<String, f32>> {
+    let path = path.unwrap_or(DEFAULT_PREFERENCE_FLOWS);
+    load_preference_flows(path, registry).unwrap_or_else(|err| {
+        eprintln!("{}: {}", path, err);
+        process::exit(1);
+    })
 }
 
 fn setup_two_party_fptp_election() -> ElectionResult {
@@ -328,6 +1878,9 @@ fn setup_two_party_fptp_election() -> ElectionResult {
 
     let constituency = Constituency {
         name: String::from("Constituency 1"),
+        subdivision: Subdivision {
+            name: String::from("Subdivision 1"),
+        },
         candidates: vec![candidate1, candidate2],
     };
 
@@ -339,32 +1892,12 @@ fn setup_two_party_fptp_election() -> ElectionResult {
             .collect(),
     };
 
-    ElectionResult {
-        datetime: Utc::now(),
-        constituencies: vec![constituency_result],
-        overall_result: [(party1.name.clone(), 2), (party2.name.clone(), 1)]
+    ElectionResult::new(
+        Utc::now(),
+        vec![constituency_result],
+        [(party1.name.clone(), 2), (party2.name.clone(), 1)]
             .iter()
             .cloned()
             .collect(),
-    }
-}
-
-fn get_preference_flows(election_result: &ElectionResult) -> HashMap<String, HashMap<String, f32>> {
-    let mut preference_flows = HashMap::new();
-
-    for constituency_result in &election_result.constituencies {
-        for candidate in &constituency_result.constituency.candidates {
-            if !preference_flows.contains_key(&candidate.party.name) {
-                let mut flow = HashMap::new();
-                for other_candidate in &constituency_result.constituency.candidates {
-                    if candidate.party.name != other_candidate.party.name {
-                        flow.insert(other_candidate.party.name.clone(), 0.5);
-                    }
-                }
-                preference_flows.insert(candidate.party.name.clone(), flow);
-            }
-        }
-    }
-
-    preference_flows
+    )
 }
@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::Write;
+
+use crate::ElectionResult;
+
+// A party that didn't win a seat, and what it would take to win it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Challenger {
+    pub party: String,
+    pub votes: u32,
+    pub votes_behind: u32,
+    pub points_behind: f64,
+    // The two-party swing from the winner that would tie the seat: half the
+    // gap in percentage points
+    pub swing_needed: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeatMarginality {
+    pub constituency: String,
+    pub subdivision: String,
+    pub winner: String,
+    pub winner_votes: u32,
+    pub runner_up: Option<String>,
+    pub majority_votes: u32,
+    pub majority_points: f64,
+    pub challengers: Vec<Challenger>, // By votes, largest first
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TargetSeat {
+    pub constituency: String,
+    pub subdivision: String,
+    pub held_by: String,
+    pub votes_behind: u32,
+    pub swing_needed: f64,
+    pub seats_if_won: u32, // The party's seats if it won this and every seat above it
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DefenceSeat {
+    pub constituency: String,
+    pub subdivision: String,
+    pub challenger: Option<String>,
+    pub majority_votes: u32,
+    pub swing_needed: f64,  // To lose the seat to the challenger
+    pub seats_if_lost: u32, // The party's seats if it lost this and every seat above it
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TargetList {
+    pub party: String,
+    pub seats: u32,
+    pub majority: u32,
+    pub seats_needed: u32, // For a majority; zero if the party has one
+    // The swing needed to win the seat that would give the party a majority
+    pub swing_for_majority: Option<f64>,
+    pub targets: Vec<TargetSeat>,   // Easiest first
+    pub defences: Vec<DefenceSeat>, // Most vulnerable first
+}
+
+/// Ranks each constituency's parties by votes under FPTP, giving the winner's
+/// majority over the runner-up and the swing each challenger needs. Seats are
+/// returned most marginal first; constituencies without votes are left out.
+pub fn marginality(election_result: &ElectionResult) -> Vec<SeatMarginality> {
+    let mut seats: Vec<SeatMarginality> = election_result
+        .constituencies
+        .iter()
+        .filter_map(|constituency_result| {
+            let total: u32 = constituency_result.results.values().sum();
+            if total == 0 {
+                return None;
+            }
+            // Parties listed with no votes didn't stand, so aren't challengers
+            let mut parties: Vec<(&String, u32)> = constituency_result
+                .results
+                .iter()
+                .filter(|(_, &votes)| votes > 0)
+                .map(|(party, &votes)| (party, votes))
+                .collect();
+            parties.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+            let (winner, winner_votes) = parties[0];
+            let points = |votes: u32| votes as f64 / total as f64 * 100.0;
+
+            let challengers: Vec<Challenger> = parties[1..]
+                .iter()
+                .map(|&(party, votes)| {
+                    let points_behind = points(winner_votes - votes);
+                    Challenger {
+                        party: party.clone(),
+                        votes,
+                        votes_behind: winner_votes - votes,
+                        points_behind,
+                        swing_needed: points_behind / 2.0,
+                    }
+                })
+                .collect();
+            let runner_up = challengers.first();
+            Some(SeatMarginality {
+                constituency: constituency_result.constituency.name.clone(),
+                subdivision: constituency_result.constituency.subdivision.name.clone(),
+                winner: winner.clone(),
+                winner_votes,
+                runner_up: runner_up.map(|c| c.party.clone()),
+                majority_votes: runner_up.map_or(winner_votes, |c| c.votes_behind),
+                majority_points: runner_up.map_or(100.0, |c| c.points_behind),
+                challengers,
+            })
+        })
+        .collect();
+    seats.sort_by(|a, b| {
+        a.majority_points
+            .total_cmp(&b.majority_points)
+            .then(a.constituency.cmp(&b.constituency))
+    });
+    seats
+}
+
+/// Builds `party`'s target list (the seats it would gain, by the swing it
+/// needs from each seat's winner) and defence list (its own seats, by the
+/// swing that would lose them to their runner-up). `majority` is the number
+/// of seats that counts as a majority, more than half by default.
+pub fn target_list(
+    seats: &[SeatMarginality],
+    party: &str,
+    majority: Option<u32>,
+) -> Result<TargetList, Box<dyn Error>> {
+    let stood = seats.iter().any(|s| {
+        s.winner == party
+            || s.challengers
+                .iter()
+                .any(|c| c.party == party && c.votes > 0)
+    });
+    if !stood {
+        return Err(format!("{} didn't stand in this election", party).into());
+    }
+    let majority = majority.unwrap_or(seats.len() as u32 / 2 + 1);
+
+    let mut held: Vec<&SeatMarginality> = seats.iter().filter(|s| s.winner == party).collect();
+    held.sort_by(|a, b| a.majority_points.total_cmp(&b.majority_points));
+    let won = held.len() as u32;
+
+    let mut targets: Vec<(&SeatMarginality, &Challenger)> = seats
+        .iter()
+        .filter_map(|seat| {
+            let challenger = seat.challengers.iter().find(|c| c.party == party)?;
+            Some((seat, challenger))
+        })
+        .collect();
+    targets.sort_by(|a, b| {
+        a.1.swing_needed
+            .total_cmp(&b.1.swing_needed)
+            .then(a.0.constituency.cmp(&b.0.constituency))
+    });
+    let targets: Vec<TargetSeat> = targets
+        .into_iter()
+        .enumerate()
+        .map(|(i, (seat, challenger))| TargetSeat {
+            constituency: seat.constituency.clone(),
+            subdivision: seat.subdivision.clone(),
+            held_by: seat.winner.clone(),
+            votes_behind: challenger.votes_behind,
+            swing_needed: challenger.swing_needed,
+            seats_if_won: won + i as u32 + 1,
+        })
+        .collect();
+
+    let defences = held
+        .into_iter()
+        .enumerate()
+        .map(|(i, seat)| DefenceSeat {
+            constituency: seat.constituency.clone(),
+            subdivision: seat.subdivision.clone(),
+            challenger: seat.runner_up.clone(),
+            majority_votes: seat.majority_votes,
+            swing_needed: seat.majority_points / 2.0,
+            seats_if_lost: won - i as u32 - 1,
+        })
+        .collect();
+
+    let seats_needed = majority.saturating_sub(won);
+    let swing_for_majority = match seats_needed {
+        0 => None,
+        n => targets.get(n as usize - 1).map(|t| t.swing_needed),
+    };
+    Ok(TargetList {
+        party: party.to_string(),
+        seats: won,
+        majority,
+        seats_needed,
+        swing_for_majority,
+        targets,
+        defences,
+    })
+}
+
+pub fn write_marginality_csv<W: Write>(
+    writer: W,
+    seats: &[SeatMarginality],
+) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record([
+        "constituency",
+        "subdivision",
+        "winner",
+        "winner_votes",
+        "runner_up",
+        "majority_votes",
+        "majority_points",
+    ])?;
+    for seat in seats {
+        csv_writer.write_record([
+            &seat.constituency,
+            &seat.subdivision,
+            &seat.winner,
+            &seat.winner_votes.to_string(),
+            seat.runner_up.as_deref().unwrap_or(""),
+            &seat.majority_votes.to_string(),
+            &format!("{:.2}", seat.majority_points),
+        ])?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+/// Writes the targets, then the defences, one row per seat. `opponent` is the
+/// seat's winner for targets and its runner-up for defences, and `seats` is
+/// the party's seat count if it won (or lost) that seat and those above it.
+pub fn write_target_list_csv<W: Write>(writer: W, list: &TargetList) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record([
+        "list",
+        "rank",
+        "constituency",
+        "subdivision",
+        "opponent",
+        "votes",
+        "swing_needed",
+        "seats",
+    ])?;
+    for (i, target) in list.targets.iter().enumerate() {
+        csv_writer.write_record([
+            "target",
+            &(i + 1).to_string(),
+            &target.constituency,
+            &target.subdivision,
+            &target.held_by,
+            &target.votes_behind.to_string(),
+            &format!("{:.2}", target.swing_needed),
+            &target.seats_if_won.to_string(),
+        ])?;
+    }
+    for (i, defence) in list.defences.iter().enumerate() {
+        csv_writer.write_record([
+            "defence",
+            &(i + 1).to_string(),
+            &defence.constituency,
+            &defence.subdivision,
+            defence.challenger.as_deref().unwrap_or(""),
+            &defence.majority_votes.to_string(),
+            &format!("{:.2}", defence.swing_needed),
+            &defence.seats_if_lost.to_string(),
+        ])?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
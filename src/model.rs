@@ -0,0 +1,152 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+use crate::schema::CURRENT_SCHEMA_VERSION;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ElectionResult {
+    pub schema_version: u32,
+    pub datetime: DateTime<Utc>,
+    pub constituencies: Vec<ConstituencyResult>,
+    pub overall_result: HashMap<String, u32>, // Overall result by party
+    pub metadata: Metadata,
+}
+
+// Where an election data file came from, so reports can cite exactly which
+// data a simulation used
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    pub election_date: Option<NaiveDate>,
+    pub country: Option<String>,
+    pub chamber: Option<String>,
+    pub source: Option<String>, // URL or file the results were read from
+    pub retrieved_at: Option<DateTime<Utc>>, // When they were scraped or imported
+    pub tool_version: Option<String>, // Psephulator version that produced the file
+    pub applied_patches: Vec<AppliedPatch>,
+    pub content_hash: Option<String>, // SHA-256 of the results, set when saved
+}
+
+// A patch file applied to the results after import, kept for provenance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedPatch {
+    pub file: String,
+    pub description: Option<String>,
+    pub operations: Vec<String>,
+}
+
+impl ElectionResult {
+    pub fn new(
+        datetime: DateTime<Utc>,
+        constituencies: Vec<ConstituencyResult>,
+        overall_result: HashMap<String, u32>,
+    ) -> Self {
+        ElectionResult {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            datetime,
+            constituencies,
+            overall_result,
+            metadata: Metadata {
+                tool_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+                ..Metadata::default()
+            },
+        }
+    }
+
+    /// SHA-256 over the constituency and overall results (not the metadata),
+    /// independent of map ordering.
+    pub fn content_hash(&self) -> String {
+        // serde_json::Value keeps object keys sorted, giving a canonical form
+        let canonical = serde_json::to_value((&self.constituencies, &self.overall_result))
+            .expect("election results always serialise")
+            .to_string();
+        Sha256::digest(canonical.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+
+    /// Checks the results still match the content hash recorded when the file
+    /// was saved. Files without a recorded hash pass.
+    pub fn verify_content_hash(&self) -> Result<(), String> {
+        match &self.metadata.content_hash {
+            Some(recorded) if *recorded != self.content_hash() => Err(format!(
+                "results do not match recorded content hash {}",
+                recorded
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// A one-line description of the data for citing in reports.
+    pub fn citation(&self) -> String {
+        let metadata = &self.metadata;
+        let mut parts = Vec::new();
+        match (&metadata.country, &metadata.chamber) {
+            (Some(country), Some(chamber)) => parts.push(format!("{} {}", country, chamber)),
+            (Some(place), None) | (None, Some(place)) => parts.push(place.clone()),
+            (None, None) => {}
+        }
+        if let Some(election_date) = metadata.election_date {
+            parts.push(format!("election of {}", election_date));
+        }
+        if let Some(source) = &metadata.source {
+            parts.push(format!("source {}", source));
+        }
+        if let Some(retrieved_at) = metadata.retrieved_at {
+            parts.push(format!(
+                "retrieved {}",
+                retrieved_at.format("%Y-%m-%d %H:%M UTC")
+            ));
+        }
+        if !metadata.applied_patches.is_empty() {
+            parts.push(format!(
+                "{} patch file(s) applied",
+                metadata.applied_patches.len()
+            ));
+        }
+        parts.push(format!("sha256 {}", &self.content_hash()[..12]));
+        parts.join(", ")
+    }
+
+    /// Recomputes the overall result from the constituency results.
+    pub fn recompute_overall_result(&mut self) {
+        let mut overall_result = HashMap::new();
+        for constituency_result in &self.constituencies {
+            for (party, &votes) in &constituency_result.results {
+                *overall_result.entry(party.clone()).or_insert(0) += votes;
+            }
+        }
+        self.overall_result = overall_result;
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstituencyResult {
+    pub constituency: Constituency,
+    pub results: HashMap<String, u32>, // Results by party
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Subdivision {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Constituency {
+    pub name: String,
+    pub subdivision: Subdivision,
+    pub candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct Party {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Candidate {
+    pub name: String,
+    pub party: Party,
+}
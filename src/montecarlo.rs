@@ -0,0 +1,570 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Dirichlet, Distribution, Normal};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+use crate::indices::{outcome_indices, OutcomeIndices};
+use crate::simulation::{constituency_winners, simulate_election, ElectoralSystem};
+use crate::ElectionResult;
+
+// How the national vote shares are perturbed in each simulated election
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NationalError {
+    // Normal errors with standard deviation `sd` points on each party's share,
+    // correlated between the listed pairs of parties (independent otherwise)
+    // and centred so they sum to zero across parties
+    Normal {
+        sd: f64,
+        #[serde(default)]
+        correlations: Vec<PartyCorrelation>,
+    },
+    // Shares drawn from a Dirichlet distribution centred on the expected
+    // shares; higher concentrations mean smaller errors
+    Dirichlet {
+        concentration: f64,
+    },
+}
+
+// The correlation between two parties' national errors, from -1 to 1
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PartyCorrelation {
+    pub parties: (String, String),
+    pub correlation: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonteCarloSettings {
+    pub runs: usize,
+    pub seed: u64,
+    pub national_error: NationalError,
+    // Extra normal errors shared by every constituency in a subdivision, and
+    // errors for each constituency alone, in points. Zero turns them off.
+    #[serde(default)]
+    pub regional_sd: f64,
+    #[serde(default)]
+    pub constituency_sd: f64,
+    // Seats needed for a majority; defaults to more than half of all seats
+    #[serde(default)]
+    pub majority: Option<u32>,
+}
+
+impl Default for MonteCarloSettings {
+    fn default() -> Self {
+        MonteCarloSettings {
+            runs: 1000,
+            seed: 0,
+            national_error: NationalError::Normal {
+                sd: 2.0,
+                correlations: Vec::new(),
+            },
+            regional_sd: 0.0,
+            constituency_sd: 0.0,
+            majority: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeatDistribution {
+    pub mean: f64,
+    pub p5: u32,
+    pub p50: u32,
+    pub p95: u32,
+    pub majority_probability: f64,
+    pub largest_party_probability: f64, // Ties count for every tied party
+    pub histogram: BTreeMap<u32, usize>, // Seats -> number of runs
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstituencyOdds {
+    pub constituency: String,
+    pub subdivision: String,
+    pub win_probabilities: BTreeMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonteCarloResult {
+    pub runs: usize,
+    pub seed: u64,
+    pub majority: u32,
+    pub hung_probability: f64,
+    pub seats: BTreeMap<String, SeatDistribution>,
+    pub indices: OutcomeIndices, // Each run's indices, averaged over the runs
+    // Empty for systems without constituency winners
+    pub constituencies: Vec<ConstituencyOdds>,
+}
+
+// Vote shares in percentage points for each constituency, with parties in a
+// fixed order so runs draw the same random numbers for the same parties
+struct Baseline {
+    parties: Vec<String>,
+    national: Vec<f64>,
+    subdivisions: Vec<String>,
+    constituencies: Vec<BaselineConstituency>,
+}
+
+struct BaselineConstituency {
+    subdivision: usize,
+    votes: f64,
+    shares: Vec<(usize, f64)>,
+}
+
+// Seats per party in one run, and each constituency's winner if the system
+// has constituency winners
+type Outcome = (HashMap<String, u32>, Option<Vec<Option<String>>>);
+
+// A lower-triangular Cholesky factor of a correlation matrix, by row
+type Factor = Vec<Vec<f64>>;
+
+fn baseline_shares(election_result: &ElectionResult) -> Baseline {
+    let mut parties: Vec<String> = election_result
+        .constituencies
+        .iter()
+        .flat_map(|c| c.results.keys().cloned())
+        .collect();
+    parties.sort();
+    parties.dedup();
+    let mut subdivisions: Vec<String> = election_result
+        .constituencies
+        .iter()
+        .map(|c| c.constituency.subdivision.name.clone())
+        .collect();
+    subdivisions.sort();
+    subdivisions.dedup();
+
+    let mut national_votes = vec![0.0; parties.len()];
+    let constituencies = election_result
+        .constituencies
+        .iter()
+        .map(|constituency_result| {
+            let total: u32 = constituency_result.results.values().sum();
+            let mut shares: Vec<(usize, f64)> = constituency_result
+                .results
+                .iter()
+                .map(|(party, &votes)| {
+                    let i = parties.binary_search(party).unwrap();
+                    national_votes[i] += votes as f64;
+                    (i, votes as f64 / total.max(1) as f64 * 100.0)
+                })
+                .collect();
+            shares.sort_by_key(|(i, _)| *i);
+            let subdivision = subdivisions
+                .binary_search(&constituency_result.constituency.subdivision.name)
+                .unwrap();
+            BaselineConstituency {
+                subdivision,
+                votes: total as f64,
+                shares,
+            }
+        })
+        .collect();
+
+    let total: f64 = national_votes.iter().sum();
+    let national = national_votes
+        .iter()
+        .map(|votes| votes / total.max(1.0) * 100.0)
+        .collect();
+    Baseline {
+        parties,
+        national,
+        subdivisions,
+        constituencies,
+    }
+}
+
+// Parties with votes; the others are placeholders in the data
+fn standing(national: &[f64]) -> Vec<usize> {
+    (0..national.len()).filter(|&i| national[i] > 0.0).collect()
+}
+
+// The lower-triangular Cholesky factor of the correlation matrix of the
+// standing parties' errors, or None if they're independent
+fn correlation_factor(
+    baseline: &Baseline,
+    correlations: &[PartyCorrelation],
+) -> Result<Option<Factor>, Box<dyn Error>> {
+    if correlations.is_empty() {
+        return Ok(None);
+    }
+    let standing = standing(&baseline.national);
+    let position = |party: &str| {
+        baseline
+            .parties
+            .iter()
+            .position(|p| p == party)
+            .and_then(|i| standing.iter().position(|&s| s == i))
+            .ok_or_else(|| format!("{} has no votes to correlate", party))
+    };
+
+    let n = standing.len();
+    let mut matrix = vec![vec![0.0; n]; n];
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    for PartyCorrelation {
+        parties: (a, b),
+        correlation,
+    } in correlations
+    {
+        let (i, j) = (position(a)?, position(b)?);
+        if i == j || !(-1.0..=1.0).contains(correlation) {
+            return Err(format!(
+                "invalid correlation {} between {} and {}",
+                correlation, a, b
+            )
+            .into());
+        }
+        matrix[i][j] = *correlation;
+        matrix[j][i] = *correlation;
+    }
+
+    let mut factor = vec![vec![0.0; n]; n];
+    for i in 0..n {
+        for j in 0..=i {
+            let sum: f64 = (0..j).map(|k| factor[i][k] * factor[j][k]).sum();
+            if i == j {
+                let pivot = matrix[i][i] - sum;
+                if pivot <= 0.0 {
+                    return Err("the party correlations aren't consistent with each other".into());
+                }
+                factor[i][i] = pivot.sqrt();
+            } else {
+                factor[i][j] = (matrix[i][j] - sum) / factor[j][j];
+            }
+        }
+    }
+    Ok(Some(factor))
+}
+
+// Normal errors for each party with votes, correlated by `factor` if there is
+// one and centred so they sum to zero. Parties without votes stay at zero.
+fn centred_normal_errors(
+    rng: &mut ChaCha8Rng,
+    national: &[f64],
+    sd: f64,
+    factor: Option<&Factor>,
+) -> Vec<f64> {
+    let mut errors = vec![0.0; national.len()];
+    let standing = standing(national);
+    if sd == 0.0 || standing.is_empty() {
+        return errors;
+    }
+    let normal = Normal::new(0.0, sd).unwrap();
+    let drawn: Vec<f64> = standing.iter().map(|_| normal.sample(rng)).collect();
+    let drawn = match factor {
+        Some(factor) => factor
+            .iter()
+            .map(|row| row.iter().zip(&drawn).map(|(l, z)| l * z).sum())
+            .collect(),
+        None => drawn,
+    };
+    for (&i, error) in standing.iter().zip(drawn) {
+        errors[i] = error;
+    }
+    let mean = errors.iter().sum::<f64>() / standing.len() as f64;
+    for &i in &standing {
+        errors[i] -= mean;
+    }
+    errors
+}
+
+fn national_errors(
+    rng: &mut ChaCha8Rng,
+    national: &[f64],
+    national_error: &NationalError,
+    factor: Option<&Factor>,
+) -> Vec<f64> {
+    match national_error {
+        NationalError::Normal { sd, .. } => centred_normal_errors(rng, national, *sd, factor),
+        NationalError::Dirichlet { concentration } => {
+            let standing = standing(national);
+            let mut errors = vec![0.0; national.len()];
+            if standing.len() < 2 {
+                return errors;
+            }
+            let alpha: Vec<f64> = standing
+                .iter()
+                .map(|&i| concentration * national[i] / 100.0)
+                .collect();
+            let drawn = Dirichlet::new(&alpha).unwrap().sample(rng);
+            for (&i, share) in standing.iter().zip(drawn) {
+                errors[i] = share * 100.0 - national[i];
+            }
+            errors
+        }
+    }
+}
+
+fn run_rng(settings: &MonteCarloSettings, run: usize) -> ChaCha8Rng {
+    let mut rng = ChaCha8Rng::seed_from_u64(settings.seed);
+    rng.set_stream(run as u64);
+    rng
+}
+
+// One simulated election: the baseline with every error applied to each
+// constituency's shares, which are then clamped and rescaled like a swing
+fn simulate_run(
+    election_result: &ElectionResult,
+    baseline: &Baseline,
+    settings: &MonteCarloSettings,
+    factor: Option<&Factor>,
+    run: usize,
+) -> ElectionResult {
+    let mut rng = run_rng(settings, run);
+
+    let national = national_errors(
+        &mut rng,
+        &baseline.national,
+        &settings.national_error,
+        factor,
+    );
+    let regional: Vec<Vec<f64>> = baseline
+        .subdivisions
+        .iter()
+        .map(|_| centred_normal_errors(&mut rng, &baseline.national, settings.regional_sd, None))
+        .collect();
+    let local = Normal::new(0.0, settings.constituency_sd.max(f64::MIN_POSITIVE)).unwrap();
+
+    let mut simulated = election_result.clone();
+    for (constituency_result, baseline_constituency) in simulated
+        .constituencies
+        .iter_mut()
+        .zip(&baseline.constituencies)
+    {
+        let new_shares: Vec<(usize, f64)> = baseline_constituency
+            .shares
+            .iter()
+            .map(|&(i, share)| {
+                if share == 0.0 {
+                    return (i, 0.0); // Placeholder entries stay empty
+                }
+                let mut share =
+                    share + national[i] + regional[baseline_constituency.subdivision][i];
+                if settings.constituency_sd > 0.0 {
+                    share += local.sample(&mut rng);
+                }
+                (i, share.max(0.0))
+            })
+            .collect();
+        let total_share: f64 = new_shares.iter().map(|(_, s)| s).sum();
+        if total_share == 0.0 {
+            continue;
+        }
+        constituency_result.results = new_shares
+            .into_iter()
+            .map(|(i, share)| {
+                let votes = (share / total_share * baseline_constituency.votes).round() as u32;
+                (baseline.parties[i].clone(), votes)
+            })
+            .collect();
+    }
+    simulated.recompute_overall_result();
+    simulated
+}
+
+// The baseline shares and correlation factor every run draws its errors from
+fn error_model(
+    election_result: &ElectionResult,
+    settings: &MonteCarloSettings,
+) -> Result<(Baseline, Option<Factor>), Box<dyn Error>> {
+    let valid = match settings.national_error {
+        NationalError::Normal { sd, .. } => sd >= 0.0,
+        NationalError::Dirichlet { concentration } => concentration > 0.0,
+    };
+    if !valid || settings.regional_sd < 0.0 || settings.constituency_sd < 0.0 {
+        return Err("error model parameters must be positive".into());
+    }
+    let baseline = baseline_shares(election_result);
+    let factor = match &settings.national_error {
+        NationalError::Normal { correlations, .. } => correlation_factor(&baseline, correlations)?,
+        NationalError::Dirichlet { .. } => None,
+    };
+    Ok((baseline, factor))
+}
+
+/// The national errors, in points by party, drawn by each of the runs a
+/// simulation with these settings would make. Useful for checking an error
+/// model before running it.
+pub fn national_error_draws(
+    election_result: &ElectionResult,
+    settings: &MonteCarloSettings,
+) -> Result<Vec<BTreeMap<String, f64>>, Box<dyn Error>> {
+    let (baseline, factor) = error_model(election_result, settings)?;
+    Ok((0..settings.runs)
+        .map(|run| {
+            let errors = national_errors(
+                &mut run_rng(settings, run),
+                &baseline.national,
+                &settings.national_error,
+                factor.as_ref(),
+            );
+            baseline.parties.iter().cloned().zip(errors).collect()
+        })
+        .collect())
+}
+
+fn percentile(sorted: &[u32], fraction: f64) -> u32 {
+    let rank = ((fraction * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len());
+    sorted[rank - 1]
+}
+
+/// Simulates `settings.runs` elections around `election_result` (usually a
+/// projection), each with randomly perturbed vote shares, and summarises the
+/// seats each party wins under `electoral_system`. Runs are spread across
+/// cores, and each draws from its own stream of the seeded generator, so the
+/// results depend only on the seed.
+pub fn run_monte_carlo(
+    election_result: &ElectionResult,
+    electoral_system: &ElectoralSystem,
+    preference_flows: Option<&HashMap<String, HashMap<String, f32>>>,
+    settings: &MonteCarloSettings,
+) -> Result<MonteCarloResult, Box<dyn Error>> {
+    if settings.runs == 0 {
+        return Err("a Monte Carlo simulation needs at least one run".into());
+    }
+    if electoral_system.needs_preference_flows() && preference_flows.is_none() {
+        return Err(format!(
+            "simulating {} needs preference flows",
+            electoral_system.name()
+        )
+        .into());
+    }
+    let (baseline, factor) = error_model(election_result, settings)?;
+    let (outcomes, indices): (Vec<Outcome>, Vec<OutcomeIndices>) = (0..settings.runs)
+        .into_par_iter()
+        .map(|run| {
+            let simulated =
+                simulate_run(election_result, &baseline, settings, factor.as_ref(), run);
+            let outcome: Outcome =
+                match constituency_winners(&simulated, electoral_system, preference_flows) {
+                    Some(winners) => {
+                        let mut seats = HashMap::new();
+                        for winner in winners.iter().flatten() {
+                            *seats.entry(winner.clone()).or_insert(0) += 1;
+                        }
+                        (seats, Some(winners))
+                    }
+                    None => (
+                        simulate_election(&simulated, electoral_system, preference_flows.cloned())
+                            .expect("preference flows are checked before the runs"),
+                        None,
+                    ),
+                };
+            let indices = outcome_indices(&simulated.overall_result, &outcome.0);
+            (outcome, indices)
+        })
+        .unzip();
+
+    let runs = settings.runs as f64;
+    let total_seats = outcomes
+        .iter()
+        .map(|(seats, _)| seats.values().sum::<u32>())
+        .max()
+        .unwrap_or(0);
+    let majority = settings.majority.unwrap_or(total_seats / 2 + 1);
+
+    let mut parties: Vec<&String> = outcomes
+        .iter()
+        .flat_map(|(seats, _)| seats.keys())
+        .collect();
+    parties.sort();
+    parties.dedup();
+    let mut hung = 0;
+    let mut largest: HashMap<&String, usize> = HashMap::new();
+    for (seats, _) in &outcomes {
+        let most = seats.values().copied().max().unwrap_or(0);
+        for (party, &party_seats) in seats {
+            if party_seats == most {
+                *largest.entry(party).or_insert(0) += 1;
+            }
+        }
+        if most < majority {
+            hung += 1;
+        }
+    }
+
+    let seats = parties
+        .iter()
+        .map(|&party| {
+            let mut counts: Vec<u32> = outcomes
+                .iter()
+                .map(|(seats, _)| seats.get(party).copied().unwrap_or(0))
+                .collect();
+            counts.sort_unstable();
+            let mut histogram = BTreeMap::new();
+            for &count in &counts {
+                *histogram.entry(count).or_insert(0) += 1;
+            }
+            let distribution = SeatDistribution {
+                mean: counts.iter().map(|&c| c as f64).sum::<f64>() / runs,
+                p5: percentile(&counts, 0.05),
+                p50: percentile(&counts, 0.5),
+                p95: percentile(&counts, 0.95),
+                majority_probability: counts.iter().filter(|&&c| c >= majority).count() as f64
+                    / runs,
+                largest_party_probability: largest.get(party).copied().unwrap_or(0) as f64 / runs,
+                histogram,
+            };
+            (party.clone(), distribution)
+        })
+        .collect();
+
+    let constituencies = if outcomes.iter().all(|(_, winners)| winners.is_some()) {
+        election_result
+            .constituencies
+            .iter()
+            .enumerate()
+            .map(|(i, constituency_result)| {
+                let mut wins: BTreeMap<String, usize> = BTreeMap::new();
+                for winner in outcomes
+                    .iter()
+                    .filter_map(|(_, winners)| winners.as_ref().and_then(|w| w[i].as_ref()))
+                {
+                    *wins.entry(winner.clone()).or_insert(0) += 1;
+                }
+                ConstituencyOdds {
+                    constituency: constituency_result.constituency.name.clone(),
+                    subdivision: constituency_result.constituency.subdivision.name.clone(),
+                    win_probabilities: wins
+                        .into_iter()
+                        .map(|(party, wins)| (party, wins as f64 / runs))
+                        .collect(),
+                }
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    Ok(MonteCarloResult {
+        runs: settings.runs,
+        seed: settings.seed,
+        majority,
+        hung_probability: hung as f64 / runs,
+        seats,
+        indices: mean_indices(&indices),
+        constituencies,
+    })
+}
+
+// Each index averaged over the runs
+fn mean_indices(indices: &[OutcomeIndices]) -> OutcomeIndices {
+    let mean = |index: fn(&OutcomeIndices) -> f64| {
+        indices.iter().map(index).sum::<f64>() / indices.len() as f64
+    };
+    OutcomeIndices {
+        gallagher: mean(|i| i.gallagher),
+        loosemore_hanby: mean(|i| i.loosemore_hanby),
+        sainte_lague: mean(|i| i.sainte_lague),
+        dhondt: mean(|i| i.dhondt),
+        effective_parties_by_votes: mean(|i| i.effective_parties_by_votes),
+        effective_parties_by_seats: mean(|i| i.effective_parties_by_seats),
+        golosov_by_votes: mean(|i| i.golosov_by_votes),
+        golosov_by_seats: mean(|i| i.golosov_by_seats),
+        wasted_vote_share: mean(|i| i.wasted_vote_share),
+    }
+}
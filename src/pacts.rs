@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::ElectionResult;
+
+// The seats a party stands down in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeatSelection {
+    All,
+    Constituencies(Vec<String>),
+    Subdivisions(Vec<String>),
+    // Seats where this party won more votes than the one standing down
+    PartnerAhead(String),
+    // Seats where this party came first
+    HeldBy(String),
+}
+
+// What happens to the votes of a party that stands down
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Redistribution {
+    #[default]
+    PreferenceFlows,
+    Abstain,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Withdrawal {
+    pub party: String,
+    pub seats: SeatSelection,
+    #[serde(default)]
+    pub redistribution: Redistribution,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pact {
+    pub name: String,
+    pub withdrawals: Vec<Withdrawal>,
+}
+
+pub fn load_pact<P: AsRef<Path>>(path: P) -> Result<Pact, Box<dyn Error>> {
+    let file_content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&file_content)?)
+}
+
+/// Lists the constituencies `withdrawal` applies to in which its party stood.
+/// Naming a constituency or subdivision that isn't in the election is an error.
+pub fn withdrawn_seats<'a>(
+    election_result: &'a ElectionResult,
+    withdrawal: &Withdrawal,
+) -> Result<Vec<&'a str>, Box<dyn Error>> {
+    let has_constituency = |name: &String| {
+        election_result
+            .constituencies
+            .iter()
+            .any(|c| &c.constituency.name == name)
+    };
+    let has_subdivision = |name: &String| {
+        election_result
+            .constituencies
+            .iter()
+            .any(|c| &c.constituency.subdivision.name == name)
+    };
+    match &withdrawal.seats {
+        SeatSelection::Constituencies(names) => {
+            if let Some(name) = names.iter().find(|name| !has_constituency(name)) {
+                return Err(format!("no constituency called '{}'", name).into());
+            }
+        }
+        SeatSelection::Subdivisions(names) => {
+            if let Some(name) = names.iter().find(|name| !has_subdivision(name)) {
+                return Err(format!("no subdivision called '{}'", name).into());
+            }
+        }
+        _ => {}
+    }
+
+    let party = &withdrawal.party;
+    let seats = election_result
+        .constituencies
+        .iter()
+        .filter(|c| {
+            let Some(&votes) = c.results.get(party) else {
+                return false;
+            };
+            match &withdrawal.seats {
+                SeatSelection::All => true,
+                SeatSelection::Constituencies(names) => names.contains(&c.constituency.name),
+                SeatSelection::Subdivisions(names) => {
+                    names.contains(&c.constituency.subdivision.name)
+                }
+                SeatSelection::PartnerAhead(partner) => {
+                    c.results.get(partner).is_some_and(|&v| v > votes)
+                }
+                SeatSelection::HeldBy(holder) => {
+                    let holder_votes = c.results.get(holder).copied().unwrap_or(0);
+                    holder_votes > 0 && c.results.values().all(|&v| v <= holder_votes)
+                }
+            }
+        })
+        .map(|c| c.constituency.name.as_str())
+        .collect();
+    Ok(seats)
+}
+
+/// Removes each withdrawing party's candidates from its seats. Their votes
+/// follow the withdrawing party's preference flows as AV transfers do: each
+/// party still standing gets its share of the vote, shares for other parties
+/// standing down in the seat are split among those still standing, and the
+/// rest (flows to parties not on the ballot, and whatever a row leaves
+/// unallocated) is lost to abstention. With `Redistribution::Abstain` all of
+/// it is. Seats are chosen from the results before the pact, so the order of
+/// the withdrawals doesn't matter.
+pub fn apply_pact(
+    election_result: &ElectionResult,
+    pact: &Pact,
+    preference_flows: Option<&HashMap<String, HashMap<String, f32>>>,
+) -> Result<ElectionResult, Box<dyn Error>> {
+    // Constituency -> parties standing down there, with their redistribution
+    let mut withdrawn: HashMap<&str, Vec<&Withdrawal>> = HashMap::new();
+    for withdrawal in &pact.withdrawals {
+        let party = &withdrawal.party;
+        let stood = election_result
+            .constituencies
+            .iter()
+            .any(|c| c.results.contains_key(party));
+        if !stood {
+            return Err(format!("{} didn't stand in this election", party).into());
+        }
+        if withdrawal.redistribution == Redistribution::PreferenceFlows {
+            let flows = preference_flows.ok_or_else(|| {
+                format!("redistributing {}'s votes needs preference flows", party)
+            })?;
+            if !flows.contains_key(party) {
+                return Err(format!("no preference flows from {}", party).into());
+            }
+        }
+        for seat in withdrawn_seats(election_result, withdrawal)? {
+            withdrawn.entry(seat).or_default().push(withdrawal);
+        }
+    }
+
+    let mut projected = election_result.clone();
+    for constituency_result in &mut projected.constituencies {
+        let Some(withdrawals) = withdrawn.get(constituency_result.constituency.name.as_str())
+        else {
+            continue;
+        };
+        let is_withdrawn = |party: &str| withdrawals.iter().any(|w| w.party == party);
+        let mut new_votes: HashMap<String, f64> = constituency_result
+            .results
+            .iter()
+            .filter(|(party, _)| !is_withdrawn(party))
+            .map(|(party, &votes)| (party.clone(), votes as f64))
+            .collect();
+
+        for withdrawal in withdrawals {
+            if withdrawal.redistribution == Redistribution::Abstain {
+                continue;
+            }
+            let votes = constituency_result.results[&withdrawal.party] as f64;
+            // In party order, so the total is the same every run
+            let row: BTreeMap<&String, f32> = preference_flows.unwrap()[&withdrawal.party]
+                .iter()
+                .map(|(to, &weight)| (to, weight))
+                .collect();
+            let total = |standing: bool| -> f32 {
+                row.iter()
+                    .filter(|(to, _)| constituency_result.results.contains_key(**to))
+                    .filter(|(to, _)| new_votes.contains_key(**to) == standing)
+                    .map(|(_, weight)| weight)
+                    .sum()
+            };
+            let (standing_total, withdrawn_total) = (total(true), total(false));
+            if standing_total == 0.0 {
+                continue; // Nobody they'd vote for is standing, so they abstain
+            }
+            // As when AV eliminates the parties they'd have gone to next
+            let scale = 1.0 + withdrawn_total / standing_total;
+            for (to, weight) in row {
+                if let Some(v) = new_votes.get_mut(to) {
+                    *v += votes * (weight * scale) as f64;
+                }
+            }
+        }
+
+        constituency_result.results = new_votes
+            .into_iter()
+            .map(|(party, votes)| (party, votes.round() as u32))
+            .collect();
+        constituency_result
+            .constituency
+            .candidates
+            .retain(|candidate| !is_withdrawn(&candidate.party.name));
+    }
+
+    projected.recompute_overall_result();
+    projected.metadata.content_hash = None;
+    Ok(projected)
+}
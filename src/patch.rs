@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::{AppliedPatch, Candidate, ConstituencyResult, ElectionResult, Party};
+
+/// A file of corrections applied to an imported `ElectionResult`, for
+/// constituencies the source can't represent properly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchFile {
+    #[serde(default)]
+    pub description: Option<String>,
+    pub patches: Vec<Patch>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedVotes {
+    pub party: String,
+    pub candidate: String,
+    pub votes: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Patch {
+    // Swap in a complete result for the constituency
+    Replace {
+        constituency: String,
+        result: ConstituencyResult,
+    },
+    // Fix the constituency's name
+    RenameConstituency {
+        constituency: String,
+        name: String,
+    },
+    // Set a party's votes, e.g. after a recount, adding the party if needed
+    SetVotes {
+        constituency: String,
+        party: String,
+        votes: u32,
+        #[serde(default)]
+        candidate: Option<String>,
+    },
+    RemoveParty {
+        constituency: String,
+        party: String,
+    },
+    RenameParty {
+        constituency: String,
+        from: String,
+        to: String,
+    },
+    // Break a lumped-together party (usually OTH) into named candidates. Any
+    // votes not accounted for stay with the original party.
+    SplitParty {
+        constituency: String,
+        party: String,
+        into: Vec<NamedVotes>,
+    },
+}
+
+pub fn load_patch_file<P: AsRef<Path>>(path: P) -> Result<PatchFile, Box<dyn Error>> {
+    let file_content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&file_content)?)
+}
+
+impl Patch {
+    pub fn constituency(&self) -> &str {
+        match self {
+            Patch::Replace { constituency, .. }
+            | Patch::RenameConstituency { constituency, .. }
+            | Patch::SetVotes { constituency, .. }
+            | Patch::RemoveParty { constituency, .. }
+            | Patch::RenameParty { constituency, .. }
+            | Patch::SplitParty { constituency, .. } => constituency,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Patch::Replace { constituency, .. } => format!("replace {}", constituency),
+            Patch::RenameConstituency { constituency, name } => {
+                format!("rename {} to {}", constituency, name)
+            }
+            Patch::SetVotes {
+                constituency,
+                party,
+                votes,
+                ..
+            } => format!("set {} votes in {} to {}", party, constituency, votes),
+            Patch::RemoveParty {
+                constituency,
+                party,
+            } => format!("remove {} from {}", party, constituency),
+            Patch::RenameParty {
+                constituency,
+                from,
+                to,
+            } => format!("rename {} to {} in {}", from, to, constituency),
+            Patch::SplitParty {
+                constituency,
+                party,
+                into,
+            } => format!(
+                "split {} in {} into {}",
+                party,
+                constituency,
+                into.iter()
+                    .map(|n| n.party.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    fn apply(&self, constituency_result: &mut ConstituencyResult) -> Result<(), Box<dyn Error>> {
+        let constituency_name = constituency_result.constituency.name.clone();
+        let missing_party =
+            |party: &str| format!("{} has no {} result to patch", constituency_name, party);
+
+        match self {
+            Patch::Replace { result, .. } => *constituency_result = result.clone(),
+            Patch::RenameConstituency { name, .. } => {
+                constituency_result.constituency.name = name.clone()
+            }
+            Patch::SetVotes {
+                party,
+                votes,
+                candidate,
+                ..
+            } => {
+                if constituency_result
+                    .results
+                    .insert(party.clone(), *votes)
+                    .is_none()
+                {
+                    add_candidate(constituency_result, party, candidate.clone());
+                } else if let Some(candidate) = candidate {
+                    for c in candidates_for(constituency_result, party) {
+                        c.name = candidate.clone();
+                    }
+                }
+            }
+            Patch::RemoveParty { party, .. } => {
+                constituency_result
+                    .results
+                    .remove(party)
+                    .ok_or_else(|| missing_party(party))?;
+                constituency_result
+                    .constituency
+                    .candidates
+                    .retain(|c| c.party.name != *party);
+            }
+            Patch::RenameParty { from, to, .. } => {
+                let votes = constituency_result
+                    .results
+                    .remove(from)
+                    .ok_or_else(|| missing_party(from))?;
+                if constituency_result
+                    .results
+                    .insert(to.clone(), votes)
+                    .is_some()
+                {
+                    return Err(format!("{} already has a {} result", constituency_name, to).into());
+                }
+                for c in candidates_for(constituency_result, from) {
+                    c.party.name = to.clone();
+                }
+            }
+            Patch::SplitParty { party, into, .. } => {
+                let votes = *constituency_result
+                    .results
+                    .get(party)
+                    .ok_or_else(|| missing_party(party))?;
+                let split: u32 = into.iter().map(|n| n.votes).sum();
+                if split > votes {
+                    return Err(format!(
+                        "splitting {} in {} assigns {} votes but it only has {}",
+                        party, constituency_name, split, votes
+                    )
+                    .into());
+                }
+
+                if split == votes {
+                    constituency_result.results.remove(party);
+                    constituency_result
+                        .constituency
+                        .candidates
+                        .retain(|c| c.party.name != *party);
+                } else {
+                    constituency_result
+                        .results
+                        .insert(party.clone(), votes - split);
+                }
+                for named in into {
+                    if constituency_result
+                        .results
+                        .insert(named.party.clone(), named.votes)
+                        .is_some()
+                    {
+                        return Err(format!(
+                            "{} already has a {} result",
+                            constituency_name, named.party
+                        )
+                        .into());
+                    }
+                    add_candidate(
+                        constituency_result,
+                        &named.party,
+                        Some(named.candidate.clone()),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn candidates_for<'a>(
+    constituency_result: &'a mut ConstituencyResult,
+    party: &'a str,
+) -> impl Iterator<Item = &'a mut Candidate> {
+    constituency_result
+        .constituency
+        .candidates
+        .iter_mut()
+        .filter(move |c| c.party.name == party)
+}
+
+fn add_candidate(constituency_result: &mut ConstituencyResult, party: &str, name: Option<String>) {
+    let candidates = &mut constituency_result.constituency.candidates;
+    candidates.push(Candidate {
+        name: name.unwrap_or_else(|| format!("Candidate{}", candidates.len() + 1)),
+        party: Party {
+            name: party.to_string(),
+        },
+    });
+}
+
+/// Applies every patch in a patch file, recomputes the overall result and
+/// records the file in the election's metadata. Patches naming a constituency
+/// that doesn't exist are errors. The patches are applied to a copy, so if any
+/// fails `election_result` is left as it was.
+pub fn apply_patch_file(
+    election_result: &mut ElectionResult,
+    patch_file: &PatchFile,
+    source: &str,
+) -> Result<(), Box<dyn Error>> {
+    let mut patched = election_result.clone();
+    for (i, patch) in patch_file.patches.iter().enumerate() {
+        let constituency_result = patched
+            .constituencies
+            .iter_mut()
+            .find(|c| c.constituency.name == patch.constituency())
+            .ok_or_else(|| {
+                format!(
+                    "{}: patches[{}]: no constituency named '{}'",
+                    source,
+                    i,
+                    patch.constituency()
+                )
+            })?;
+        patch
+            .apply(constituency_result)
+            .map_err(|e| format!("{}: patches[{}]: {}", source, i, e))?;
+    }
+
+    patched.recompute_overall_result();
+    patched.metadata.applied_patches.push(AppliedPatch {
+        file: source.to_string(),
+        description: patch_file.description.clone(),
+        operations: patch_file.patches.iter().map(Patch::describe).collect(),
+    });
+    *election_result = patched;
+    Ok(())
+}
+
+pub fn apply_patch_path<P: AsRef<Path>>(
+    election_result: &mut ElectionResult,
+    path: P,
+) -> Result<(), Box<dyn Error>> {
+    let patch_file = load_patch_file(&path)?;
+    apply_patch_file(
+        election_result,
+        &patch_file,
+        &path.as_ref().display().to_string(),
+    )
+}
@@ -0,0 +1,323 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::indices::{outcome_indices, OutcomeIndices};
+use crate::registry::PartyRegistry;
+use crate::simulation::ElectoralSystem;
+use crate::swing::{project, SwingModel, Swings};
+use crate::ElectionResult;
+
+// Columns of a polls file that aren't party shares
+const POLL_COLUMNS: [&str; 5] = [
+    "pollster",
+    "fieldwork_start",
+    "fieldwork_end",
+    "sample_size",
+    "region",
+];
+
+// Region values that mark a national poll
+const NATIONAL: [&str; 2] = ["", "National"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Poll {
+    pub pollster: String,
+    pub fieldwork_start: NaiveDate,
+    pub fieldwork_end: NaiveDate,
+    pub sample_size: u32,
+    pub region: Option<String>, // A subdivision, or None for national polls
+    pub shares: HashMap<String, f64>, // Percentage points by party
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollAverageSettings {
+    // A poll's weight halves for every `half_life_days` between the end of its
+    // fieldwork and the date of the average
+    pub half_life_days: f64,
+    // Weight grows with the square root of the sample size relative to this
+    pub reference_sample_size: f64,
+    pub adjust_house_effects: bool,
+}
+
+impl Default for PollAverageSettings {
+    fn default() -> Self {
+        PollAverageSettings {
+            half_life_days: 14.0,
+            reference_sample_size: 1000.0,
+            adjust_house_effects: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollAverage {
+    pub polls_used: usize,
+    pub shares: HashMap<String, f64>,
+    // Pollster -> party -> points each pollster's polls were adjusted down by
+    pub house_effects: HashMap<String, HashMap<String, f64>>,
+}
+
+// A seat projection from the polling average on a given date
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Nowcast {
+    pub generated_at: DateTime<Utc>,
+    pub as_of: NaiveDate,
+    pub model: SwingModel,
+    pub polls_used: usize,
+    pub national: HashMap<String, f64>,
+    pub regional: HashMap<String, HashMap<String, f64>>,
+    pub seats: BTreeMap<String, u32>,
+    pub indices: OutcomeIndices,
+}
+
+/// Reads a polls CSV with `pollster`, `fieldwork_start`, `fieldwork_end`
+/// (YYYY-MM-DD), `sample_size` and optional `region` columns; every other
+/// column is a party's share in percentage points. Blank shares are parties
+/// the poll didn't report. Party columns are normalised through `registry` if
+/// there is one, adding together columns that turn out to be the same party.
+pub fn read_polls_csv<R: Read>(
+    reader: R,
+    registry: Option<&PartyRegistry>,
+) -> Result<Vec<Poll>, Box<dyn Error>> {
+    let mut csv_reader = csv::Reader::from_reader(reader);
+    let headers = csv_reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or_else(|| format!("polls file has no '{}' column", name))
+    };
+    let pollster_column = column("pollster")?;
+    let start_column = column("fieldwork_start")?;
+    let end_column = column("fieldwork_end")?;
+    let sample_column = column("sample_size")?;
+    let region_column = column("region").ok();
+    let party_columns: Vec<(usize, String)> = headers
+        .iter()
+        .enumerate()
+        .filter(|(_, h)| !POLL_COLUMNS.contains(&h.trim()))
+        .map(|(i, h)| {
+            let party = h.trim();
+            (
+                i,
+                registry.map_or(party.to_string(), |r| r.normalise(party)),
+            )
+        })
+        .collect();
+
+    let mut polls = Vec::new();
+    for (row, record) in csv_reader.records().enumerate() {
+        let record = record?;
+        let row = row + 2;
+        let date = |i: usize| {
+            NaiveDate::parse_from_str(record[i].trim(), "%Y-%m-%d")
+                .map_err(|_| format!("row {}: '{}' is not a YYYY-MM-DD date", row, &record[i]))
+        };
+        let fieldwork_start = date(start_column)?;
+        let fieldwork_end = date(end_column)?;
+        if fieldwork_end < fieldwork_start {
+            return Err(format!("row {}: fieldwork ends before it starts", row).into());
+        }
+        let sample_size = record[sample_column]
+            .trim()
+            .replace(',', "")
+            .parse()
+            .map_err(|_| {
+                format!(
+                    "row {}: '{}' is not a sample size",
+                    row, &record[sample_column]
+                )
+            })?;
+        let region = region_column
+            .map(|i| record[i].trim())
+            .filter(|r| !NATIONAL.contains(r))
+            .map(str::to_string);
+
+        let mut shares = HashMap::new();
+        for (i, party) in &party_columns {
+            let share = record[*i].trim().trim_end_matches('%');
+            if share.is_empty() {
+                continue;
+            }
+            let share: f64 = share
+                .parse()
+                .map_err(|_| format!("row {}: '{}' is not a share for {}", row, share, party))?;
+            *shares.entry(party.clone()).or_insert(0.0) += share;
+        }
+
+        polls.push(Poll {
+            pollster: record[pollster_column].trim().to_string(),
+            fieldwork_start,
+            fieldwork_end,
+            sample_size,
+            region,
+            shares,
+        });
+    }
+    Ok(polls)
+}
+
+pub fn load_polls<P: AsRef<Path>>(
+    path: P,
+    registry: Option<&PartyRegistry>,
+) -> Result<Vec<Poll>, Box<dyn Error>> {
+    read_polls_csv(File::open(path)?, registry)
+}
+
+fn poll_weight(poll: &Poll, as_of: NaiveDate, settings: &PollAverageSettings) -> f64 {
+    let age = (as_of - poll.fieldwork_end).num_days().max(0) as f64;
+    let recency = 0.5_f64.powf(age / settings.half_life_days);
+    let size = (poll.sample_size as f64 / settings.reference_sample_size).sqrt();
+    recency * size
+}
+
+// Weighted mean of each party's share over the polls that reported it
+fn weighted_shares<'a>(
+    polls: impl Iterator<Item = (&'a Poll, f64)>,
+    adjustment: impl Fn(&Poll, &str) -> f64,
+) -> HashMap<String, f64> {
+    let mut totals: HashMap<String, (f64, f64)> = HashMap::new();
+    for (poll, weight) in polls {
+        for (party, share) in &poll.shares {
+            let total = totals.entry(party.clone()).or_default();
+            total.0 += (share - adjustment(poll, party)) * weight;
+            total.1 += weight;
+        }
+    }
+    totals
+        .into_iter()
+        .filter(|(_, (_, weight))| *weight > 0.0)
+        .map(|(party, (share, weight))| (party, share / weight))
+        .collect()
+}
+
+/// Averages the polls with fieldwork finished by `as_of`, weighting them by
+/// recency and sample size. With house effect adjustment, each pollster's
+/// average lean relative to the others is estimated and removed, so a pollster
+/// that polls often doesn't pull the average towards its own lean. Returns
+/// `None` if no polls qualify.
+pub fn poll_average(
+    polls: &[&Poll],
+    as_of: NaiveDate,
+    settings: &PollAverageSettings,
+) -> Option<PollAverage> {
+    let weighted: Vec<(&Poll, f64)> = polls
+        .iter()
+        .filter(|poll| poll.fieldwork_end <= as_of)
+        .map(|poll| (*poll, poll_weight(poll, as_of, settings)))
+        .collect();
+    if weighted.is_empty() {
+        return None;
+    }
+
+    let unadjusted = weighted_shares(weighted.iter().copied(), |_, _| 0.0);
+    let mut house_effects: HashMap<String, HashMap<String, f64>> = HashMap::new();
+    if settings.adjust_house_effects {
+        let mut pollsters: Vec<&str> = weighted.iter().map(|(p, _)| p.pollster.as_str()).collect();
+        pollsters.sort();
+        pollsters.dedup();
+        // A pollster's lean is its own weighted average minus the overall one
+        for pollster in &pollsters {
+            let own = weighted_shares(
+                weighted
+                    .iter()
+                    .copied()
+                    .filter(|(p, _)| p.pollster == *pollster),
+                |_, _| 0.0,
+            );
+            house_effects.insert(
+                pollster.to_string(),
+                own.iter()
+                    .map(|(party, share)| (party.clone(), share - unadjusted[party]))
+                    .collect(),
+            );
+        }
+        // Centre the leans on the average pollster, not the average poll
+        let mut parties: Vec<String> = unadjusted.keys().cloned().collect();
+        parties.sort();
+        for party in parties {
+            let leans: Vec<f64> = house_effects
+                .values()
+                .filter_map(|effects| effects.get(&party).copied())
+                .collect();
+            let mean_lean = leans.iter().sum::<f64>() / leans.len() as f64;
+            for effects in house_effects.values_mut() {
+                if let Some(effect) = effects.get_mut(&party) {
+                    *effect -= mean_lean;
+                }
+            }
+        }
+    }
+
+    let shares = weighted_shares(weighted.iter().copied(), |poll, party| {
+        house_effects
+            .get(&poll.pollster)
+            .and_then(|effects| effects.get(party))
+            .copied()
+            .unwrap_or(0.0)
+    });
+    Some(PollAverage {
+        polls_used: weighted.len(),
+        shares,
+        house_effects,
+    })
+}
+
+/// Projects seats from the polls: national polls are averaged into a national
+/// swing from `baseline`, and regional polls into swings for their
+/// subdivisions.
+pub fn nowcast(
+    baseline: &ElectionResult,
+    polls: &[Poll],
+    as_of: NaiveDate,
+    settings: &PollAverageSettings,
+    model: SwingModel,
+    electoral_system: &ElectoralSystem,
+    preference_flows: Option<&HashMap<String, HashMap<String, f32>>>,
+) -> Result<Nowcast, Box<dyn Error>> {
+    let national_polls: Vec<&Poll> = polls.iter().filter(|p| p.region.is_none()).collect();
+    let national = poll_average(&national_polls, as_of, settings)
+        .ok_or_else(|| format!("no national polls finished by {}", as_of))?;
+
+    let mut regions: Vec<&str> = polls.iter().filter_map(|p| p.region.as_deref()).collect();
+    regions.sort();
+    regions.dedup();
+    let mut polls_used = national.polls_used;
+    let mut regional = HashMap::new();
+    for region in regions {
+        let region_polls: Vec<&Poll> = polls
+            .iter()
+            .filter(|p| p.region.as_deref() == Some(region))
+            .collect();
+        if let Some(average) = poll_average(&region_polls, as_of, settings) {
+            polls_used += average.polls_used;
+            regional.insert(region.to_string(), average.shares);
+        }
+    }
+
+    let swings = Swings::to_target_shares(baseline, &national.shares, &regional);
+    let projection = project(baseline, model, &swings, electoral_system, preference_flows)?;
+    Ok(Nowcast {
+        generated_at: Utc::now(),
+        as_of,
+        model,
+        polls_used,
+        national: national.shares,
+        regional,
+        indices: outcome_indices(&projection.result.overall_result, &projection.seats),
+        seats: projection.seats.into_iter().collect(),
+    })
+}
+
+/// Appends a nowcast to a JSON lines history file, one nowcast per line, so
+/// projections can be charted over time.
+pub fn append_nowcast<P: AsRef<Path>>(path: P, nowcast: &Nowcast) -> Result<(), Box<dyn Error>> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(nowcast)?)?;
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::ElectionResult;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegisteredParty {
+    pub id: String,
+    pub name: String,
+    pub short_name: String,
+    pub colour: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub alliances: Vec<String>, // Alliance ids this party belongs to
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alliance {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PartyRegistry {
+    pub parties: Vec<RegisteredParty>,
+    #[serde(default)]
+    pub alliances: Vec<Alliance>,
+}
+
+// A registry file holds one PartyRegistry per election, keyed by election id (e.g. "uk_2024")
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RegistryFile {
+    elections: HashMap<String, PartyRegistry>,
+}
+
+pub fn load_party_registry<P: AsRef<Path>>(
+    path: P,
+    election: &str,
+) -> Result<PartyRegistry, Box<dyn Error>> {
+    let file_content = fs::read_to_string(path)?;
+    let mut file: RegistryFile = serde_json::from_str(&file_content)?;
+    let registry = file
+        .elections
+        .remove(election)
+        .ok_or_else(|| format!("no party registry for election '{}'", election))?;
+    registry.validate()?;
+    Ok(registry)
+}
+
+impl PartyRegistry {
+    /// Checks that ids and aliases are unambiguous and that every alliance a
+    /// party claims membership of is defined.
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        let mut seen: HashMap<String, &str> = HashMap::new();
+        for party in &self.parties {
+            for label in std::iter::once(&party.id).chain(&party.aliases) {
+                if let Some(other) = seen.insert(label.to_lowercase(), &party.id) {
+                    if other != party.id {
+                        return Err(format!(
+                            "'{}' is used by both {} and {}",
+                            label, other, party.id
+                        )
+                        .into());
+                    }
+                }
+            }
+            for alliance in &party.alliances {
+                if self.alliance(alliance).is_none() {
+                    return Err(
+                        format!("{} belongs to unknown alliance '{}'", party.id, alliance).into(),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn party(&self, id: &str) -> Option<&RegisteredParty> {
+        self.parties.iter().find(|p| p.id == id)
+    }
+
+    pub fn alliance(&self, id: &str) -> Option<&Alliance> {
+        self.alliances.iter().find(|a| a.id == id)
+    }
+
+    /// Resolves an id or alias (case-insensitively) to a canonical party id.
+    pub fn canonical_id(&self, label: &str) -> Option<&str> {
+        let label = label.trim();
+        self.parties
+            .iter()
+            .find(|p| {
+                p.id.eq_ignore_ascii_case(label)
+                    || p.aliases.iter().any(|a| a.eq_ignore_ascii_case(label))
+            })
+            .map(|p| p.id.as_str())
+    }
+
+    /// Like `canonical_id`, but leaves labels the registry doesn't know unchanged.
+    pub fn normalise(&self, label: &str) -> String {
+        self.canonical_id(label)
+            .map(str::to_string)
+            .unwrap_or_else(|| label.to_string())
+    }
+
+    pub fn alliance_members(&self, alliance: &str) -> Vec<&str> {
+        self.parties
+            .iter()
+            .filter(|p| p.alliances.iter().any(|a| a == alliance))
+            .map(|p| p.id.as_str())
+            .collect()
+    }
+
+    /// Rewrites every party label in an election result to its canonical id,
+    /// merging the votes of labels that turn out to be the same party.
+    pub fn normalise_election(&self, election_result: &mut ElectionResult) {
+        for constituency_result in &mut election_result.constituencies {
+            constituency_result.results = self.normalise_votes(&constituency_result.results);
+            for candidate in &mut constituency_result.constituency.candidates {
+                candidate.party.name = self.normalise(&candidate.party.name);
+            }
+        }
+        election_result.overall_result = self.normalise_votes(&election_result.overall_result);
+    }
+
+    pub fn normalise_votes(&self, votes: &HashMap<String, u32>) -> HashMap<String, u32> {
+        let mut normalised = HashMap::new();
+        for (party, &party_votes) in votes {
+            *normalised.entry(self.normalise(party)).or_insert(0) += party_votes;
+        }
+        normalised
+    }
+
+    pub fn normalise_preference_flows(
+        &self,
+        preference_flows: &HashMap<String, HashMap<String, f32>>,
+    ) -> HashMap<String, HashMap<String, f32>> {
+        let mut normalised: HashMap<String, HashMap<String, f32>> = HashMap::new();
+        for (from, flows) in preference_flows {
+            let row = normalised.entry(self.normalise(from)).or_default();
+            for (to, &share) in flows {
+                *row.entry(self.normalise(to)).or_insert(0.0) += share;
+            }
+        }
+        normalised
+    }
+
+    /// Totals seat counts by alliance. A party is counted under the first
+    /// alliance it belongs to; parties outside any alliance keep their own id.
+    pub fn aggregate_by_alliance(&self, seats: &HashMap<String, u32>) -> HashMap<String, u32> {
+        let mut aggregated = HashMap::new();
+        for (party, &party_seats) in seats {
+            let key = self
+                .party(party)
+                .and_then(|p| p.alliances.first())
+                .unwrap_or(party);
+            *aggregated.entry(key.clone()).or_insert(0) += party_seats;
+        }
+        aggregated
+    }
+}
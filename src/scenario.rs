@@ -0,0 +1,265 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::datasets::load_election;
+use crate::indices::{outcome_indices, OutcomeIndices};
+use crate::pacts::{apply_pact, load_pact};
+use crate::schema::save_election_data;
+use crate::simulation::{load_preference_flows, ElectoralSystem};
+use crate::swing::{
+    apply_swing, compare_with_baseline, ProjectionModel, SeatChange, SwingModel, Swings,
+};
+use crate::tactical::{apply_tactical_voting, load_tactical_voting};
+use crate::transition::{apply_transitions, load_transition_matrix, DEFAULT_BASELINE_TURNOUT};
+use crate::ElectionResult;
+
+// A reproducible run: a base election, the changes made to it, and the
+// systems to count it under. Relative paths are relative to the scenario file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    pub name: String,
+    pub election: PathBuf,
+    #[serde(default)]
+    pub preference_flows: Option<PathBuf>, // Needed for AV, STV and pacts
+    #[serde(default)]
+    pub swing: Option<ScenarioSwing>,
+    #[serde(default)]
+    pub transitions: Option<ScenarioTransitions>,
+    #[serde(default)]
+    pub pacts: Vec<PathBuf>, // Applied in order, after any swing or transitions
+    #[serde(default)]
+    pub tactical: Option<PathBuf>, // Applied last
+    pub systems: Vec<ElectoralSystem>,
+    #[serde(default)]
+    pub outputs: Vec<ScenarioOutput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioSwing {
+    #[serde(default)]
+    pub model: SwingModel,
+    #[serde(flatten)]
+    pub swings: Swings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioTransitions {
+    pub matrix: PathBuf,
+    #[serde(default = "default_turnout")]
+    pub turnout: f64,
+}
+
+fn default_turnout() -> f64 {
+    DEFAULT_BASELINE_TURNOUT
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScenarioOutput {
+    Summary,               // Printed seat counts
+    ReportJson(PathBuf),   // The whole report
+    SeatsCsv(PathBuf),     // One row per system and party
+    ChangesCsv(PathBuf),   // One row per system and seat changing hands
+    ElectionJson(PathBuf), // The election after the scenario's changes
+}
+
+// The seats under one system, before and after the scenario's changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SystemOutcome {
+    pub system: ElectoralSystem,
+    pub baseline_seats: BTreeMap<String, u32>,
+    pub seats: BTreeMap<String, u32>,
+    pub changes: Vec<SeatChange>,
+    pub indices: OutcomeIndices, // For the seats after the changes
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioReport {
+    pub name: String,
+    pub model: Option<ProjectionModel>, // None without a swing or transitions
+    pub outcomes: Vec<SystemOutcome>,
+    #[serde(skip)]
+    pub result: Option<ElectionResult>, // The election after the scenario's changes
+}
+
+/// Reads a scenario file, resolving its relative paths against the file's
+/// directory so scenarios can be kept next to the data they use.
+pub fn load_scenario<P: AsRef<Path>>(path: P) -> Result<Scenario, Box<dyn Error>> {
+    let file_content = fs::read_to_string(&path)?;
+    let mut scenario: Scenario = serde_json::from_str(&file_content)?;
+    let base = path.as_ref().parent().unwrap_or(Path::new(""));
+    let resolve = |path: &mut PathBuf| {
+        if path.is_relative() {
+            *path = base.join(&*path);
+        }
+    };
+
+    resolve(&mut scenario.election);
+    if let Some(path) = &mut scenario.preference_flows {
+        resolve(path);
+    }
+    if let Some(transitions) = &mut scenario.transitions {
+        resolve(&mut transitions.matrix);
+    }
+    scenario.pacts.iter_mut().for_each(resolve);
+    if let Some(path) = &mut scenario.tactical {
+        resolve(path);
+    }
+    for output in &mut scenario.outputs {
+        match output {
+            ScenarioOutput::Summary => {}
+            ScenarioOutput::ReportJson(path)
+            | ScenarioOutput::SeatsCsv(path)
+            | ScenarioOutput::ChangesCsv(path)
+            | ScenarioOutput::ElectionJson(path) => resolve(path),
+        }
+    }
+    Ok(scenario)
+}
+
+/// Applies the scenario's swing or transitions, pacts and tactical voting to
+/// its election, then counts the seats before and after under each system.
+pub fn run_scenario(scenario: &Scenario) -> Result<ScenarioReport, Box<dyn Error>> {
+    if scenario.swing.is_some() && scenario.transitions.is_some() {
+        return Err("a scenario can have swings or transitions, not both".into());
+    }
+    if scenario.systems.is_empty() {
+        return Err("a scenario needs at least one electoral system".into());
+    }
+
+    let (baseline, registry) = load_election(&scenario.election)?;
+    let preference_flows = match &scenario.preference_flows {
+        Some(path) => Some(load_preference_flows(path, registry.as_ref())?),
+        None => None,
+    };
+
+    let (mut projected, model) = match (&scenario.swing, &scenario.transitions) {
+        (Some(swing), _) => (
+            apply_swing(&baseline, swing.model, &swing.swings)?,
+            Some(ProjectionModel::Swing(swing.model)),
+        ),
+        (_, Some(transitions)) => (
+            apply_transitions(
+                &baseline,
+                &load_transition_matrix(&transitions.matrix)?,
+                transitions.turnout,
+            )?,
+            Some(ProjectionModel::TransitionMatrix),
+        ),
+        _ => (baseline.clone(), None),
+    };
+    for path in &scenario.pacts {
+        projected = apply_pact(&projected, &load_pact(path)?, preference_flows.as_ref())?;
+    }
+    if let Some(path) = &scenario.tactical {
+        projected = apply_tactical_voting(&projected, &load_tactical_voting(path)?)?;
+    }
+
+    let mut outcomes = Vec::new();
+    for system in &scenario.systems {
+        let projection = compare_with_baseline(
+            &baseline,
+            projected.clone(),
+            model.unwrap_or(ProjectionModel::Swing(SwingModel::Uniform)),
+            system,
+            preference_flows.as_ref(),
+        )?;
+        outcomes.push(SystemOutcome {
+            system: system.clone(),
+            indices: outcome_indices(&projected.overall_result, &projection.seats),
+            baseline_seats: projection.baseline_seats.into_iter().collect(),
+            seats: projection.seats.into_iter().collect(),
+            changes: projection.changes,
+        });
+    }
+
+    Ok(ScenarioReport {
+        name: scenario.name.clone(),
+        model,
+        outcomes,
+        result: Some(projected),
+    })
+}
+
+/// Writes the scenario's file outputs. Summaries are left to the caller.
+pub fn write_scenario_outputs(
+    scenario: &Scenario,
+    report: &ScenarioReport,
+) -> Result<(), Box<dyn Error>> {
+    for output in &scenario.outputs {
+        match output {
+            ScenarioOutput::Summary => {}
+            ScenarioOutput::ReportJson(path) => {
+                let mut file = File::create(path)?;
+                writeln!(file, "{}", serde_json::to_string_pretty(report)?)?;
+            }
+            ScenarioOutput::SeatsCsv(path) => write_outcome_seats_csv(File::create(path)?, report)?,
+            ScenarioOutput::ChangesCsv(path) => {
+                write_outcome_changes_csv(File::create(path)?, report)?
+            }
+            ScenarioOutput::ElectionJson(path) => {
+                let result = report
+                    .result
+                    .as_ref()
+                    .ok_or("the report has no projected election")?;
+                save_election_data(path, result)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn write_outcome_seats_csv<W: Write>(
+    writer: W,
+    report: &ScenarioReport,
+) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(["system", "party", "baseline_seats", "seats", "change"])?;
+    for outcome in &report.outcomes {
+        let mut parties: Vec<&String> = outcome
+            .baseline_seats
+            .keys()
+            .chain(outcome.seats.keys())
+            .collect();
+        parties.sort();
+        parties.dedup();
+        for party in parties {
+            let before = outcome.baseline_seats.get(party).copied().unwrap_or(0);
+            let after = outcome.seats.get(party).copied().unwrap_or(0);
+            csv_writer.write_record([
+                outcome.system.name().to_string(),
+                party.clone(),
+                before.to_string(),
+                after.to_string(),
+                (after as i64 - before as i64).to_string(),
+            ])?;
+        }
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+pub fn write_outcome_changes_csv<W: Write>(
+    writer: W,
+    report: &ScenarioReport,
+) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(["system", "constituency", "subdivision", "from", "to"])?;
+    for outcome in &report.outcomes {
+        for change in &outcome.changes {
+            csv_writer.write_record([
+                outcome.system.name(),
+                &change.constituency,
+                &change.subdivision,
+                &change.from,
+                &change.to,
+            ])?;
+        }
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::ElectionResult;
+
+// Version history of the election data files:
+//   1 - constituencies have no subdivision (data/uk_2024_full.json)
+//   2 - constituencies carry a subdivision, files record `schema_version`
+//   3 - a metadata block records the patches applied after import
+//   4 - the metadata block records provenance, and `datetime` is the election
+//       date rather than the scrape time
+pub const CURRENT_SCHEMA_VERSION: u32 = 4;
+
+// Subdivision given to constituencies migrated from files that predate subdivisions
+pub const UNKNOWN_SUBDIVISION: &str = "Unknown";
+
+type Migration = fn(&mut Value) -> Result<(), Box<dyn Error>>;
+
+// MIGRATIONS[i] upgrades a file from version i + 1 to version i + 2
+const MIGRATIONS: [Migration; 3] = [migrate_v1_to_v2, migrate_v2_to_v3, migrate_v3_to_v4];
+
+/// Works out the schema version of a raw election file. Files written before
+/// `schema_version` existed are recognised by their shape.
+pub fn detect_schema_version(data: &Value) -> Result<u32, Box<dyn Error>> {
+    if let Some(version) = data.get("schema_version") {
+        return version
+            .as_u64()
+            .map(|v| v as u32)
+            .ok_or_else(|| "schema_version must be a positive integer".into());
+    }
+
+    let constituencies = data
+        .get("constituencies")
+        .and_then(Value::as_array)
+        .ok_or("election file has no constituencies")?;
+
+    let has_subdivisions = constituencies
+        .first()
+        .and_then(|c| c.get("constituency"))
+        .map(|c| c.get("subdivision").is_some())
+        .unwrap_or(true);
+
+    Ok(if has_subdivisions { 2 } else { 1 })
+}
+
+/// Upgrades a raw election file to `CURRENT_SCHEMA_VERSION`, returning the
+/// version it started at.
+pub fn migrate(data: &mut Value) -> Result<u32, Box<dyn Error>> {
+    let original_version = detect_schema_version(data)?;
+    if original_version == 0 || original_version > CURRENT_SCHEMA_VERSION {
+        return Err(format!(
+            "unsupported schema version {} (this build supports up to {})",
+            original_version, CURRENT_SCHEMA_VERSION
+        )
+        .into());
+    }
+
+    for migration in &MIGRATIONS[(original_version - 1) as usize..] {
+        migration(data)?;
+    }
+
+    data.as_object_mut()
+        .ok_or("election file must be a JSON object")?
+        .insert("schema_version".to_string(), json!(CURRENT_SCHEMA_VERSION));
+
+    Ok(original_version)
+}
+
+fn migrate_v1_to_v2(data: &mut Value) -> Result<(), Box<dyn Error>> {
+    let constituencies = data
+        .get_mut("constituencies")
+        .and_then(Value::as_array_mut)
+        .ok_or("election file has no constituencies")?;
+
+    for constituency_result in constituencies {
+        let constituency = constituency_result
+            .get_mut("constituency")
+            .and_then(Value::as_object_mut)
+            .ok_or("constituency result has no constituency")?;
+        constituency
+            .entry("subdivision")
+            .or_insert_with(|| json!({ "name": UNKNOWN_SUBDIVISION }));
+    }
+
+    Ok(())
+}
+
+fn migrate_v2_to_v3(data: &mut Value) -> Result<(), Box<dyn Error>> {
+    data.as_object_mut()
+        .ok_or("election file must be a JSON object")?
+        .entry("metadata")
+        .or_insert_with(|| json!({ "applied_patches": [] }));
+    Ok(())
+}
+
+fn migrate_v3_to_v4(data: &mut Value) -> Result<(), Box<dyn Error>> {
+    let data = data
+        .as_object_mut()
+        .ok_or("election file must be a JSON object")?;
+    // Earlier files stamped `datetime` at scrape time, so that is when they
+    // were retrieved; the election date itself is unknown
+    let retrieved_at = data.get("datetime").cloned().unwrap_or(Value::Null);
+    let metadata = data
+        .get_mut("metadata")
+        .and_then(Value::as_object_mut)
+        .ok_or("election file has no metadata")?;
+    for field in [
+        "election_date",
+        "country",
+        "chamber",
+        "source",
+        "tool_version",
+        "content_hash",
+    ] {
+        metadata.entry(field).or_insert(Value::Null);
+    }
+    metadata.entry("retrieved_at").or_insert(retrieved_at);
+    Ok(())
+}
+
+/// Parses an election file of any supported schema version into the current model.
+pub fn parse_election_data(file_content: &str) -> Result<ElectionResult, Box<dyn Error>> {
+    let mut data: Value = serde_json::from_str(file_content)?;
+    migrate(&mut data)?;
+    Ok(serde_json::from_value(data)?)
+}
+
+pub fn load_election_data<P: AsRef<Path>>(path: P) -> Result<ElectionResult, Box<dyn Error>> {
+    let file_content = fs::read_to_string(path)?;
+    parse_election_data(&file_content)
+}
+
+/// Writes an election file, recording the content hash of its results.
+pub fn save_election_data<P: AsRef<Path>>(
+    path: P,
+    election_result: &ElectionResult,
+) -> Result<(), Box<dyn Error>> {
+    let mut stamped = election_result.clone();
+    stamped.metadata.content_hash = Some(election_result.content_hash());
+    let json_data = serde_json::to_string_pretty(&stamped)?;
+    fs::write(path, json_data)?;
+    Ok(())
+}
+
+/// Rewrites an election file in place at the current schema version, returning
+/// the version it was upgraded from.
+pub fn migrate_file<P: AsRef<Path>>(path: P) -> Result<u32, Box<dyn Error>> {
+    let file_content = fs::read_to_string(&path)?;
+    let mut data: Value = serde_json::from_str(&file_content)?;
+    let original_version = migrate(&mut data)?;
+    // Round-trip through the model so the rewritten file is known to load
+    let election_result: ElectionResult = serde_json::from_value(data)?;
+    save_election_data(&path, &election_result)?;
+    Ok(original_version)
+}
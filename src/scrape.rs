@@ -0,0 +1,412 @@
+use chrono::{NaiveDate, Utc};
+use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::patch::apply_patch_path;
+use crate::{Candidate, Constituency, ConstituencyResult, ElectionResult, Party, Subdivision};
+
+/// A source spec: where an election's results page lives and how to read its
+/// results tables. Party columns are found from the table headers rather than
+/// by position, so layout changes either still parse or fail loudly.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrapeConfig {
+    #[serde(default)]
+    pub url: Option<String>, // Live page, used when no saved copy is given
+    #[serde(default)]
+    pub election_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub chamber: Option<String>,
+    #[serde(default = "default_table_selector")]
+    pub table_selector: String,
+    pub subdivisions: Vec<String>, // Subdivision of each results table, in page order
+    #[serde(default = "default_constituency_header")]
+    pub constituency_header: String,
+    #[serde(default = "default_votes_header")]
+    pub votes_header: String, // Heading grouping the per-party vote columns
+    pub header_aliases: HashMap<String, String>, // Party column heading -> party id
+    // Patch files applied after import, relative to the spec, for constituencies
+    // the tables can't represent (e.g. several independents lumped into "Other")
+    #[serde(default)]
+    pub patches: Vec<PathBuf>,
+}
+
+fn default_table_selector() -> String {
+    "table.wikitable".to_string()
+}
+
+fn default_constituency_header() -> String {
+    "Constituency".to_string()
+}
+
+fn default_votes_header() -> String {
+    "Votes".to_string()
+}
+
+impl Default for ScrapeConfig {
+    fn default() -> Self {
+        ScrapeConfig {
+            url: None,
+            election_date: None,
+            country: None,
+            chamber: None,
+            table_selector: default_table_selector(),
+            subdivisions: Vec::new(),
+            constituency_header: default_constituency_header(),
+            votes_header: default_votes_header(),
+            header_aliases: HashMap::new(),
+            patches: Vec::new(),
+        }
+    }
+}
+
+pub fn load_scrape_config<P: AsRef<Path>>(path: P) -> Result<ScrapeConfig, Box<dyn Error>> {
+    let file_content = fs::read_to_string(&path)?;
+    let mut config: ScrapeConfig = serde_json::from_str(&file_content)?;
+    Selector::parse(&config.table_selector)
+        .map_err(|_| format!("invalid table selector '{}'", config.table_selector))?;
+    let spec_dir = path.as_ref().parent().unwrap_or(Path::new(""));
+    config.patches = config.patches.iter().map(|p| spec_dir.join(p)).collect();
+    Ok(config)
+}
+
+impl ScrapeConfig {
+    fn party_for_heading(&self, heading: &str) -> Option<&String> {
+        self.header_aliases
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(heading))
+            .map(|(_, party)| party)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Cell {
+    text: String,
+    header: bool,
+}
+
+// Footnote markers and hidden sort keys, which aren't part of what a cell shows
+fn is_hidden(element: &ElementRef) -> bool {
+    let element = element.value();
+    let style = element.attr("style").unwrap_or("").replace(' ', "");
+    let has_class = |class: &str| element.classes().any(|c| c == class);
+    (element.name() == "sup" && has_class("reference"))
+        || has_class("sortkey")
+        || style.contains("display:none")
+}
+
+fn visible_text(element: &ElementRef, text: &mut String) {
+    for child in element.children() {
+        if let Some(child_element) = ElementRef::wrap(child) {
+            if !is_hidden(&child_element) {
+                visible_text(&child_element, text);
+            }
+        } else if let Some(child_text) = child.value().as_text() {
+            text.push_str(child_text);
+        }
+    }
+}
+
+fn cell_text(element: &ElementRef) -> String {
+    let mut text = String::new();
+    visible_text(element, &mut text);
+    text.trim().to_string()
+}
+
+fn span(element: &ElementRef, attribute: &str) -> usize {
+    element
+        .value()
+        .attr(attribute)
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(1)
+        .max(1)
+}
+
+// Continues cells spanning down from earlier rows into the current one
+fn fill_spanned(grid_row: &mut Vec<Cell>, spanning: &mut HashMap<usize, (Cell, usize)>) {
+    while let Some((cell, rows_left)) = spanning.get_mut(&grid_row.len()) {
+        grid_row.push(cell.clone());
+        *rows_left -= 1;
+        if *rows_left == 0 {
+            spanning.remove(&(grid_row.len() - 1));
+        }
+    }
+}
+
+fn heading(row: &[Cell], column: usize) -> Option<&str> {
+    row.get(column).map(|c| c.text.as_str())
+}
+
+// Lays a table out as a grid, repeating cells across their rowspan and colspan
+fn table_grid(table: &ElementRef) -> Vec<Vec<Cell>> {
+    let row_selector = Selector::parse("tr").unwrap();
+    let mut grid = Vec::new();
+    let mut spanning: HashMap<usize, (Cell, usize)> = HashMap::new(); // column -> (cell, rows left)
+
+    for row in table.select(&row_selector) {
+        let cells = row
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|c| c.value().name() == "td" || c.value().name() == "th");
+        let mut grid_row = Vec::new();
+        for element in cells {
+            fill_spanned(&mut grid_row, &mut spanning);
+            let cell = Cell {
+                text: cell_text(&element),
+                header: element.value().name() == "th",
+            };
+            let rowspan = span(&element, "rowspan");
+            for _ in 0..span(&element, "colspan") {
+                if rowspan > 1 {
+                    spanning.insert(grid_row.len(), (cell.clone(), rowspan - 1));
+                }
+                grid_row.push(cell.clone());
+            }
+        }
+        fill_spanned(&mut grid_row, &mut spanning);
+
+        if !grid_row.is_empty() {
+            grid.push(grid_row);
+        }
+    }
+
+    grid
+}
+
+struct TableLayout {
+    constituency_column: usize,
+    party_columns: Vec<(usize, String)>,
+    header_rows: usize,
+}
+
+// Works out which columns hold what from the header rows. Returns None for
+// tables that aren't constituency results tables.
+fn table_layout(
+    grid: &[Vec<Cell>],
+    config: &ScrapeConfig,
+    subdivision: Option<&str>,
+) -> Result<Option<TableLayout>, Box<dyn Error>> {
+    let header_rows = grid
+        .iter()
+        .take_while(|row| row.iter().all(|cell| cell.header))
+        .count();
+    if header_rows == 0 {
+        return Ok(None);
+    }
+    let headers = &grid[..header_rows];
+    let columns = headers.iter().map(Vec::len).max().unwrap_or(0);
+
+    let constituency_column = (0..columns).find(|&column| {
+        headers.iter().any(|row| {
+            heading(row, column)
+                .is_some_and(|h| h.eq_ignore_ascii_case(&config.constituency_header))
+        })
+    });
+
+    let mut party_columns = Vec::new();
+    let mut unknown = Vec::new();
+    for column in 0..columns {
+        let group = heading(&headers[0], column);
+        let label = heading(&headers[header_rows - 1], column);
+        let (Some(group), Some(label)) = (group, label) else {
+            continue;
+        };
+        if !group.eq_ignore_ascii_case(&config.votes_header)
+            || label.eq_ignore_ascii_case(&config.votes_header)
+        {
+            continue;
+        }
+        match config.party_for_heading(label) {
+            Some(party) => party_columns.push((column, party.clone())),
+            None => unknown.push(label.to_string()),
+        }
+    }
+
+    let Some(constituency_column) = constituency_column else {
+        return Ok(None);
+    };
+    if !unknown.is_empty() {
+        return Err(format!(
+            "{} table has party columns with no header alias: {}",
+            subdivision.unwrap_or("results"),
+            unknown.join(", ")
+        )
+        .into());
+    }
+    if party_columns.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(TableLayout {
+        constituency_column,
+        party_columns,
+        header_rows,
+    }))
+}
+
+// Finds the results tables on a page, appending them to `results_tables`
+fn collect_results_tables(
+    html: &str,
+    config: &ScrapeConfig,
+    results_tables: &mut Vec<(Vec<Vec<Cell>>, TableLayout)>,
+) -> Result<(), Box<dyn Error>> {
+    let document = Html::parse_document(html);
+    let table_selector = Selector::parse(&config.table_selector)
+        .map_err(|_| format!("invalid table selector '{}'", config.table_selector))?;
+
+    for table in document.select(&table_selector) {
+        let grid = table_grid(&table);
+        let subdivision = config
+            .subdivisions
+            .get(results_tables.len())
+            .map(String::as_str);
+        if let Some(layout) = table_layout(&grid, config, subdivision)? {
+            results_tables.push((grid, layout));
+        }
+    }
+
+    Ok(())
+}
+
+// Empty cells and dashes mean the party didn't stand; anything else must be
+// a number
+fn vote_count(text: &str) -> Option<u32> {
+    match text {
+        "" | "–" => Some(0),
+        _ => text.replace(',', "").parse().ok(),
+    }
+}
+
+fn read_results_tables(
+    results_tables: &[(Vec<Vec<Cell>>, TableLayout)],
+    config: &ScrapeConfig,
+) -> Result<ElectionResult, Box<dyn Error>> {
+    if results_tables.len() != config.subdivisions.len() {
+        return Err(format!(
+            "expected {} results tables ({}), found {}",
+            config.subdivisions.len(),
+            config.subdivisions.join(", "),
+            results_tables.len()
+        )
+        .into());
+    }
+
+    let mut constituencies = Vec::new();
+    let mut overall_result = HashMap::new();
+
+    for ((grid, layout), table_name) in results_tables.iter().zip(&config.subdivisions) {
+        for (row, cells) in grid.iter().enumerate().skip(layout.header_rows) {
+            let Some(constituency_cell) = cells.get(layout.constituency_column) else {
+                continue;
+            };
+            let constituency_name = constituency_cell.text.clone();
+            if constituency_name.is_empty() {
+                continue;
+            }
+
+            let mut candidates = Vec::new();
+            let mut results = HashMap::new();
+
+            for (column, party_name) in &layout.party_columns {
+                let text = cells.get(*column).map_or("", |cell| cell.text.as_str());
+                let votes = vote_count(text).ok_or_else(|| {
+                    format!(
+                        "{} table, row {}, column {} ({}): '{}' isn't a vote count",
+                        table_name,
+                        row + 1,
+                        column + 1,
+                        party_name,
+                        text
+                    )
+                })?;
+                candidates.push(Candidate {
+                    name: format!("Candidate{}", candidates.len() + 1),
+                    party: Party {
+                        name: party_name.clone(),
+                    },
+                });
+                *overall_result.entry(party_name.clone()).or_insert(0) += votes;
+                results.insert(party_name.clone(), votes);
+            }
+
+            constituencies.push(ConstituencyResult {
+                constituency: Constituency {
+                    subdivision: Subdivision {
+                        name: table_name.to_string(),
+                    },
+                    name: constituency_name,
+                    candidates,
+                },
+                results,
+            });
+        }
+    }
+
+    // Without an election date in the spec, fall back to the scrape time
+    let datetime = config
+        .election_date
+        .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .unwrap_or_else(Utc::now);
+    let mut election_result = ElectionResult::new(datetime, constituencies, overall_result);
+    let metadata = &mut election_result.metadata;
+    metadata.election_date = config.election_date;
+    metadata.country = config.country.clone();
+    metadata.chamber = config.chamber.clone();
+    metadata.source = config.url.clone();
+    metadata.retrieved_at = Some(Utc::now());
+
+    for patch in &config.patches {
+        apply_patch_path(&mut election_result, patch)?;
+    }
+    Ok(election_result)
+}
+
+/// Parses the constituency results tables of a saved results page.
+pub fn parse_results_page(
+    html: &str,
+    config: &ScrapeConfig,
+) -> Result<ElectionResult, Box<dyn Error>> {
+    let mut results_tables = Vec::new();
+    collect_results_tables(html, config, &mut results_tables)?;
+    read_results_tables(&results_tables, config)
+}
+
+/// Parses a saved results page, or every `.html` page in a directory (in file
+/// name order, so the results tables can be split across pages) into a single
+/// `ElectionResult`.
+pub fn scrape_path<P: AsRef<Path>>(
+    path: P,
+    config: &ScrapeConfig,
+) -> Result<ElectionResult, Box<dyn Error>> {
+    let path = path.as_ref();
+    if !path.is_dir() {
+        let mut election_result = parse_results_page(&fs::read_to_string(path)?, config)?;
+        election_result.metadata.source = Some(path.display().to_string());
+        return Ok(election_result);
+    }
+
+    let mut pages: Vec<_> = fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    pages.retain(|page| {
+        page.extension()
+            .is_some_and(|ext| ext == "html" || ext == "htm")
+    });
+    pages.sort();
+    if pages.is_empty() {
+        return Err(format!("no .html files in {}", path.display()).into());
+    }
+
+    let mut results_tables = Vec::new();
+    for page in pages {
+        collect_results_tables(&fs::read_to_string(&page)?, config, &mut results_tables)?;
+    }
+    let mut election_result = read_results_tables(&results_tables, config)?;
+    election_result.metadata.source = Some(path.display().to_string());
+    Ok(election_result)
+}
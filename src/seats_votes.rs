@@ -0,0 +1,305 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Write;
+
+use crate::simulation::{simulate_election, ElectoralSystem};
+use crate::swing::{apply_swing, SwingModel, Swings};
+use crate::ElectionResult;
+
+// How close the thresholds found by bisection are, in percentage points. The
+// result is rounded up to the hundredth.
+const SWING_PRECISION: f64 = 0.01;
+
+// What a party is trying to win
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SeatTarget {
+    LargestParty, // More seats than any other party
+    Seats(u32),   // At least this many seats, such as 326
+    // More than half the seats taken up, leaving out these abstaining
+    // parties' seats (such as Sinn Féin's)
+    WorkingMajority(Vec<String>),
+}
+
+impl SeatTarget {
+    pub fn is_met(&self, party: &str, seats: &HashMap<String, u32>) -> bool {
+        let party_seats = seats.get(party).copied().unwrap_or(0);
+        match self {
+            SeatTarget::LargestParty => {
+                party_seats > 0
+                    && seats
+                        .iter()
+                        .all(|(other, &s)| other == party || s < party_seats)
+            }
+            SeatTarget::Seats(majority) => party_seats >= *majority,
+            SeatTarget::WorkingMajority(abstaining) => {
+                let sitting: u32 = seats
+                    .iter()
+                    .filter(|(other, _)| !abstaining.contains(other))
+                    .map(|(_, &s)| s)
+                    .sum();
+                !abstaining.iter().any(|a| a == party) && party_seats > sitting / 2
+            }
+        }
+    }
+}
+
+// One point on a party's seats-votes curve
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CurvePoint {
+    pub swing: f64,      // Applied to the party, in percentage points
+    pub vote_share: f64, // The party's national share after the swing, in points
+    pub seats: u32,
+    pub seat_share: f64, // Fraction of the seats
+    pub largest_party: bool,
+    pub majority: bool,
+}
+
+// The swings a party needs to become the largest party and to win a majority
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwingThresholds {
+    pub party: String,
+    pub vote_share: f64, // Before any swing, in points
+    pub seats: u32,
+    pub largest_party_swing: Option<f64>,
+    pub majority_swing: Option<f64>,
+}
+
+/// Projects swings to a single party under a single-winner system. A swing of
+/// `x` points moves `x` points of the national vote to the party from all the
+/// others, in proportion to their national shares, and is applied to each
+/// constituency under the chosen model.
+pub struct SwingCalculator<'a> {
+    election_result: &'a ElectionResult,
+    national_shares: HashMap<String, f64>, // In points
+    electoral_system: ElectoralSystem,
+    preference_flows: Option<&'a HashMap<String, HashMap<String, f32>>>,
+    model: SwingModel,
+}
+
+impl<'a> SwingCalculator<'a> {
+    pub fn new(
+        election_result: &'a ElectionResult,
+        electoral_system: &ElectoralSystem,
+        preference_flows: Option<&'a HashMap<String, HashMap<String, f32>>>,
+        model: SwingModel,
+    ) -> Result<Self, Box<dyn Error>> {
+        if electoral_system.needs_preference_flows() && preference_flows.is_none() {
+            return Err(format!("{} needs preference flows", electoral_system.name()).into());
+        }
+        if !electoral_system.is_single_winner() {
+            return Err(format!(
+                "{} doesn't elect one member per constituency",
+                electoral_system.name()
+            )
+            .into());
+        }
+        let mut votes: HashMap<String, u32> = HashMap::new();
+        for constituency_result in &election_result.constituencies {
+            for (party, &party_votes) in &constituency_result.results {
+                *votes.entry(party.clone()).or_default() += party_votes;
+            }
+        }
+        let total_votes: u32 = votes.values().sum();
+        let national_shares = votes
+            .into_iter()
+            .map(|(party, v)| (party, v as f64 / total_votes.max(1) as f64 * 100.0))
+            .collect();
+        Ok(SwingCalculator {
+            election_result,
+            national_shares,
+            electoral_system: electoral_system.clone(),
+            preference_flows,
+            model,
+        })
+    }
+
+    /// The party's share of the vote and everyone's seats after a swing.
+    pub fn outcome(
+        &self,
+        party: &str,
+        swing: f64,
+    ) -> Result<(f64, HashMap<String, u32>), Box<dyn Error>> {
+        let share = self.national_shares.get(party).copied().unwrap_or(0.0);
+        let swings: HashMap<String, f64> = self
+            .national_shares
+            .iter()
+            .map(|(other, &other_share)| {
+                let other_swing = if other == party {
+                    swing
+                } else if share < 100.0 {
+                    -swing * other_share / (100.0 - share)
+                } else {
+                    0.0
+                };
+                (other.clone(), other_swing)
+            })
+            .collect();
+        let projected = apply_swing(self.election_result, self.model, &Swings::from(swings))?;
+        let total_votes: u32 = projected.overall_result.values().sum();
+        let party_votes = projected.overall_result.get(party).copied().unwrap_or(0);
+        let vote_share = party_votes as f64 / total_votes.max(1) as f64 * 100.0;
+        let seats = simulate_election(
+            &projected,
+            &self.electoral_system,
+            self.preference_flows.cloned(),
+        )?;
+        Ok((vote_share, seats))
+    }
+
+    /// The smallest swing at which `party` meets `target`, found by bisection
+    /// to within a hundredth of a point. It's negative if the party meets the
+    /// target already and could lose that much, and `None` if even taking
+    /// every other party's votes where it stands isn't enough. Assumes the
+    /// party's seats don't fall as its share rises.
+    pub fn swing_needed(
+        &self,
+        party: &str,
+        target: &SeatTarget,
+    ) -> Result<Option<f64>, Box<dyn Error>> {
+        let share = self.party_share(party)?;
+        let is_met = |swing: f64| -> Result<bool, Box<dyn Error>> {
+            Ok(target.is_met(party, &self.outcome(party, swing)?.1))
+        };
+
+        let (mut low, mut high) = (-share, 100.0 - share);
+        if !is_met(high)? {
+            return Ok(None);
+        }
+        if is_met(low)? {
+            return Ok(Some(low));
+        }
+        while high - low > SWING_PRECISION {
+            let middle = (low + high) / 2.0;
+            if is_met(middle)? {
+                high = middle;
+            } else {
+                low = middle;
+            }
+        }
+        // Rounded up, so the swing reported is one that meets the target
+        Ok(Some((high * 100.0).ceil() / 100.0))
+    }
+
+    pub fn thresholds(
+        &self,
+        party: &str,
+        majority: &SeatTarget,
+    ) -> Result<SwingThresholds, Box<dyn Error>> {
+        let (vote_share, seats) = self.outcome(party, 0.0)?;
+        Ok(SwingThresholds {
+            party: party.to_string(),
+            vote_share,
+            seats: seats.get(party).copied().unwrap_or(0),
+            largest_party_swing: self.swing_needed(party, &SeatTarget::LargestParty)?,
+            majority_swing: self.swing_needed(party, majority)?,
+        })
+    }
+
+    /// Sweeps the swing to `party` from `from` to `to` points in steps of
+    /// `step`, giving its vote share and seats at each.
+    pub fn seats_votes_curve(
+        &self,
+        party: &str,
+        from: f64,
+        to: f64,
+        step: f64,
+        majority: &SeatTarget,
+    ) -> Result<Vec<CurvePoint>, Box<dyn Error>> {
+        if step <= 0.0 || from > to {
+            return Err("the sweep needs a positive step from a lower to a higher swing".into());
+        }
+        self.party_share(party)?;
+
+        let steps = ((to - from) / step + 1e-9).floor() as usize;
+        (0..=steps)
+            .map(|i| {
+                let swing = from + i as f64 * step;
+                let (vote_share, seats) = self.outcome(party, swing)?;
+                let total_seats: u32 = seats.values().sum();
+                let party_seats = seats.get(party).copied().unwrap_or(0);
+                Ok(CurvePoint {
+                    swing,
+                    vote_share,
+                    seats: party_seats,
+                    seat_share: party_seats as f64 / total_seats.max(1) as f64,
+                    largest_party: SeatTarget::LargestParty.is_met(party, &seats),
+                    majority: majority.is_met(party, &seats),
+                })
+            })
+            .collect()
+    }
+
+    // The party's national share in points, which must be more than zero to
+    // be swung under every model
+    fn party_share(&self, party: &str) -> Result<f64, Box<dyn Error>> {
+        let (share, _) = self.outcome(party, 0.0)?;
+        if share == 0.0 {
+            return Err(format!("{} has no votes to swing", party).into());
+        }
+        Ok(share)
+    }
+}
+
+/// The majority `election_result`'s parliament needs by default: more than
+/// half of its constituencies.
+pub fn default_majority(election_result: &ElectionResult) -> SeatTarget {
+    SeatTarget::Seats(election_result.constituencies.len() as u32 / 2 + 1)
+}
+
+pub fn write_curve_csv<W: Write>(
+    writer: W,
+    party: &str,
+    curve: &[CurvePoint],
+) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record([
+        "party",
+        "swing",
+        "vote_share",
+        "seats",
+        "seat_share",
+        "largest_party",
+        "majority",
+    ])?;
+    for point in curve {
+        csv_writer.write_record([
+            party,
+            &format!("{:.2}", point.swing),
+            &format!("{:.2}", point.vote_share),
+            &point.seats.to_string(),
+            &format!("{:.4}", point.seat_share),
+            &point.largest_party.to_string(),
+            &point.majority.to_string(),
+        ])?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
+
+pub fn write_thresholds_csv<W: Write>(
+    writer: W,
+    thresholds: &[SwingThresholds],
+) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record([
+        "party",
+        "vote_share",
+        "seats",
+        "largest_party_swing",
+        "majority_swing",
+    ])?;
+    let swing = |swing: Option<f64>| swing.map_or(String::new(), |s| format!("{:.2}", s));
+    for party in thresholds {
+        csv_writer.write_record([
+            &party.party,
+            &format!("{:.2}", party.vote_share),
+            &party.seats.to_string(),
+            &swing(party.largest_party_swing),
+            &swing(party.majority_swing),
+        ])?;
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,478 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::registry::PartyRegistry;
+use crate::{ConstituencyResult, ElectionResult};
+
+// Constituencies grouped into each multi-member STV district. The data has no
+// geography, so districts are runs of constituencies in file order.
+const STV_DISTRICT_SIZE: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ElectoralSystem {
+    #[serde(rename = "fptp")]
+    FirstPastThePost,
+    // National list PR, with as many seats as constituencies
+    #[serde(rename = "pr")]
+    ProportionalRepresentation,
+    #[serde(rename = "av")]
+    AlternativeVote, // Added for AV system
+    // List PR within each subdivision, with as many seats as it has constituencies
+    #[serde(rename = "regional_pr")]
+    RegionalProportionalRepresentation,
+    // STV in districts of up to STV_DISTRICT_SIZE constituencies from the same
+    // subdivision, taken in file order
+    #[serde(rename = "stv")]
+    SingleTransferableVote,
+    // FPTP constituencies topped up with as many national list seats
+    #[serde(rename = "mmp")]
+    MixedMemberProportional,
+}
+
+impl ElectoralSystem {
+    pub const ALL: [ElectoralSystem; 6] = [
+        ElectoralSystem::FirstPastThePost,
+        ElectoralSystem::AlternativeVote,
+        ElectoralSystem::ProportionalRepresentation,
+        ElectoralSystem::RegionalProportionalRepresentation,
+        ElectoralSystem::SingleTransferableVote,
+        ElectoralSystem::MixedMemberProportional,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ElectoralSystem::FirstPastThePost => "fptp",
+            ElectoralSystem::ProportionalRepresentation => "pr",
+            ElectoralSystem::AlternativeVote => "av",
+            ElectoralSystem::RegionalProportionalRepresentation => "regional_pr",
+            ElectoralSystem::SingleTransferableVote => "stv",
+            ElectoralSystem::MixedMemberProportional => "mmp",
+        }
+    }
+
+    // Systems that transfer votes between parties
+    pub fn needs_preference_flows(&self) -> bool {
+        matches!(
+            self,
+            ElectoralSystem::AlternativeVote | ElectoralSystem::SingleTransferableVote
+        )
+    }
+
+    // Systems that elect one member per constituency, so have
+    // `constituency_winners`
+    pub fn is_single_winner(&self) -> bool {
+        matches!(
+            self,
+            ElectoralSystem::FirstPastThePost | ElectoralSystem::AlternativeVote
+        )
+    }
+}
+
+/// Counts the seats each party wins under `electoral_system`. AV and STV need
+/// preference flows; it's an error to leave them out.
+pub fn simulate_election(
+    election_result: &ElectionResult,
+    electoral_system: &ElectoralSystem,
+    preference_flows: Option<HashMap<String, HashMap<String, f32>>>,
+) -> Result<HashMap<String, u32>, Box<dyn Error>> {
+    if electoral_system.needs_preference_flows() && preference_flows.is_none() {
+        return Err(format!("{} needs preference flows", electoral_system.name()).into());
+    }
+    let seats = match electoral_system {
+        ElectoralSystem::FirstPastThePost => simulate_first_past_the_post(election_result),
+        ElectoralSystem::ProportionalRepresentation => {
+            simulate_proportional_representation(election_result)
+        }
+        ElectoralSystem::AlternativeVote => {
+            simulate_alternative_vote(election_result, preference_flows.unwrap_or_default())
+        }
+        ElectoralSystem::RegionalProportionalRepresentation => {
+            simulate_regional_proportional_representation(election_result)
+        }
+        ElectoralSystem::SingleTransferableVote => simulate_single_transferable_vote(
+            election_result,
+            &preference_flows.unwrap_or_default(),
+        ),
+        ElectoralSystem::MixedMemberProportional => {
+            simulate_mixed_member_proportional(election_result)
+        }
+    };
+    Ok(seats)
+}
+
+fn simulate_first_past_the_post(election_result: &ElectionResult) -> HashMap<String, u32> {
+    count_seats(
+        election_result
+            .constituencies
+            .iter()
+            .map(first_past_the_post_winner),
+    )
+}
+
+// Ties go to the alphabetically first party, so results don't vary by run
+fn first_past_the_post_winner(constituency_result: &ConstituencyResult) -> Option<String> {
+    constituency_result
+        .results
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(party, _)| party.clone())
+}
+
+fn count_seats(winners: impl Iterator<Item = Option<String>>) -> HashMap<String, u32> {
+    let mut seat_wins: HashMap<String, u32> = HashMap::new();
+    for winner in winners.flatten() {
+        *seat_wins.entry(winner).or_insert(0) += 1;
+    }
+    seat_wins
+}
+
+fn simulate_proportional_representation(election_result: &ElectionResult) -> HashMap<String, u32> {
+    let seats = election_result.constituencies.len() as u32;
+    dhondt(&election_result.overall_result, seats, &HashMap::new())
+}
+
+fn simulate_regional_proportional_representation(
+    election_result: &ElectionResult,
+) -> HashMap<String, u32> {
+    let mut seats = HashMap::new();
+    for (subdivision_seats, votes) in subdivision_totals(election_result) {
+        for (party, party_seats) in dhondt(&votes, subdivision_seats, &HashMap::new()) {
+            *seats.entry(party).or_insert(0) += party_seats;
+        }
+    }
+    seats
+}
+
+fn simulate_mixed_member_proportional(election_result: &ElectionResult) -> HashMap<String, u32> {
+    // List seats go to the parties furthest below their proportional share,
+    // counting the constituencies they already won, as in the Scottish AMS
+    let mut seats = simulate_first_past_the_post(election_result);
+    let list_seats = election_result.constituencies.len() as u32;
+    for (party, party_seats) in dhondt(&election_result.overall_result, list_seats, &seats) {
+        *seats.entry(party).or_insert(0) += party_seats;
+    }
+    seats
+}
+
+// Seats and votes by party in each subdivision, in order of first appearance
+fn subdivision_totals(election_result: &ElectionResult) -> Vec<(u32, HashMap<String, u32>)> {
+    let mut subdivisions: Vec<(&str, u32, HashMap<String, u32>)> = Vec::new();
+    for constituency_result in &election_result.constituencies {
+        let name = constituency_result.constituency.subdivision.name.as_str();
+        let index = match subdivisions.iter().position(|(n, _, _)| *n == name) {
+            Some(index) => index,
+            None => {
+                subdivisions.push((name, 0, HashMap::new()));
+                subdivisions.len() - 1
+            }
+        };
+        let (_, seats, votes) = &mut subdivisions[index];
+        *seats += 1;
+        for (party, &party_votes) in &constituency_result.results {
+            *votes.entry(party.clone()).or_insert(0) += party_votes;
+        }
+    }
+    subdivisions
+        .into_iter()
+        .map(|(_, seats, votes)| (seats, votes))
+        .collect()
+}
+
+/// Allocates `seats` by the D'Hondt highest averages method. Parties already
+/// holding seats in `held` have their divisors start from there, and only the
+/// newly allocated seats are returned. Ties go to the party with more votes,
+/// then the alphabetically first.
+pub fn dhondt(
+    votes: &HashMap<String, u32>,
+    seats: u32,
+    held: &HashMap<String, u32>,
+) -> HashMap<String, u32> {
+    let mut parties: Vec<(&String, u64)> = votes
+        .iter()
+        .filter(|(_, &v)| v > 0)
+        .map(|(party, &v)| (party, v as u64))
+        .collect();
+    parties.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+    let mut allocated: HashMap<String, u32> = HashMap::new();
+    for _ in 0..seats {
+        let divisor = |party: &String| {
+            let held = held.get(party).copied().unwrap_or(0);
+            (held + allocated.get(party).copied().unwrap_or(0) + 1) as u64
+        };
+        // The first party with the highest average; averages are compared by
+        // cross-multiplying so there's no rounding
+        let mut best: Option<(&String, u64, u64)> = None;
+        for &(party, party_votes) in &parties {
+            let party_divisor = divisor(party);
+            let better = match best {
+                None => true,
+                Some((_, best_votes, best_divisor)) => {
+                    party_votes * best_divisor > best_votes * party_divisor
+                }
+            };
+            if better {
+                best = Some((party, party_votes, party_divisor));
+            }
+        }
+        match best {
+            Some((party, _, _)) => *allocated.entry(party.clone()).or_insert(0) += 1,
+            None => break,
+        }
+    }
+    allocated
+}
+
+fn simulate_single_transferable_vote(
+    election_result: &ElectionResult,
+    preference_flows: &HashMap<String, HashMap<String, f32>>,
+) -> HashMap<String, u32> {
+    let mut seats = HashMap::new();
+    let mut subdivisions: Vec<&str> = Vec::new();
+    for constituency_result in &election_result.constituencies {
+        let name = constituency_result.constituency.subdivision.name.as_str();
+        if !subdivisions.contains(&name) {
+            subdivisions.push(name);
+        }
+    }
+    for subdivision in subdivisions {
+        let constituencies: Vec<&ConstituencyResult> = election_result
+            .constituencies
+            .iter()
+            .filter(|c| c.constituency.subdivision.name == subdivision)
+            .collect();
+        for district in constituencies.chunks(STV_DISTRICT_SIZE) {
+            let mut votes = HashMap::new();
+            for constituency_result in district {
+                for (party, &party_votes) in &constituency_result.results {
+                    *votes.entry(party.clone()).or_insert(0.0) += party_votes as f64;
+                }
+            }
+            let district_seats =
+                single_transferable_vote_seats(votes, district.len() as u32, preference_flows);
+            for (party, party_seats) in district_seats {
+                *seats.entry(party).or_insert(0) += party_seats;
+            }
+        }
+    }
+    seats
+}
+
+/// Counts an STV election in one district at the level of parties, assuming
+/// each party's voters rank all its candidates first. A party reaching the
+/// Droop quota wins a seat and keeps its surplus for its next candidate; when
+/// nobody reaches it, the weakest party is eliminated and its votes transfer
+/// by its preference flows to the parties still in the count. Once there are
+/// no more parties left than seats, each remaining party wins one.
+pub fn single_transferable_vote_seats(
+    mut votes: HashMap<String, f64>,
+    seats: u32,
+    preference_flows: &HashMap<String, HashMap<String, f32>>,
+) -> HashMap<String, u32> {
+    let total: f64 = votes.values().sum();
+    let quota = (total / (seats as f64 + 1.0)).floor() + 1.0;
+    let mut elected: HashMap<String, u32> = HashMap::new();
+    let mut seats_left = seats;
+    votes.retain(|_, v| *v > 0.0);
+
+    while seats_left > 0 && !votes.is_empty() {
+        // Continuing parties, strongest first
+        let mut continuing: Vec<(String, f64)> =
+            votes.iter().map(|(party, &v)| (party.clone(), v)).collect();
+        continuing.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let (strongest, strongest_votes) = &continuing[0];
+        if *strongest_votes >= quota {
+            *elected.entry(strongest.clone()).or_insert(0) += 1;
+            seats_left -= 1;
+            let remaining = strongest_votes - quota;
+            if remaining > 0.0 {
+                votes.insert(strongest.clone(), remaining);
+            } else {
+                votes.remove(strongest);
+            }
+            continue;
+        }
+
+        if continuing.len() as u32 <= seats_left {
+            for (party, _) in &continuing {
+                *elected.entry(party.clone()).or_insert(0) += 1;
+            }
+            break;
+        }
+
+        let (weakest, weakest_votes) = continuing.last().unwrap();
+        votes.remove(weakest);
+        let Some(flows) = preference_flows.get(weakest) else {
+            continue; // No flows, so its votes exhaust
+        };
+        let flows_total: f32 = flows
+            .iter()
+            .filter(|(to, _)| votes.contains_key(*to))
+            .map(|(_, flow)| flow)
+            .sum();
+        if flows_total == 0.0 {
+            continue;
+        }
+        for (to, &flow) in flows {
+            if let Some(v) = votes.get_mut(to) {
+                *v += weakest_votes * (flow / flows_total) as f64;
+            }
+        }
+    }
+    elected
+}
+
+fn simulate_alternative_vote(
+    election_result: &ElectionResult,
+    preference_flows: HashMap<String, HashMap<String, f32>>,
+) -> HashMap<String, u32> {
+    count_seats(
+        election_result
+            .constituencies
+            .iter()
+            .map(|c| alternative_vote_winner(c, &preference_flows)),
+    )
+}
+
+fn alternative_vote_winner(
+    constituency_result: &ConstituencyResult,
+    preference_flows: &HashMap<String, HashMap<String, f32>>,
+) -> Option<String> {
+    let mut votes = constituency_result.results.clone();
+    let mut eliminated = Vec::new();
+    while votes.len() > 2 {
+        // Find the party with the minimum votes and remove it. As under
+        // FPTP, ties favour the party whose name sorts first, so the last
+        // of the tied parties goes out.
+        let (min_party, min_votes) = votes
+            .iter()
+            .min_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+            .map(|(party, &votes)| (party.clone(), votes))
+            .unwrap();
+
+        votes.remove(&min_party);
+        eliminated.push(min_party.clone());
+
+        let remaining_parties: Vec<_> = votes.keys().cloned().collect();
+        for (party, &party_votes) in preference_flows.get(&min_party).unwrap_or(&HashMap::new()) {
+            if remaining_parties.contains(party) {
+                let additional_votes = ((min_votes as f32) * party_votes).round() as u32;
+                // println!("Party {} gets {} additional votes", party, additional_votes);
+                *votes.get_mut(party).unwrap() += additional_votes;
+            }
+        }
+
+        // Redistribute votes for the eliminated party proportionally if it was previously allocated to eliminated parties
+        let mut redistributed_votes = 0;
+        if !eliminated.is_empty() {
+            for (party, &percentage) in preference_flows.get(&min_party).unwrap_or(&HashMap::new())
+            {
+                if eliminated.contains(party) {
+                    let redistributed = ((min_votes as f32) * percentage).round() as u32;
+                    // println!("Redistributed {} for {}", redistributed, party);
+                    redistributed_votes += redistributed;
+                }
+            }
+
+            let total_remaining_percentage: f32 = remaining_parties
+                .iter()
+                .map(|p| {
+                    preference_flows
+                        .get(&min_party)
+                        .unwrap_or(&HashMap::new())
+                        .get(p)
+                        .cloned()
+                        .unwrap_or(0.0)
+                })
+                .sum();
+
+            for party in &remaining_parties {
+                if let Some(&party_votes) = preference_flows
+                    .get(&min_party)
+                    .unwrap_or(&HashMap::new())
+                    .get(party)
+                {
+                    let proportional_share =
+                        (party_votes / total_remaining_percentage) * redistributed_votes as f32;
+                    *votes.get_mut(party).unwrap() += proportional_share.round() as u32;
+                }
+            }
+        }
+    }
+
+    // Determine the winner among the last two remaining parties
+    votes
+        .iter()
+        .max_by(|a, b| a.1.cmp(b.1).then(b.0.cmp(a.0)))
+        .map(|(party, _)| party.clone())
+}
+
+/// The winner of each constituency, in the order of
+/// `election_result.constituencies`, or `None` for systems that don't elect
+/// one member per constituency. Constituencies with no votes have no winner.
+pub fn constituency_winners(
+    election_result: &ElectionResult,
+    electoral_system: &ElectoralSystem,
+    preference_flows: Option<&HashMap<String, HashMap<String, f32>>>,
+) -> Option<Vec<Option<String>>> {
+    let constituencies = election_result.constituencies.iter();
+    match electoral_system {
+        ElectoralSystem::FirstPastThePost => {
+            Some(constituencies.map(first_past_the_post_winner).collect())
+        }
+        ElectoralSystem::AlternativeVote => {
+            let preference_flows = preference_flows?;
+            Some(
+                constituencies
+                    .map(|c| alternative_vote_winner(c, preference_flows))
+                    .collect(),
+            )
+        }
+        ElectoralSystem::ProportionalRepresentation
+        | ElectoralSystem::RegionalProportionalRepresentation
+        | ElectoralSystem::SingleTransferableVote
+        | ElectoralSystem::MixedMemberProportional => None,
+    }
+}
+
+/// Loads a preference flows file, normalising its party labels through
+/// `registry` if there is one.
+pub fn load_preference_flows<P: AsRef<Path>>(
+    path: P,
+    registry: Option<&PartyRegistry>,
+) -> Result<HashMap<String, HashMap<String, f32>>, Box<dyn Error>> {
+    let file_content = fs::read_to_string(path)?;
+    let preference_flows = serde_json::from_str(&file_content)?;
+    Ok(match registry {
+        Some(registry) => registry.normalise_preference_flows(&preference_flows),
+        None => preference_flows,
+    })
+}
+
+/// Builds a placeholder preference flow map giving every party a flat 0.5 flow to
+/// each party it stood against.
+pub fn get_preference_flows(
+    election_result: &ElectionResult,
+) -> HashMap<String, HashMap<String, f32>> {
+    let mut preference_flows = HashMap::new();
+
+    for constituency_result in &election_result.constituencies {
+        for candidate in &constituency_result.constituency.candidates {
+            if !preference_flows.contains_key(&candidate.party.name) {
+                let mut flow = HashMap::new();
+                for other_candidate in &constituency_result.constituency.candidates {
+                    if candidate.party.name != other_candidate.party.name {
+                        flow.insert(other_candidate.party.name.clone(), 0.5);
+                    }
+                }
+                preference_flows.insert(candidate.party.name.clone(), flow);
+            }
+        }
+    }
+
+    preference_flows
+}
@@ -0,0 +1,409 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+use crate::simulation::{constituency_winners, simulate_election, ElectoralSystem};
+use crate::{ConstituencyResult, ElectionResult};
+
+// A constituency whose winner differs between the baseline and a projection
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeatChange {
+    pub constituency: String,
+    pub subdivision: String,
+    pub from: String,
+    pub to: String,
+}
+
+// How a national change in vote share is translated into each constituency
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwingModel {
+    // Every constituency moves by the national change in percentage points
+    #[default]
+    Uniform,
+    // Each party's vote scales by the ratio of its new to old national share
+    Proportional,
+    // Losing parties lose in proportion to their local strength, and the
+    // votes they lose go to the gaining parties in proportion to their
+    // national gains. A simplification in the spirit of Electoral Calculus's
+    // strong transition model, not their published model: it has no split
+    // between strong and weak supporters.
+    StrongTransition,
+    // Halfway between uniform and proportional swing: each party's new local
+    // share is the mean of what the two models give
+    Hybrid,
+}
+
+impl SwingModel {
+    pub const ALL: [SwingModel; 4] = [
+        SwingModel::Uniform,
+        SwingModel::Proportional,
+        SwingModel::StrongTransition,
+        SwingModel::Hybrid,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SwingModel::Uniform => "uniform",
+            SwingModel::Proportional => "proportional",
+            SwingModel::StrongTransition => "strong_transition",
+            SwingModel::Hybrid => "hybrid",
+        }
+    }
+}
+
+// What produced a projection, so results can say which model was used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProjectionModel {
+    Swing(SwingModel),
+    TransitionMatrix,
+}
+
+impl ProjectionModel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProjectionModel::Swing(SwingModel::Uniform) => "uniform swing",
+            ProjectionModel::Swing(SwingModel::Proportional) => "proportional swing",
+            ProjectionModel::Swing(SwingModel::StrongTransition) => "strong transition swing",
+            ProjectionModel::Swing(SwingModel::Hybrid) => "hybrid swing",
+            ProjectionModel::TransitionMatrix => "transition matrix",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Projection {
+    pub model: ProjectionModel,
+    pub result: ElectionResult, // The projected constituency results
+    pub seats: HashMap<String, u32>,
+    pub baseline_seats: HashMap<String, u32>,
+    pub changes: Vec<SeatChange>, // Empty for systems without constituency winners
+}
+
+// Changes in vote share in percentage points, by party. A subdivision's own
+// swings take precedence over the national swing for the parties they name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Swings {
+    #[serde(default)]
+    pub national: HashMap<String, f64>,
+    #[serde(default)]
+    pub regional: HashMap<String, HashMap<String, f64>>, // Subdivision -> party -> points
+}
+
+impl From<HashMap<String, f64>> for Swings {
+    fn from(national: HashMap<String, f64>) -> Self {
+        Swings {
+            national,
+            regional: HashMap::new(),
+        }
+    }
+}
+
+impl Swings {
+    /// The swings that take each party from its share of the vote in
+    /// `baseline` to a target share, nationally and within subdivisions.
+    /// Parties that didn't stand in the baseline (or that subdivision) are
+    /// skipped, since there are no votes to swing.
+    pub fn to_target_shares(
+        baseline: &ElectionResult,
+        national: &HashMap<String, f64>,
+        regional: &HashMap<String, HashMap<String, f64>>,
+    ) -> Swings {
+        let swings_to = |shares: HashMap<String, f64>, targets: &HashMap<String, f64>| {
+            targets
+                .iter()
+                .filter_map(|(party, target)| {
+                    shares
+                        .get(party)
+                        .map(|share| (party.clone(), target - share))
+                })
+                .collect()
+        };
+
+        Swings {
+            national: swings_to(vote_shares(baseline.constituencies.iter()), national),
+            regional: regional
+                .iter()
+                .map(|(subdivision, targets)| {
+                    let shares = vote_shares(
+                        baseline
+                            .constituencies
+                            .iter()
+                            .filter(|c| c.constituency.subdivision.name == *subdivision),
+                    );
+                    (subdivision.clone(), swings_to(shares, targets))
+                })
+                .collect(),
+        }
+    }
+}
+
+// Vote shares in percentage points across a set of constituencies
+fn vote_shares<'a>(
+    constituencies: impl Iterator<Item = &'a ConstituencyResult>,
+) -> HashMap<String, f64> {
+    let mut votes: HashMap<String, u32> = HashMap::new();
+    for constituency_result in constituencies {
+        for (party, &party_votes) in &constituency_result.results {
+            *votes.entry(party.clone()).or_insert(0) += party_votes;
+        }
+    }
+    let total: u32 = votes.values().sum();
+    votes
+        .into_iter()
+        .map(|(party, party_votes)| {
+            let share = if total == 0 {
+                0.0
+            } else {
+                party_votes as f64 / total as f64 * 100.0
+            };
+            (party, share)
+        })
+        .collect()
+}
+
+fn check_swing_parties(
+    model: SwingModel,
+    scope: &str,
+    base_shares: &HashMap<String, f64>,
+    swings: &HashMap<String, f64>,
+) -> Result<(), Box<dyn Error>> {
+    for (party, &swing) in swings {
+        match base_shares.get(party) {
+            None => return Err(format!("no {} votes to swing in {}", party, scope).into()),
+            Some(&share) if share == 0.0 && swing != 0.0 && model != SwingModel::Uniform => {
+                return Err(format!(
+                    "{} has no votes in {} to scale under {} swing",
+                    party,
+                    scope,
+                    model.name()
+                )
+                .into())
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// A constituency's new shares under `model`, before rescaling to 100%
+fn swung_shares(
+    model: SwingModel,
+    shares: &HashMap<&String, f64>,
+    base_shares: &HashMap<String, f64>,
+    swings: &HashMap<String, f64>,
+) -> HashMap<String, f64> {
+    let swing = |party: &String| swings.get(party).copied().unwrap_or(0.0);
+    // How much of a party's vote it keeps (or gains, if over 1) across the
+    // area its swing applies to
+    let ratio = |party: &String| {
+        let base_share = base_shares[party];
+        if base_share == 0.0 {
+            1.0
+        } else {
+            ((base_share + swing(party)) / base_share).max(0.0)
+        }
+    };
+
+    match model {
+        SwingModel::Uniform => shares
+            .iter()
+            .map(|(&party, share)| (party.clone(), share + swing(party)))
+            .collect(),
+        SwingModel::Proportional => shares
+            .iter()
+            .map(|(&party, share)| (party.clone(), share * ratio(party)))
+            .collect(),
+        SwingModel::StrongTransition => {
+            let national_loss: f64 = swings.values().filter(|s| **s < 0.0).map(|s| -s).sum();
+            let local_loss: f64 = shares
+                .iter()
+                .filter(|(&party, _)| swing(party) < 0.0)
+                .map(|(&party, share)| share * (1.0 - ratio(party)))
+                .sum();
+            shares
+                .iter()
+                .map(|(&party, share)| {
+                    let swing = swing(party);
+                    let new_share = if swing < 0.0 {
+                        share * ratio(party)
+                    } else if national_loss > 0.0 {
+                        // Where the losing parties were strong there is more
+                        // to gain
+                        share + local_loss * swing / national_loss
+                    } else {
+                        share + swing
+                    };
+                    (party.clone(), new_share)
+                })
+                .collect()
+        }
+        SwingModel::Hybrid => shares
+            .iter()
+            .map(|(&party, share)| {
+                let uniform = share + swing(party);
+                let proportional = share * ratio(party);
+                (party.clone(), (uniform + proportional) / 2.0)
+            })
+            .collect(),
+    }
+}
+
+/// Applies a swing under `model`: `swings` gives each party's change in vote
+/// share in percentage points, nationally or within a subdivision, and a
+/// regional swing is measured against the party's share in that subdivision.
+/// Shares pushed below zero are clamped, and each constituency is rescaled so
+/// its turnout is unchanged, taking any shortfall from the other parties in
+/// proportion to their shares. Parties only gain votes where they stood.
+pub fn apply_swing(
+    election_result: &ElectionResult,
+    model: SwingModel,
+    swings: &Swings,
+) -> Result<ElectionResult, Box<dyn Error>> {
+    let national_shares = vote_shares(election_result.constituencies.iter());
+    check_swing_parties(model, "this election", &national_shares, &swings.national)?;
+
+    // Each subdivision's swings, falling back to the national swing, and the
+    // shares they are measured against
+    let mut regions = HashMap::new();
+    for (subdivision, regional_swings) in &swings.regional {
+        let mut constituencies = election_result
+            .constituencies
+            .iter()
+            .filter(|c| c.constituency.subdivision.name == *subdivision)
+            .peekable();
+        if constituencies.peek().is_none() {
+            return Err(format!("no constituencies in subdivision {}", subdivision).into());
+        }
+        let regional_shares = vote_shares(constituencies);
+        check_swing_parties(model, subdivision, &regional_shares, regional_swings)?;
+
+        let mut region_swings = swings.national.clone();
+        let mut base_shares = national_shares.clone();
+        for (party, &swing) in regional_swings {
+            region_swings.insert(party.clone(), swing);
+            base_shares.insert(party.clone(), regional_shares[party]);
+        }
+        regions.insert(subdivision.as_str(), (region_swings, base_shares));
+    }
+
+    let mut projected = election_result.clone();
+    for constituency_result in &mut projected.constituencies {
+        let total: u32 = constituency_result.results.values().sum();
+        if total == 0 {
+            continue;
+        }
+        let (swings, base_shares) = regions
+            .get(constituency_result.constituency.subdivision.name.as_str())
+            .map(|(swings, base_shares)| (swings, base_shares))
+            .unwrap_or((&swings.national, &national_shares));
+
+        // Parties listed with no votes didn't stand, so they gain nothing
+        let shares: HashMap<&String, f64> = constituency_result
+            .results
+            .iter()
+            .filter(|(_, &votes)| votes > 0)
+            .map(|(party, &votes)| (party, votes as f64 / total as f64 * 100.0))
+            .collect();
+        // In party order, so the total and the rounding are the same every run
+        let new_shares: BTreeMap<String, f64> = swung_shares(model, &shares, base_shares, swings)
+            .into_iter()
+            .map(|(party, share)| (party, share.max(0.0)))
+            .collect();
+        // Rescaling to 100% redistributes whatever clamping (or a set of swings
+        // that doesn't sum to zero) added or removed
+        let total_share: f64 = new_shares.values().sum();
+        if total_share == 0.0 {
+            continue;
+        }
+
+        for (party, share) in new_shares {
+            let votes = (share / total_share * total as f64).round() as u32;
+            constituency_result.results.insert(party, votes);
+        }
+    }
+
+    projected.recompute_overall_result();
+    projected.metadata.content_hash = None;
+    Ok(projected)
+}
+
+/// Lists the constituencies whose winner differs between two sets of winners
+/// for the same constituencies.
+pub fn seat_changes(
+    election_result: &ElectionResult,
+    before: &[Option<String>],
+    after: &[Option<String>],
+) -> Vec<SeatChange> {
+    election_result
+        .constituencies
+        .iter()
+        .zip(before.iter().zip(after))
+        .filter_map(|(constituency_result, winners)| match winners {
+            (Some(from), Some(to)) if from != to => Some(SeatChange {
+                constituency: constituency_result.constituency.name.clone(),
+                subdivision: constituency_result.constituency.subdivision.name.clone(),
+                from: from.clone(),
+                to: to.clone(),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Projects an election under a swing and simulates both the baseline and the
+/// projection under `electoral_system`.
+pub fn project(
+    baseline: &ElectionResult,
+    model: SwingModel,
+    swings: &Swings,
+    electoral_system: &ElectoralSystem,
+    preference_flows: Option<&HashMap<String, HashMap<String, f32>>>,
+) -> Result<Projection, Box<dyn Error>> {
+    let projected = apply_swing(baseline, model, swings)?;
+    compare_with_baseline(
+        baseline,
+        projected,
+        ProjectionModel::Swing(model),
+        electoral_system,
+        preference_flows,
+    )
+}
+
+/// Simulates a baseline election and a projection of it under
+/// `electoral_system`, listing the seats that change hands.
+pub fn compare_with_baseline(
+    baseline: &ElectionResult,
+    projected: ElectionResult,
+    model: ProjectionModel,
+    electoral_system: &ElectoralSystem,
+    preference_flows: Option<&HashMap<String, HashMap<String, f32>>>,
+) -> Result<Projection, Box<dyn Error>> {
+    if electoral_system.needs_preference_flows() && preference_flows.is_none() {
+        return Err(format!(
+            "projecting under {} needs preference flows",
+            electoral_system.name()
+        )
+        .into());
+    }
+
+    let simulate = |election_result: &ElectionResult| {
+        simulate_election(election_result, electoral_system, preference_flows.cloned())
+    };
+    let changes = match (
+        constituency_winners(baseline, electoral_system, preference_flows),
+        constituency_winners(&projected, electoral_system, preference_flows),
+    ) {
+        (Some(before), Some(after)) => seat_changes(baseline, &before, &after),
+        _ => Vec::new(),
+    };
+
+    Ok(Projection {
+        model,
+        seats: simulate(&projected)?,
+        baseline_seats: simulate(baseline)?,
+        changes,
+        result: projected,
+    })
+}
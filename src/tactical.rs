@@ -0,0 +1,136 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::ElectionResult;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TacticalVoting {
+    // Fraction of a party's supporters willing to vote tactically
+    pub fraction: f64,
+    // Overrides of `fraction` for particular parties
+    #[serde(default)]
+    pub party_fractions: HashMap<String, f64>,
+    // Points behind the local leader at which a party is out of contention
+    pub margin: f64,
+    // Party -> parties its tactical voters would lend their vote to, with the
+    // fraction of them willing to vote for each. Same shape as preference flows.
+    pub acceptability: HashMap<String, HashMap<String, f32>>,
+}
+
+impl TacticalVoting {
+    fn fraction_for(&self, party: &str) -> f64 {
+        self.party_fractions
+            .get(party)
+            .copied()
+            .unwrap_or(self.fraction)
+    }
+}
+
+pub fn load_tactical_voting<P: AsRef<Path>>(path: P) -> Result<TacticalVoting, Box<dyn Error>> {
+    let file_content = fs::read_to_string(path)?;
+    let tactical_voting: TacticalVoting = serde_json::from_str(&file_content)?;
+    validate_tactical_voting(&tactical_voting)?;
+    Ok(tactical_voting)
+}
+
+pub fn validate_tactical_voting(tactical_voting: &TacticalVoting) -> Result<(), Box<dyn Error>> {
+    let fractions = std::iter::once(("tactical voters", tactical_voting.fraction)).chain(
+        tactical_voting
+            .party_fractions
+            .iter()
+            .map(|(party, &fraction)| (party.as_str(), fraction)),
+    );
+    for (whose, fraction) in fractions {
+        if !(0.0..=1.0).contains(&fraction) {
+            return Err(format!("fraction for {} ({}) is not a fraction", whose, fraction).into());
+        }
+    }
+    if tactical_voting.margin < 0.0 {
+        return Err(format!("margin {} is negative", tactical_voting.margin).into());
+    }
+    for (from, row) in &tactical_voting.acceptability {
+        for (to, &fraction) in row {
+            if to == from {
+                return Err(format!("{} can't be an alternative to itself", from).into());
+            }
+            if !(0.0..=1.0).contains(&fraction) {
+                return Err(format!(
+                    "acceptability of {} to {} voters ({}) is not a fraction",
+                    to, from, fraction
+                )
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Models tactical voting in every constituency. A party more than `margin`
+/// points behind the local leader is out of contention, and the willing
+/// fraction of its supporters lends their votes to the best-placed party they
+/// find acceptable, provided that party is itself in contention. Contention is
+/// judged on the results before anyone switches, so switches don't cascade.
+pub fn apply_tactical_voting(
+    election_result: &ElectionResult,
+    tactical_voting: &TacticalVoting,
+) -> Result<ElectionResult, Box<dyn Error>> {
+    validate_tactical_voting(tactical_voting)?;
+
+    let mut projected = election_result.clone();
+    for constituency_result in &mut projected.constituencies {
+        let votes_cast: u32 = constituency_result.results.values().sum();
+        if votes_cast == 0 {
+            continue;
+        }
+        let share = |votes: u32| votes as f64 / votes_cast as f64 * 100.0;
+        let leader_share = share(
+            constituency_result
+                .results
+                .values()
+                .copied()
+                .max()
+                .unwrap_or(0),
+        );
+        let in_contention = |votes: u32| leader_share - share(votes) <= tactical_voting.margin;
+
+        let mut moves = Vec::new();
+        for (from, &votes) in &constituency_result.results {
+            if in_contention(votes) {
+                continue;
+            }
+            let Some(row) = tactical_voting.acceptability.get(from) else {
+                continue;
+            };
+            // Ties go to the alphabetically first party so results don't
+            // depend on map order
+            let best = row
+                .iter()
+                .filter(|(_, &fraction)| fraction > 0.0)
+                .filter_map(|(to, &fraction)| {
+                    let to_votes = *constituency_result.results.get(to)?;
+                    Some((to, to_votes, fraction))
+                })
+                .filter(|&(_, to_votes, _)| in_contention(to_votes))
+                .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(a.0)));
+            if let Some((to, _, fraction)) = best {
+                let moved = votes as f64 * tactical_voting.fraction_for(from) * fraction as f64;
+                moves.push((from.clone(), to.clone(), moved.round() as u32));
+            }
+        }
+        for (from, to, moved) in moves {
+            if let Some(v) = constituency_result.results.get_mut(&from) {
+                *v -= moved;
+            }
+            if let Some(v) = constituency_result.results.get_mut(&to) {
+                *v += moved;
+            }
+        }
+    }
+
+    projected.recompute_overall_result();
+    projected.metadata.content_hash = None;
+    Ok(projected)
+}
@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::simulation::ElectoralSystem;
+use crate::swing::{compare_with_baseline, Projection, ProjectionModel};
+use crate::ElectionResult;
+
+// Pseudo-party for people who didn't vote. Flows from it bring non-voters out;
+// flows to it model voters staying at home.
+pub const DID_NOT_VOTE: &str = "DNV";
+
+// Turnout assumed when estimating non-voters; roughly the UK's at the 2024
+// general election
+pub const DEFAULT_BASELINE_TURNOUT: f64 = 0.6;
+
+// How far a row of the matrix may be from summing to 1
+const ROW_TOLERANCE: f32 = 0.001;
+
+pub fn load_transition_matrix<P: AsRef<Path>>(
+    path: P,
+) -> Result<HashMap<String, HashMap<String, f32>>, Box<dyn Error>> {
+    let file_content = fs::read_to_string(path)?;
+    let matrix = serde_json::from_str(&file_content)?;
+    validate_transition_matrix(&matrix)?;
+    Ok(matrix)
+}
+
+/// Checks that every row of a transition matrix is a set of non-negative
+/// fractions summing to 1. A row must say where all of a party's voters go,
+/// including the ones who stay.
+pub fn validate_transition_matrix(
+    matrix: &HashMap<String, HashMap<String, f32>>,
+) -> Result<(), Box<dyn Error>> {
+    for (from, row) in matrix {
+        if let Some((to, fraction)) = row.iter().find(|(_, &fraction)| fraction < 0.0) {
+            return Err(format!(
+                "transition from {} to {} is negative ({})",
+                from, to, fraction
+            )
+            .into());
+        }
+        let total: f32 = row.values().sum();
+        if (total - 1.0).abs() > ROW_TOLERANCE {
+            return Err(format!("transitions from {} sum to {}, not 1", from, total).into());
+        }
+    }
+    Ok(())
+}
+
+/// Moves voters between parties in every constituency: `matrix[from][to]` is
+/// the fraction of `from`'s voters who now vote for `to`. Parties without a row
+/// keep all their voters. Every party in the matrix other than `DNV` must have
+/// stood somewhere; where a destination didn't stand in a constituency, its
+/// share of the row is split among the destinations that did.
+///
+/// The data files don't record electorates, so the number of non-voters in
+/// each constituency is estimated from `baseline_turnout`.
+pub fn apply_transitions(
+    election_result: &ElectionResult,
+    matrix: &HashMap<String, HashMap<String, f32>>,
+    baseline_turnout: f64,
+) -> Result<ElectionResult, Box<dyn Error>> {
+    validate_transition_matrix(matrix)?;
+    if !(baseline_turnout > 0.0 && baseline_turnout <= 1.0) {
+        return Err(format!("baseline turnout {} is not a fraction", baseline_turnout).into());
+    }
+    let stood = |party: &String| {
+        party == DID_NOT_VOTE
+            || election_result
+                .constituencies
+                .iter()
+                .any(|c| c.results.contains_key(party))
+    };
+    for (from, row) in matrix {
+        if !stood(from) {
+            return Err(format!("no {} voters to move in this election", from).into());
+        }
+        // Likely a typo or a party from another election, which would
+        // otherwise quietly have its share split among the rest
+        if let Some(to) = row.keys().find(|to| !stood(to)) {
+            return Err(format!(
+                "transitions from {} go to {}, which didn't stand in this election",
+                from, to
+            )
+            .into());
+        }
+    }
+
+    let mut projected = election_result.clone();
+    for constituency_result in &mut projected.constituencies {
+        let votes_cast: u32 = constituency_result.results.values().sum();
+        let non_voters = votes_cast as f64 * (1.0 - baseline_turnout) / baseline_turnout;
+
+        let mut sources: Vec<(&str, f64)> = constituency_result
+            .results
+            .iter()
+            .map(|(party, &votes)| (party.as_str(), votes as f64))
+            .collect();
+        sources.push((DID_NOT_VOTE, non_voters));
+
+        let mut new_votes: HashMap<String, f64> = constituency_result
+            .results
+            .keys()
+            .map(|party| (party.clone(), 0.0))
+            .collect();
+        for (from, votes) in sources {
+            let Some(row) = matrix.get(from) else {
+                // No row: everyone stays put
+                if let Some(v) = new_votes.get_mut(from) {
+                    *v += votes;
+                }
+                continue;
+            };
+
+            let available = |to: &String| to == DID_NOT_VOTE || new_votes.contains_key(to);
+            let available_total: f32 = row
+                .iter()
+                .filter(|(to, _)| available(to))
+                .map(|(_, fraction)| fraction)
+                .sum();
+            if available_total == 0.0 {
+                continue; // Nobody they'd move to stood, so they abstain
+            }
+            let moves: Vec<(String, f64)> = row
+                .iter()
+                .filter(|(to, _)| available(to))
+                .map(|(to, &fraction)| (to.clone(), votes * (fraction / available_total) as f64))
+                .collect();
+            for (to, moved) in moves {
+                if let Some(v) = new_votes.get_mut(&to) {
+                    *v += moved;
+                }
+            }
+        }
+
+        constituency_result.results = new_votes
+            .into_iter()
+            .map(|(party, votes)| (party, votes.round() as u32))
+            .collect();
+    }
+
+    projected.recompute_overall_result();
+    projected.metadata.content_hash = None;
+    Ok(projected)
+}
+
+/// Projects an election under a transition matrix and simulates both the
+/// baseline and the projection under `electoral_system`.
+pub fn project_transitions(
+    baseline: &ElectionResult,
+    matrix: &HashMap<String, HashMap<String, f32>>,
+    baseline_turnout: f64,
+    electoral_system: &ElectoralSystem,
+    preference_flows: Option<&HashMap<String, HashMap<String, f32>>>,
+) -> Result<Projection, Box<dyn Error>> {
+    let projected = apply_transitions(baseline, matrix, baseline_turnout)?;
+    compare_with_baseline(
+        baseline,
+        projected,
+        ProjectionModel::TransitionMatrix,
+        electoral_system,
+        preference_flows,
+    )
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use crate::schema::{detect_schema_version, parse_election_data, CURRENT_SCHEMA_VERSION};
+use crate::ElectionResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Warning, // Worth a look, but the data can still be used
+    Error,   // The data shouldn't be used as it is
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub constituency: Option<String>,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    fn new(severity: Severity, constituency: Option<&str>, message: String) -> Self {
+        ValidationIssue {
+            severity,
+            constituency: constituency.map(str::to_string),
+            message,
+        }
+    }
+}
+
+/// Checks an election for problems that would make simulations misleading:
+/// missing or duplicated constituencies, results that don't match the recorded
+/// content hash or national totals, and candidates without results.
+pub fn validate_election(election_result: &ElectionResult) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    if election_result.constituencies.is_empty() {
+        issues.push(ValidationIssue::new(
+            Severity::Error,
+            None,
+            "election has no constituencies".to_string(),
+        ));
+    }
+    if let Err(err) = election_result.verify_content_hash() {
+        issues.push(ValidationIssue::new(Severity::Error, None, err));
+    }
+
+    let mut names = HashSet::new();
+    let mut totals: BTreeMap<&String, u32> = BTreeMap::new();
+    for constituency_result in &election_result.constituencies {
+        let name = constituency_result.constituency.name.as_str();
+        if !names.insert(name) {
+            issues.push(ValidationIssue::new(
+                Severity::Error,
+                Some(name),
+                "constituency appears more than once".to_string(),
+            ));
+        }
+        if constituency_result
+            .results
+            .values()
+            .all(|&votes| votes == 0)
+        {
+            issues.push(ValidationIssue::new(
+                Severity::Error,
+                Some(name),
+                "constituency has no votes".to_string(),
+            ));
+        }
+
+        let candidates: HashSet<&str> = constituency_result
+            .constituency
+            .candidates
+            .iter()
+            .map(|c| c.party.name.as_str())
+            .collect();
+        let mut parties: Vec<&String> = constituency_result.results.keys().collect();
+        parties.sort();
+        for party in parties {
+            if !candidates.contains(party.as_str()) {
+                issues.push(ValidationIssue::new(
+                    Severity::Warning,
+                    Some(name),
+                    format!("{} has votes but no candidate", party),
+                ));
+            }
+        }
+
+        let mut top: Vec<u32> = constituency_result.results.values().copied().collect();
+        top.sort_unstable_by(|a, b| b.cmp(a));
+        if top.len() > 1 && top[0] > 0 && top[0] == top[1] {
+            issues.push(ValidationIssue::new(
+                Severity::Warning,
+                Some(name),
+                "tie for first place".to_string(),
+            ));
+        }
+
+        for (party, &votes) in &constituency_result.results {
+            *totals.entry(party).or_default() += votes;
+        }
+    }
+
+    for (party, total) in totals {
+        let recorded = election_result.overall_result.get(party).copied();
+        if recorded != Some(total) {
+            issues.push(ValidationIssue::new(
+                Severity::Warning,
+                None,
+                format!(
+                    "{} has {} votes in its constituencies but {} in the national result",
+                    party,
+                    total,
+                    recorded.unwrap_or(0)
+                ),
+            ));
+        }
+    }
+    issues
+}
+
+/// Validates an election file, also noting if it needs migrating to the
+/// current schema version. Fails only if the file can't be read or parsed.
+pub fn validate_election_file<P: AsRef<Path>>(
+    path: P,
+) -> Result<Vec<ValidationIssue>, Box<dyn Error>> {
+    let file_content = fs::read_to_string(path)?;
+    let data: Value = serde_json::from_str(&file_content)?;
+    let version = detect_schema_version(&data)?;
+
+    let mut issues = Vec::new();
+    if version < CURRENT_SCHEMA_VERSION {
+        issues.push(ValidationIssue::new(
+            Severity::Warning,
+            None,
+            format!(
+                "file is at schema version {}; `migrate` upgrades it to {}",
+                version, CURRENT_SCHEMA_VERSION
+            ),
+        ));
+    }
+    issues.extend(validate_election(&parse_election_data(&file_content)?));
+    Ok(issues)
+}
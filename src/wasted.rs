@@ -0,0 +1,180 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::io::Write;
+
+use crate::simulation::{constituency_winners, ElectoralSystem};
+use crate::ElectionResult;
+
+// A party's wasted votes in one area. Votes are first preferences.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartyWastedVotes {
+    pub party: String,
+    pub votes: u32,
+    pub seats: u32,
+    pub lost_votes: u32,    // Cast where the party lost
+    pub surplus_votes: u32, // Beyond what its winners needed
+    pub wasted_votes: u32,  // Lost plus surplus
+    pub wasted_share: f64,  // Fraction of the party's own votes
+    // The party's wasted votes less its share of everyone's, as a fraction
+    // of all votes in the area. Positive means the system works against it.
+    pub efficiency_gap: f64,
+    pub votes_per_seat: Option<f64>, // None if it won no seats
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WastedVoteAnalysis {
+    pub system: ElectoralSystem,
+    pub national: Vec<PartyWastedVotes>, // By votes, largest first
+    pub subdivisions: BTreeMap<String, Vec<PartyWastedVotes>>,
+}
+
+#[derive(Default)]
+struct Tally {
+    votes: u32,
+    seats: u32,
+    lost_votes: u32,
+    surplus_votes: u32,
+}
+
+fn summarise(tallies: HashMap<&str, Tally>) -> Vec<PartyWastedVotes> {
+    let total_votes: u32 = tallies.values().map(|t| t.votes).sum();
+    let total_wasted: u32 = tallies
+        .values()
+        .map(|t| t.lost_votes + t.surplus_votes)
+        .sum();
+    let mut parties: Vec<PartyWastedVotes> = tallies
+        .into_iter()
+        .map(|(party, tally)| {
+            let wasted_votes = tally.lost_votes + tally.surplus_votes;
+            let (wasted_share, efficiency_gap) = if tally.votes == 0 {
+                (0.0, 0.0)
+            } else {
+                let vote_share = tally.votes as f64 / total_votes as f64;
+                (
+                    wasted_votes as f64 / tally.votes as f64,
+                    (wasted_votes as f64 - total_wasted as f64 * vote_share) / total_votes as f64,
+                )
+            };
+            PartyWastedVotes {
+                party: party.to_string(),
+                votes: tally.votes,
+                seats: tally.seats,
+                lost_votes: tally.lost_votes,
+                surplus_votes: tally.surplus_votes,
+                wasted_votes,
+                wasted_share,
+                efficiency_gap,
+                votes_per_seat: (tally.seats > 0).then(|| tally.votes as f64 / tally.seats as f64),
+            }
+        })
+        .collect();
+    parties.sort_by(|a, b| b.votes.cmp(&a.votes).then(a.party.cmp(&b.party)));
+    parties
+}
+
+/// Counts each party's wasted votes under a single-winner system, nationally
+/// and in each subdivision. Votes for a constituency's losers are wasted, as
+/// are a winner's votes beyond one more than the runner-up's first
+/// preferences; an AV winner who trailed on first preferences wastes none.
+pub fn analyse_wasted_votes(
+    election_result: &ElectionResult,
+    electoral_system: &ElectoralSystem,
+    preference_flows: Option<&HashMap<String, HashMap<String, f32>>>,
+) -> Result<WastedVoteAnalysis, Box<dyn Error>> {
+    if electoral_system.needs_preference_flows() && preference_flows.is_none() {
+        return Err(format!("{} needs preference flows", electoral_system.name()).into());
+    }
+    let winners = constituency_winners(election_result, electoral_system, preference_flows)
+        .ok_or_else(|| {
+            format!(
+                "{} doesn't elect one member per constituency",
+                electoral_system.name()
+            )
+        })?;
+
+    let mut national: HashMap<&str, Tally> = HashMap::new();
+    let mut subdivisions: BTreeMap<&str, HashMap<&str, Tally>> = BTreeMap::new();
+    for (constituency_result, winner) in election_result.constituencies.iter().zip(&winners) {
+        let subdivision = subdivisions
+            .entry(&constituency_result.constituency.subdivision.name)
+            .or_default();
+        for (party, &votes) in &constituency_result.results {
+            let (seats, lost_votes, surplus_votes) = if winner.as_ref() == Some(party) {
+                let runner_up = constituency_result
+                    .results
+                    .iter()
+                    .filter(|(other, _)| *other != party)
+                    .map(|(_, &v)| v)
+                    .max()
+                    .unwrap_or(0);
+                (1, 0, votes.saturating_sub(runner_up + 1))
+            } else {
+                (0, votes, 0)
+            };
+            for tallies in [&mut national, &mut *subdivision] {
+                let tally = tallies.entry(party).or_default();
+                tally.votes += votes;
+                tally.seats += seats;
+                tally.lost_votes += lost_votes;
+                tally.surplus_votes += surplus_votes;
+            }
+        }
+    }
+
+    Ok(WastedVoteAnalysis {
+        system: electoral_system.clone(),
+        national: summarise(national),
+        subdivisions: subdivisions
+            .into_iter()
+            .map(|(name, tallies)| (name.to_string(), summarise(tallies)))
+            .collect(),
+    })
+}
+
+/// Writes the analysis as CSV with one row per area and party. The national
+/// rows come first, with an `area` of `national`.
+pub fn write_wasted_votes_csv<W: Write>(
+    writer: W,
+    analysis: &WastedVoteAnalysis,
+) -> Result<(), Box<dyn Error>> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record([
+        "area",
+        "party",
+        "votes",
+        "seats",
+        "lost_votes",
+        "surplus_votes",
+        "wasted_votes",
+        "wasted_share",
+        "efficiency_gap",
+        "votes_per_seat",
+    ])?;
+    let areas = std::iter::once(("national", &analysis.national)).chain(
+        analysis
+            .subdivisions
+            .iter()
+            .map(|(name, parties)| (name.as_str(), parties)),
+    );
+    for (area, parties) in areas {
+        for party in parties {
+            csv_writer.write_record([
+                area,
+                &party.party,
+                &party.votes.to_string(),
+                &party.seats.to_string(),
+                &party.lost_votes.to_string(),
+                &party.surplus_votes.to_string(),
+                &party.wasted_votes.to_string(),
+                &format!("{:.4}", party.wasted_share),
+                &format!("{:.4}", party.efficiency_gap),
+                &party
+                    .votes_per_seat
+                    .map_or(String::new(), |v| format!("{:.0}", v)),
+            ])?;
+        }
+    }
+    csv_writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+// Fixtures shared by the integration tests. Each test file is its own crate
+// and uses only some of these.
+#![allow(dead_code)]
+
+use chrono::Utc;
+use std::collections::HashMap;
+
+use psephulator::{
+    Candidate, Constituency, ConstituencyResult, ElectionResult, Party, Subdivision,
+};
+
+// A constituency with a candidate for each party in `results`
+pub fn constituency(name: &str, subdivision: &str, results: &[(&str, u32)]) -> ConstituencyResult {
+    ConstituencyResult {
+        constituency: Constituency {
+            name: name.to_string(),
+            subdivision: Subdivision {
+                name: subdivision.to_string(),
+            },
+            candidates: results
+                .iter()
+                .map(|(party, _)| Candidate {
+                    name: format!("{} candidate", party),
+                    party: Party {
+                        name: party.to_string(),
+                    },
+                })
+                .collect(),
+        },
+        results: results.iter().map(|(p, v)| (p.to_string(), *v)).collect(),
+    }
+}
+
+// A safe Labour seat and a Labour-Conservative marginal in North, and a safe
+// Conservative seat in South
+pub fn three_seat_election() -> ElectionResult {
+    let mut election_result = ElectionResult::new(
+        Utc::now(),
+        vec![
+            constituency(
+                "Safe",
+                "North",
+                &[("LAB", 6000), ("CON", 3000), ("LD", 1000)],
+            ),
+            constituency(
+                "Marginal",
+                "North",
+                &[("LAB", 4200), ("CON", 4000), ("LD", 1800)],
+            ),
+            constituency(
+                "Tory",
+                "South",
+                &[("CON", 5000), ("LAB", 2000), ("LD", 300)],
+            ),
+        ],
+        HashMap::new(),
+    );
+    election_result.recompute_overall_result();
+    election_result
+}
@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use psephulator::comparison::{compare_systems, summarise_outcome, write_comparison_csv};
+use psephulator::schema::load_election_data;
+use psephulator::ElectoralSystem;
+
+fn counts(counts: &[(&str, u32)]) -> HashMap<String, u32> {
+    counts.iter().map(|(p, c)| (p.to_string(), *c)).collect()
+}
+
+#[test]
+fn test_compare_every_system_on_bundled_data() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+    let flows: HashMap<String, HashMap<String, f32>> =
+        psephulator::simulation::load_preference_flows("data/preference_flows_england.json", None)
+            .unwrap();
+    let comparisons =
+        compare_systems(&election_result, &ElectoralSystem::ALL, Some(&flows)).unwrap();
+    assert_eq!(comparisons.len(), 6);
+
+    let fptp = &comparisons[0];
+    assert_eq!(fptp.system, ElectoralSystem::FirstPastThePost);
+    assert_eq!((fptp.total_seats, fptp.majority), (650, 326));
+    assert_eq!(fptp.largest_party.as_deref(), Some("LAB"));
+    assert_eq!(fptp.majority_party.as_deref(), Some("LAB"));
+    assert_eq!(fptp.parties[0].party, "LAB");
+    assert_eq!(fptp.parties[0].seats, 411);
+
+    // List PR tracks the vote closely; FPTP doesn't
+    let pr = comparisons
+        .iter()
+        .find(|c| c.system == ElectoralSystem::ProportionalRepresentation)
+        .unwrap();
+    assert_eq!(pr.total_seats, 650);
+    assert!(pr.majority_party.is_none());
+    assert!(pr.indices.gallagher < 1.0 && fptp.indices.gallagher > 20.0);
+
+    let mmp = comparisons.last().unwrap();
+    assert_eq!(mmp.total_seats, 1300);
+
+    assert!(compare_systems(&election_result, &ElectoralSystem::ALL, None).is_err());
+
+    let mut csv = Vec::new();
+    write_comparison_csv(&mut csv, &comparisons[..1]).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("system,party,votes,vote_share,seats,seat_share,"));
+    assert!(csv.contains("\nfptp,LAB,9681159,0.3374,411,0.6323,LAB,LAB,23.73,30.08,48.22,1.880,4.71,2.24,4.04,1.73,0.0000\n"));
+}
+
+#[test]
+fn test_tied_largest_parties_are_hung() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+    let seats = counts(&[("LAB", 300), ("CON", 300), ("LD", 50)]);
+    let summary = summarise_outcome(&election_result, &ElectoralSystem::FirstPastThePost, &seats);
+    assert_eq!(summary.largest_party, None);
+    assert_eq!(summary.majority_party, None);
+    assert_eq!(summary.majority, 326);
+}
@@ -0,0 +1,125 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use psephulator::csv_io::{
+    read_election_csv, write_election_csv, write_seats_csv, ColumnMapping, CsvLayout,
+};
+
+const WIDE_CSV: &str = "\
+Seat,Nation,Labour,Conservative,Notes
+Aldershot,England,\"19,764\",14081,recount
+Glasgow East,Scotland,15543,1707,
+";
+
+const LONG_CSV: &str = "\
+constituency,candidate,party,votes
+Bradford West,Naz Shah,LAB,11724
+Bradford West,Muhammed Islam,IND,11017
+Bradford West,Akeel Hussain,IND,3547
+Aldershot,Alex Baker,LAB,19764
+";
+
+#[test]
+fn test_reads_wide_csv_with_mapped_party_columns() {
+    let mut mapping = ColumnMapping::wide("Seat", Some("Nation"));
+    mapping.parties = [("Labour", "LAB"), ("Conservative", "CON")]
+        .iter()
+        .map(|(c, p)| (c.to_string(), p.to_string()))
+        .collect();
+
+    let election_result = read_election_csv(WIDE_CSV.as_bytes(), &mapping, Utc::now()).unwrap();
+
+    assert_eq!(election_result.constituencies.len(), 2);
+    let aldershot = &election_result.constituencies[0];
+    assert_eq!(aldershot.results.get("LAB"), Some(&19764));
+    assert_eq!(
+        election_result.constituencies[1]
+            .constituency
+            .subdivision
+            .name,
+        "Scotland"
+    );
+    assert_eq!(election_result.overall_result.get("CON"), Some(&15788));
+}
+
+#[test]
+fn test_reads_wide_csv_using_every_unignored_column() {
+    let mut mapping = ColumnMapping::wide("Seat", Some("Nation"));
+    mapping.ignore = vec!["Notes".to_string()];
+
+    let election_result = read_election_csv(WIDE_CSV.as_bytes(), &mapping, Utc::now()).unwrap();
+
+    assert_eq!(election_result.overall_result.len(), 2);
+    assert_eq!(election_result.overall_result.get("Labour"), Some(&35307));
+}
+
+#[test]
+fn test_reads_long_csv_keeping_independents_apart() {
+    let mapping = ColumnMapping::long("constituency", None);
+
+    let election_result = read_election_csv(LONG_CSV.as_bytes(), &mapping, Utc::now()).unwrap();
+
+    let bradford = &election_result.constituencies[0];
+    assert_eq!(bradford.results.get("IND"), Some(&11017));
+    assert_eq!(bradford.results.get("IND2"), Some(&3547));
+    assert_eq!(bradford.constituency.candidates[0].name, "Naz Shah");
+    assert_eq!(election_result.overall_result.get("IND"), Some(&11017));
+    assert_eq!(election_result.overall_result.get("IND2"), Some(&3547));
+    assert_eq!(election_result.overall_result.get("LAB"), Some(&31488));
+}
+
+#[test]
+fn test_overall_result_matches_the_constituencies() {
+    let mapping = ColumnMapping::long("constituency", None);
+
+    let election_result = read_election_csv(LONG_CSV.as_bytes(), &mapping, Utc::now()).unwrap();
+
+    let mut totals: HashMap<String, u32> = HashMap::new();
+    for constituency_result in &election_result.constituencies {
+        for (party, &votes) in &constituency_result.results {
+            *totals.entry(party.clone()).or_default() += votes;
+        }
+    }
+    assert_eq!(election_result.overall_result, totals);
+}
+
+#[test]
+fn test_long_csv_round_trips() {
+    let mapping = ColumnMapping::long("constituency", Some("subdivision"));
+    let original = read_election_csv(
+        LONG_CSV.as_bytes(),
+        &ColumnMapping::long("constituency", None),
+        Utc::now(),
+    )
+    .unwrap();
+
+    let mut written = Vec::new();
+    write_election_csv(&mut written, &original, CsvLayout::Long).unwrap();
+    let reread = read_election_csv(written.as_slice(), &mapping, Utc::now()).unwrap();
+
+    assert_eq!(reread.constituencies.len(), original.constituencies.len());
+    assert_eq!(
+        reread.constituencies[0].results,
+        original.constituencies[0].results
+    );
+}
+
+#[test]
+fn test_writes_seat_and_vote_shares() {
+    let election_result = read_election_csv(
+        LONG_CSV.as_bytes(),
+        &ColumnMapping::long("constituency", None),
+        Utc::now(),
+    )
+    .unwrap();
+    let seats: HashMap<String, u32> = [("LAB".to_string(), 2)].into_iter().collect();
+
+    let mut written = Vec::new();
+    write_seats_csv(&mut written, &election_result, &seats).unwrap();
+    let written = String::from_utf8(written).unwrap();
+
+    assert!(written.starts_with("party,seats,seat_share,votes,vote_share\n"));
+    assert!(written.contains("LAB,2,1.0000,31488,"));
+    assert!(written.contains("IND,0,0.0000,11017,"));
+    assert!(written.contains("IND2,0,0.0000,3547,"));
+}
@@ -0,0 +1,86 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use psephulator::diff::{diff_outcomes, diff_winners, write_seat_matrix_csv};
+use psephulator::schema::load_election_data;
+use psephulator::swing::{apply_swing, SwingModel};
+use psephulator::{ElectionResult, ElectoralSystem};
+
+mod common;
+use common::constituency;
+
+fn winners(winners: &[Option<&str>]) -> Vec<Option<String>> {
+    winners.iter().map(|w| w.map(str::to_string)).collect()
+}
+
+#[test]
+fn test_diff_winners() {
+    let names = ["One", "Two", "Three", "Four", "Five"];
+    let election_result = ElectionResult::new(
+        Utc::now(),
+        names
+            .iter()
+            .map(|name| constituency(name, "Region", &[("A", 1)]))
+            .collect(),
+        HashMap::new(),
+    );
+    let before = winners(&[Some("CON"), Some("CON"), Some("LD"), Some("LAB"), None]);
+    let after = winners(&[
+        Some("LAB"),
+        Some("LAB"),
+        Some("CON"),
+        Some("LAB"),
+        Some("LD"),
+    ]);
+    let diff = diff_winners(&election_result, &before, &after);
+
+    assert_eq!(diff.changes.len(), 3);
+    assert_eq!(diff.changes[2].constituency, "Three");
+    assert_eq!(
+        (
+            diff.flows[0].from.as_str(),
+            diff.flows[0].to.as_str(),
+            diff.flows[0].seats
+        ),
+        ("CON", "LAB", 2)
+    );
+    assert_eq!(diff.net["LAB"], 2);
+    assert_eq!(diff.net["CON"], -1);
+    assert_eq!(diff.net["LD"], -1);
+    assert_eq!(diff.gains_and_losses("CON"), (1, 2));
+    assert_eq!(diff.matrix["LAB"]["LAB"], 1);
+
+    let mut csv = Vec::new();
+    write_seat_matrix_csv(&mut csv, &diff).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "from,CON,LAB,LD\nCON,0,2,0\nLAB,0,1,0\nLD,1,0,0\n"
+    );
+}
+
+#[test]
+fn test_diff_projection_against_baseline() {
+    let baseline = load_election_data("data/uk_2024.json").unwrap();
+    let swings = HashMap::from([("LAB".to_string(), -5.0), ("CON".to_string(), 5.0)]).into();
+    let mut projected = apply_swing(&baseline, SwingModel::Uniform, &swings).unwrap();
+    // Matching is by name, not position
+    projected.constituencies.reverse();
+
+    let fptp = ElectoralSystem::FirstPastThePost;
+    let diff = diff_outcomes(&baseline, &fptp, &projected, &fptp, None).unwrap();
+    assert!(diff.net["CON"] > 0 && diff.net["LAB"] < 0);
+    assert_eq!(diff.net.values().sum::<i64>(), 0);
+    let flows: u32 = diff.flows.iter().map(|f| f.seats).sum();
+    assert_eq!(flows as usize, diff.changes.len());
+
+    assert!(diff_outcomes(
+        &baseline,
+        &fptp,
+        &baseline,
+        &ElectoralSystem::AlternativeVote,
+        None
+    )
+    .is_err());
+    projected.constituencies.pop();
+    assert!(diff_outcomes(&baseline, &fptp, &projected, &fptp, None).is_err());
+}
@@ -0,0 +1,75 @@
+use chrono::Utc;
+
+use psephulator::datasets::{load_dataset_index, DATASET_INDEX};
+use psephulator::hoc::{import_hoc_csv, read_hoc_csv};
+
+#[test]
+fn test_imports_hoc_constituency_results() {
+    let election_result = import_hoc_csv("tests/fixtures/hoc_ge_sample.csv", Utc::now()).unwrap();
+
+    assert_eq!(election_result.constituencies.len(), 4);
+    assert_eq!(election_result.overall_result.get("CON"), Some(&42000));
+    assert_eq!(election_result.overall_result.get("APNI"), Some(&2000));
+    assert_eq!(election_result.overall_result.get("OTH"), Some(&500));
+
+    let scotland = &election_result.constituencies[2];
+    assert_eq!(scotland.constituency.name, "Samplebrough");
+    assert_eq!(scotland.constituency.subdivision.name, "Scotland");
+    assert!(!scotland.results.contains_key("BRX"));
+    assert!(scotland
+        .constituency
+        .candidates
+        .iter()
+        .any(|c| c.name == "Morag Campbell" && c.party.name == "SNP"));
+}
+
+#[test]
+fn test_rejects_non_numeric_vote_counts() {
+    let csv = "Constituency name,Country name,Con,Lab\nA,England,100,lots\n";
+
+    let err = read_hoc_csv(csv.as_bytes(), Utc::now()).unwrap_err();
+    assert!(err.to_string().contains("Lab"));
+}
+
+#[test]
+fn test_reads_snake_case_library_headers() {
+    let csv = "\
+ons_id,constituency_name,country_name,member_first_name,member_surname,first_party,electorate,con,lab,ruk,apni,all_other_candidates,of_which_other_winner
+E14000001,Aldershot,England,Alex,Baker,Lab,78553,14081,19764,8210,0,2437,0
+";
+
+    let election_result = read_hoc_csv(csv.as_bytes(), Utc::now()).unwrap();
+
+    let aldershot = &election_result.constituencies[0];
+    assert_eq!(aldershot.constituency.subdivision.name, "England");
+    assert_eq!(aldershot.results.get("REF"), Some(&8210));
+    assert_eq!(aldershot.results.get("OTH"), Some(&2437));
+    assert!(!aldershot.results.contains_key("ELECTORATE"));
+    assert!(aldershot
+        .constituency
+        .candidates
+        .iter()
+        .any(|c| c.name == "Alex Baker" && c.party.name == "LAB"));
+}
+
+#[test]
+fn test_rejects_unrecognised_columns() {
+    let csv = "Constituency name,Country name,Turnout,Con,Lab\nA,England,0.6,100,200\n";
+
+    let err = read_hoc_csv(csv.as_bytes(), Utc::now()).unwrap_err();
+    assert!(err.to_string().contains("Turnout"), "{}", err);
+}
+
+#[test]
+fn test_bundled_datasets_load() {
+    let index = load_dataset_index(DATASET_INDEX).unwrap();
+
+    for dataset in &index.datasets {
+        let election_result = index.load(dataset).unwrap();
+        assert!(
+            !election_result.constituencies.is_empty(),
+            "{} is empty",
+            dataset.id
+        );
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use psephulator::indices::{
+    dhondt_index, effective_number_of_parties, gallagher_index, golosov_number_of_parties,
+    loosemore_hanby_index, outcome_indices, sainte_lague_index, wasted_vote_share,
+};
+
+fn counts(counts: &[(&str, u32)]) -> HashMap<String, u32> {
+    counts.iter().map(|(p, c)| (p.to_string(), *c)).collect()
+}
+
+fn assert_close(actual: f64, expected: f64) {
+    assert!(
+        (actual - expected).abs() < 0.001,
+        "{} != {}",
+        actual,
+        expected
+    );
+}
+
+#[test]
+fn test_proportional_outcome() {
+    let votes = counts(&[("A", 50), ("B", 30), ("C", 20)]);
+    let indices = outcome_indices(&votes, &counts(&[("A", 5), ("B", 3), ("C", 2)]));
+    assert_close(indices.gallagher, 0.0);
+    assert_close(indices.loosemore_hanby, 0.0);
+    assert_close(indices.sainte_lague, 0.0);
+    assert_close(indices.dhondt, 1.0);
+    assert_close(
+        indices.effective_parties_by_votes,
+        indices.effective_parties_by_seats,
+    );
+    assert_close(indices.wasted_vote_share, 0.0);
+}
+
+#[test]
+fn test_disproportionality_indices() {
+    // A wins everything on half the vote: differences of 50, -30 and -20 points
+    let votes = counts(&[("A", 50), ("B", 30), ("C", 20)]);
+    let seats = counts(&[("A", 10)]);
+    assert_close(gallagher_index(&votes, &seats), 43.589);
+    assert_close(loosemore_hanby_index(&votes, &seats), 50.0);
+    // 50²/50 + 30²/30 + 20²/20
+    assert_close(sainte_lague_index(&votes, &seats), 100.0);
+    assert_close(dhondt_index(&votes, &seats), 2.0);
+    assert_close(wasted_vote_share(&votes, &seats), 0.5);
+}
+
+#[test]
+fn test_effective_number_of_parties() {
+    let even = counts(&[("A", 25), ("B", 25), ("C", 25), ("D", 25)]);
+    assert_close(effective_number_of_parties(&even), 4.0);
+    assert_close(golosov_number_of_parties(&even), 4.0);
+
+    // Shares of 0.5, 0.3 and 0.2
+    let uneven = counts(&[("A", 50), ("B", 30), ("C", 20), ("D", 0)]);
+    assert_close(effective_number_of_parties(&uneven), 1.0 / 0.38);
+    let golosov = 1.0 + 0.3 / (0.3 + 0.25 - 0.09) + 0.2 / (0.2 + 0.25 - 0.04);
+    assert_close(golosov_number_of_parties(&uneven), golosov);
+
+    assert_close(effective_number_of_parties(&HashMap::new()), 0.0);
+}
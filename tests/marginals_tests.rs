@@ -0,0 +1,105 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use psephulator::marginals::{marginality, target_list, write_target_list_csv};
+use psephulator::schema::load_election_data;
+use psephulator::ElectionResult;
+
+mod common;
+use common::constituency;
+
+fn election() -> ElectionResult {
+    ElectionResult::new(
+        Utc::now(),
+        vec![
+            constituency("Safe", "Region", &[("A", 700), ("B", 200), ("C", 100)]),
+            constituency("Close", "Region", &[("A", 450), ("B", 430), ("C", 120)]),
+            constituency("Held", "Region", &[("B", 500), ("A", 400), ("C", 100)]),
+            constituency("Empty", "Region", &[("A", 0), ("B", 0)]),
+        ],
+        HashMap::new(),
+    )
+}
+
+#[test]
+fn test_marginality() {
+    let seats = marginality(&election());
+    let names: Vec<&str> = seats.iter().map(|s| s.constituency.as_str()).collect();
+    assert_eq!(names, ["Close", "Held", "Safe"]);
+
+    let close = &seats[0];
+    assert_eq!(close.winner, "A");
+    assert_eq!(close.runner_up.as_deref(), Some("B"));
+    assert_eq!(close.majority_votes, 20);
+    assert!((close.majority_points - 2.0).abs() < 1e-9);
+    let c = &close.challengers[1];
+    assert_eq!((c.party.as_str(), c.votes_behind), ("C", 330));
+    assert!((c.swing_needed - 16.5).abs() < 1e-9);
+}
+
+#[test]
+fn test_target_and_defence_lists() {
+    let seats = marginality(&election());
+    let list = target_list(&seats, "B", None).unwrap();
+    assert_eq!((list.seats, list.majority, list.seats_needed), (1, 2, 1));
+    assert_eq!(list.targets[0].constituency, "Close");
+    assert_eq!(list.targets[0].seats_if_won, 2);
+    assert!((list.swing_for_majority.unwrap() - 1.0).abs() < 1e-9);
+    assert_eq!(list.targets[1].constituency, "Safe");
+    assert_eq!(list.defences.len(), 1);
+    assert_eq!(list.defences[0].challenger.as_deref(), Some("A"));
+    assert!((list.defences[0].swing_needed - 5.0).abs() < 1e-9);
+    assert_eq!(list.defences[0].seats_if_lost, 0);
+
+    let list = target_list(&seats, "A", None).unwrap();
+    assert_eq!((list.seats_needed, list.swing_for_majority), (0, None));
+    // C contests only three seats, so can never hold four
+    let list = target_list(&seats, "C", Some(4)).unwrap();
+    assert_eq!(list.swing_for_majority, None);
+    assert!(target_list(&seats, "D", None).is_err());
+
+    let mut csv = Vec::new();
+    write_target_list_csv(&mut csv, &target_list(&seats, "B", None).unwrap()).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.contains("\ntarget,1,Close,Region,A,20,1.00,2\n"));
+    assert!(csv.contains("\ndefence,1,Held,Region,A,100,5.00,0\n"));
+}
+
+#[test]
+fn test_parties_without_votes_are_not_challengers() {
+    let election_result = ElectionResult::new(
+        Utc::now(),
+        vec![
+            constituency("One", "Region", &[("A", 500), ("B", 300), ("X", 0)]),
+            constituency("Two", "Region", &[("B", 450), ("A", 400), ("X", 0)]),
+        ],
+        HashMap::new(),
+    );
+    let seats = marginality(&election_result);
+    assert!(seats
+        .iter()
+        .all(|s| s.challengers.iter().all(|c| c.party != "X")));
+
+    // X is listed everywhere but got no votes, so it didn't stand
+    assert!(target_list(&seats, "X", None).is_err());
+    let list = target_list(&seats, "A", None).unwrap();
+    assert_eq!(list.targets.len(), 1);
+}
+
+#[test]
+fn test_bundled_target_lists() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+    let seats = marginality(&election_result);
+    assert_eq!(seats.len(), 650);
+    assert!(seats
+        .windows(2)
+        .all(|w| w[0].majority_points <= w[1].majority_points));
+
+    let list = target_list(&seats, "CON", Some(326)).unwrap();
+    assert_eq!((list.seats, list.seats_needed), (121, 205));
+    assert_eq!(list.targets[204].seats_if_won, 326);
+    assert_eq!(
+        list.swing_for_majority,
+        Some(list.targets[204].swing_needed)
+    );
+}
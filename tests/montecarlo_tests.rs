@@ -0,0 +1,207 @@
+use psephulator::indices::outcome_indices;
+use psephulator::montecarlo::{
+    national_error_draws, run_monte_carlo, MonteCarloSettings, NationalError, PartyCorrelation,
+};
+use psephulator::schema::load_election_data;
+use psephulator::{simulate_election, ElectoralSystem};
+
+mod common;
+use common::three_seat_election;
+
+fn settings(runs: usize, seed: u64, national_error: NationalError) -> MonteCarloSettings {
+    MonteCarloSettings {
+        runs,
+        seed,
+        national_error,
+        constituency_sd: 1.0,
+        ..MonteCarloSettings::default()
+    }
+}
+
+fn normal(sd: f64) -> NationalError {
+    NationalError::Normal {
+        sd,
+        correlations: Vec::new(),
+    }
+}
+
+fn correlation(a: &str, b: &str, correlation: f64) -> PartyCorrelation {
+    PartyCorrelation {
+        parties: (a.to_string(), b.to_string()),
+        correlation,
+    }
+}
+
+// The sample correlation between two parties' national errors
+fn sample_correlation(settings: &MonteCarloSettings, a: &str, b: &str) -> f64 {
+    let draws = national_error_draws(&three_seat_election(), settings).unwrap();
+    let n = draws.len() as f64;
+    let mean = |party: &str| draws.iter().map(|d| d[party]).sum::<f64>() / n;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let covariance = |x: &str, mean_x: f64, y: &str, mean_y: f64| {
+        draws
+            .iter()
+            .map(|d| (d[x] - mean_x) * (d[y] - mean_y))
+            .sum::<f64>()
+            / n
+    };
+    covariance(a, mean_a, b, mean_b)
+        / (covariance(a, mean_a, a, mean_a) * covariance(b, mean_b, b, mean_b)).sqrt()
+}
+
+#[test]
+fn test_same_seed_gives_same_result() {
+    let election_result = three_seat_election();
+    let run = |seed| {
+        let result = run_monte_carlo(
+            &election_result,
+            &ElectoralSystem::FirstPastThePost,
+            None,
+            &settings(200, seed, normal(3.0)),
+        )
+        .unwrap();
+        serde_json::to_string(&result).unwrap()
+    };
+
+    assert_eq!(run(42), run(42));
+    assert_ne!(run(42), run(43));
+}
+
+#[test]
+fn test_without_error_every_run_matches_the_point_projection() {
+    let election_result = three_seat_election();
+    let mut no_error = settings(50, 1, normal(0.0));
+    no_error.constituency_sd = 0.0;
+    let result = run_monte_carlo(
+        &election_result,
+        &ElectoralSystem::FirstPastThePost,
+        None,
+        &no_error,
+    )
+    .unwrap();
+
+    let lab = &result.seats["LAB"];
+    assert_eq!((lab.p5, lab.p50, lab.p95), (2, 2, 2));
+    assert_eq!(lab.majority_probability, 1.0);
+    assert_eq!(lab.largest_party_probability, 1.0);
+    assert_eq!(result.majority, 2);
+    assert_eq!(result.hung_probability, 0.0);
+    assert_eq!(result.constituencies[1].win_probabilities["LAB"], 1.0);
+
+    let seats =
+        simulate_election(&election_result, &ElectoralSystem::FirstPastThePost, None).unwrap();
+    let expected = outcome_indices(&election_result.overall_result, &seats);
+    assert!((result.indices.gallagher - expected.gallagher).abs() < 0.01);
+    assert!(
+        (result.indices.effective_parties_by_seats - expected.effective_parties_by_seats).abs()
+            < 0.01
+    );
+}
+
+#[test]
+fn test_win_probabilities_and_percentiles() {
+    let election_result = three_seat_election();
+    let result = run_monte_carlo(
+        &election_result,
+        &ElectoralSystem::FirstPastThePost,
+        None,
+        &settings(500, 7, normal(3.0)),
+    )
+    .unwrap();
+
+    // The marginal changes hands in some runs, the safe seat never does
+    let marginal = &result.constituencies[1].win_probabilities;
+    assert!(marginal["LAB"] > 0.5 && marginal["CON"] > 0.05);
+    assert!((marginal.values().sum::<f64>() - 1.0).abs() < 1e-9);
+    assert_eq!(result.constituencies[0].win_probabilities["LAB"], 1.0);
+
+    let con = &result.seats["CON"];
+    assert!(con.p5 <= con.p50 && con.p50 <= con.p95);
+    assert_eq!(con.histogram.values().sum::<usize>(), 500);
+}
+
+#[test]
+fn test_dirichlet_errors_on_bundled_data() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+    let result = run_monte_carlo(
+        &election_result,
+        &ElectoralSystem::FirstPastThePost,
+        None,
+        &settings(
+            20,
+            3,
+            NationalError::Dirichlet {
+                concentration: 2000.0,
+            },
+        ),
+    )
+    .unwrap();
+
+    let point =
+        simulate_election(&election_result, &ElectoralSystem::FirstPastThePost, None).unwrap();
+    let lab = &result.seats["LAB"];
+    assert!(lab.p5 <= point["LAB"] + 40 && lab.p95 + 40 >= point["LAB"]);
+    assert_eq!(result.constituencies.len(), 650);
+    assert_eq!(result.majority, 326);
+
+    let invalid = settings(20, 3, NationalError::Dirichlet { concentration: 0.0 });
+    assert!(run_monte_carlo(
+        &election_result,
+        &ElectoralSystem::FirstPastThePost,
+        None,
+        &invalid
+    )
+    .is_err());
+}
+
+#[test]
+fn test_correlated_normal_errors() {
+    let independent = settings(4000, 5, normal(2.0));
+    let correlated = settings(
+        4000,
+        5,
+        NationalError::Normal {
+            sd: 2.0,
+            correlations: vec![correlation("LAB", "LD", 0.8)],
+        },
+    );
+
+    // Centring makes independent errors for three parties correlate at -0.5;
+    // with LAB and LD at 0.8 beforehand, theirs correlate at about 0.36
+    let before = sample_correlation(&independent, "LAB", "LD");
+    let after = sample_correlation(&correlated, "LAB", "LD");
+    assert!((before + 0.5).abs() < 0.05, "{}", before);
+    assert!((after - 0.36).abs() < 0.05, "{}", after);
+}
+
+#[test]
+fn test_rejects_inconsistent_or_unknown_correlations() {
+    let election_result = three_seat_election();
+    let with_correlations = |correlations| {
+        settings(
+            10,
+            0,
+            NationalError::Normal {
+                sd: 2.0,
+                correlations,
+            },
+        )
+    };
+
+    let inconsistent = with_correlations(vec![
+        correlation("LAB", "CON", 0.9),
+        correlation("LAB", "LD", 0.9),
+        correlation("CON", "LD", -0.9),
+    ]);
+    let unknown = with_correlations(vec![correlation("LAB", "GRN", 0.5)]);
+    for settings in [inconsistent, unknown] {
+        assert!(national_error_draws(&election_result, &settings).is_err());
+        assert!(run_monte_carlo(
+            &election_result,
+            &ElectoralSystem::FirstPastThePost,
+            None,
+            &settings
+        )
+        .is_err());
+    }
+}
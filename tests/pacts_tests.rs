@@ -0,0 +1,190 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use psephulator::pacts::{
+    apply_pact, load_pact, withdrawn_seats, Pact, Redistribution, SeatSelection, Withdrawal,
+};
+use psephulator::schema::load_election_data;
+use psephulator::{simulate_election, ElectionResult, ElectoralSystem};
+
+mod common;
+use common::constituency;
+
+fn three_seat_election() -> ElectionResult {
+    let mut election_result = ElectionResult::new(
+        Utc::now(),
+        vec![
+            constituency(
+                "Clacton",
+                "England",
+                &[("LAB", 5000), ("CON", 4000), ("REF", 3000)],
+            ),
+            constituency(
+                "Boston",
+                "England",
+                &[("LAB", 5000), ("REF", 4000), ("CON", 3000)],
+            ),
+            constituency(
+                "Newport",
+                "Wales",
+                &[("LAB", 5000), ("CON", 4500), ("REF", 1000)],
+            ),
+        ],
+        HashMap::new(),
+    );
+    election_result.recompute_overall_result();
+    election_result
+}
+
+fn flows() -> HashMap<String, HashMap<String, f32>> {
+    HashMap::from([
+        (
+            "REF".to_string(),
+            HashMap::from([("CON".to_string(), 0.8), ("LAB".to_string(), 0.2)]),
+        ),
+        (
+            "CON".to_string(),
+            HashMap::from([("REF".to_string(), 0.6), ("LAB".to_string(), 0.2)]),
+        ),
+    ])
+}
+
+fn withdrawal(party: &str, seats: SeatSelection) -> Withdrawal {
+    Withdrawal {
+        party: party.to_string(),
+        seats,
+        redistribution: Redistribution::PreferenceFlows,
+    }
+}
+
+#[test]
+fn test_seat_selection() {
+    let election_result = three_seat_election();
+    let seats = |party, selection| {
+        withdrawn_seats(&election_result, &withdrawal(party, selection)).unwrap()
+    };
+
+    assert_eq!(seats("REF", SeatSelection::All).len(), 3);
+    assert_eq!(
+        seats(
+            "REF",
+            SeatSelection::Subdivisions(vec!["Wales".to_string()])
+        ),
+        ["Newport"]
+    );
+    assert_eq!(
+        seats("REF", SeatSelection::PartnerAhead("CON".to_string())),
+        ["Clacton", "Newport"]
+    );
+    assert_eq!(
+        seats("CON", SeatSelection::PartnerAhead("REF".to_string())),
+        ["Boston"]
+    );
+    assert!(seats("REF", SeatSelection::HeldBy("CON".to_string())).is_empty());
+
+    let unknown = withdrawal(
+        "REF",
+        SeatSelection::Constituencies(vec!["Nowhere".to_string()]),
+    );
+    assert!(withdrawn_seats(&election_result, &unknown).is_err());
+}
+
+#[test]
+fn test_stand_down_redistributes_by_preference_flows() {
+    let election_result = three_seat_election();
+    let pact = Pact {
+        name: "Reform stands aside".to_string(),
+        withdrawals: vec![withdrawal(
+            "REF",
+            SeatSelection::PartnerAhead("CON".to_string()),
+        )],
+    };
+    let projected = apply_pact(&election_result, &pact, Some(&flows())).unwrap();
+
+    let clacton = &projected.constituencies[0];
+    assert_eq!(clacton.results["CON"], 6400);
+    assert_eq!(clacton.results["LAB"], 5600);
+    assert!(!clacton.results.contains_key("REF"));
+    assert!(clacton
+        .constituency
+        .candidates
+        .iter()
+        .all(|c| c.party.name != "REF"));
+    assert_eq!(projected.constituencies[1].results["REF"], 4000);
+
+    let seats = simulate_election(&projected, &ElectoralSystem::FirstPastThePost, None).unwrap();
+    assert_eq!(seats["CON"], 2);
+    assert!(apply_pact(&election_result, &pact, None).is_err());
+}
+
+#[test]
+fn test_unallocated_flows_abstain_as_under_av() {
+    let election_result = three_seat_election();
+    let pact = |parties: &[&str]| Pact {
+        name: "Newport".to_string(),
+        withdrawals: parties
+            .iter()
+            .map(|party| {
+                withdrawal(
+                    party,
+                    SeatSelection::Constituencies(vec!["Newport".to_string()]),
+                )
+            })
+            .collect(),
+    };
+
+    // CON's flows only cover 80% of its vote, so 900 of its 4500 abstain
+    let projected = apply_pact(&election_result, &pact(&["CON"]), Some(&flows())).unwrap();
+    let newport = &projected.constituencies[2].results;
+    assert_eq!(newport["LAB"], 5900);
+    assert_eq!(newport["REF"], 3700);
+
+    // With both standing down, the share each would have passed to the other
+    // goes to LAB, as AV passes on flows to eliminated parties
+    let projected = apply_pact(&election_result, &pact(&["CON", "REF"]), Some(&flows())).unwrap();
+    assert_eq!(
+        projected.constituencies[2].results,
+        HashMap::from([("LAB".to_string(), 9600)])
+    );
+}
+
+#[test]
+fn test_stand_down_to_abstention() {
+    let election_result = three_seat_election();
+    let mut abstain = withdrawal("CON", SeatSelection::All);
+    abstain.redistribution = Redistribution::Abstain;
+    let pact = Pact {
+        name: "Conservatives stand aside".to_string(),
+        withdrawals: vec![abstain],
+    };
+    let projected = apply_pact(&election_result, &pact, None).unwrap();
+
+    assert!(!projected.overall_result.contains_key("CON"));
+    assert_eq!(projected.overall_result["LAB"], 15000);
+    assert_eq!(projected.overall_result["REF"], 8000);
+}
+
+#[test]
+fn test_example_pacts_apply_to_bundled_data() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+    let flows: HashMap<String, HashMap<String, f32>> = serde_json::from_str(
+        &std::fs::read_to_string("data/preference_flows_england.json").unwrap(),
+    )
+    .unwrap();
+
+    let brexit_party = load_pact("data/pacts/brexit_party_2019.json").unwrap();
+    let seats = withdrawn_seats(&election_result, &brexit_party.withdrawals[0]).unwrap();
+    let before =
+        simulate_election(&election_result, &ElectoralSystem::FirstPastThePost, None).unwrap();
+    assert_eq!(seats.len() as u32, before["CON"]);
+    apply_pact(&election_result, &brexit_party, Some(&flows)).unwrap();
+
+    let remain_alliance = load_pact("data/pacts/remain_alliance_2019.json").unwrap();
+    let projected = apply_pact(&election_result, &remain_alliance, Some(&flows)).unwrap();
+    let bath = projected
+        .constituencies
+        .iter()
+        .find(|c| c.constituency.name == "Bath")
+        .unwrap();
+    assert!(!bath.results.contains_key("GRN"));
+}
@@ -0,0 +1,110 @@
+use serde_json::json;
+
+use psephulator::patch::{apply_patch_file, PatchFile};
+use psephulator::schema::parse_election_data;
+use psephulator::ElectionResult;
+
+fn election() -> ElectionResult {
+    parse_election_data(
+        &json!({
+            "schema_version": 3,
+            "datetime": "2024-07-04T00:00:00Z",
+            "constituencies": [{
+                "constituency": {
+                    "name": "Bradfrod West",
+                    "subdivision": { "name": "England" },
+                    "candidates": [
+                        { "name": "Candidate1", "party": { "name": "LAB" } },
+                        { "name": "Candidate2", "party": { "name": "OTH" } }
+                    ]
+                },
+                "results": { "LAB": 11724, "OTH": 15000 }
+            }],
+            "overall_result": { "LAB": 11724, "OTH": 15000 },
+            "metadata": { "applied_patches": [] }
+        })
+        .to_string(),
+    )
+    .unwrap()
+}
+
+fn patch_file(patches: serde_json::Value) -> PatchFile {
+    serde_json::from_value(json!({ "description": "test fixes", "patches": patches })).unwrap()
+}
+
+#[test]
+fn test_applies_patches_and_records_them() {
+    let mut election_result = election();
+    let patches = patch_file(json!([
+        { "op": "rename_constituency", "constituency": "Bradfrod West", "name": "Bradford West" },
+        { "op": "split_party", "constituency": "Bradford West", "party": "OTH", "into": [
+            { "party": "Independent1", "candidate": "Muhammed Islam", "votes": 11017 },
+            { "party": "Independent2", "candidate": "Akeel Hussain", "votes": 3547 }
+        ]},
+        { "op": "set_votes", "constituency": "Bradford West", "party": "LAB", "votes": 11730 }
+    ]));
+
+    apply_patch_file(&mut election_result, &patches, "fixes.json").unwrap();
+
+    let bradford = &election_result.constituencies[0];
+    assert_eq!(bradford.constituency.name, "Bradford West");
+    assert_eq!(bradford.results.get("OTH"), Some(&436));
+    assert_eq!(bradford.results.get("Independent1"), Some(&11017));
+    assert!(bradford
+        .constituency
+        .candidates
+        .iter()
+        .any(|c| c.name == "Akeel Hussain" && c.party.name == "Independent2"));
+    assert_eq!(election_result.overall_result.get("LAB"), Some(&11730));
+
+    let applied = &election_result.metadata.applied_patches;
+    assert_eq!(applied[0].file, "fixes.json");
+    assert_eq!(applied[0].description.as_deref(), Some("test fixes"));
+    assert_eq!(
+        applied[0].operations[0],
+        "rename Bradfrod West to Bradford West"
+    );
+}
+
+#[test]
+fn test_rejects_patches_for_unknown_constituencies() {
+    let mut election_result = election();
+    let patches = patch_file(json!([
+        { "op": "remove_party", "constituency": "Bradford East", "party": "LAB" }
+    ]));
+
+    let err = apply_patch_file(&mut election_result, &patches, "fixes.json").unwrap_err();
+    assert!(err.to_string().contains("Bradford East"));
+    assert!(err.to_string().starts_with("fixes.json: patches[0]: "));
+}
+
+#[test]
+fn test_rejects_splits_larger_than_the_party_vote() {
+    let mut election_result = election();
+    let patches = patch_file(json!([
+        { "op": "split_party", "constituency": "Bradfrod West", "party": "OTH", "into": [
+            { "party": "Independent1", "candidate": "Muhammed Islam", "votes": 20000 }
+        ]}
+    ]));
+
+    assert!(apply_patch_file(&mut election_result, &patches, "fixes.json").is_err());
+}
+
+#[test]
+fn test_failed_patch_files_leave_the_election_unchanged() {
+    let mut election_result = election();
+    let patches = patch_file(json!([
+        { "op": "set_votes", "constituency": "Bradfrod West", "party": "LAB", "votes": 11730 },
+        { "op": "remove_party", "constituency": "Bradfrod West", "party": "CON" }
+    ]));
+
+    let err = apply_patch_file(&mut election_result, &patches, "fixes.json").unwrap_err();
+
+    assert!(err.to_string().starts_with("fixes.json: patches[1]: "));
+    assert!(err.to_string().contains("no CON result"));
+    assert_eq!(
+        election_result.constituencies[0].results.get("LAB"),
+        Some(&11724)
+    );
+    assert!(election_result.metadata.applied_patches.is_empty());
+}
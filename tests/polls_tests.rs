@@ -0,0 +1,119 @@
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+use psephulator::polls::{load_polls, nowcast, poll_average, Poll, PollAverageSettings};
+use psephulator::schema::load_election_data;
+use psephulator::swing::SwingModel;
+use psephulator::ElectoralSystem;
+
+const FIXTURE: &str = "tests/fixtures/polls_sample.csv";
+
+fn date(date: &str) -> NaiveDate {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap()
+}
+
+fn poll(pollster: &str, fieldwork_end: &str, sample_size: u32, lab: f64) -> Poll {
+    Poll {
+        pollster: pollster.to_string(),
+        fieldwork_start: date(fieldwork_end),
+        fieldwork_end: date(fieldwork_end),
+        sample_size,
+        region: None,
+        shares: HashMap::from([("LAB".to_string(), lab), ("CON".to_string(), 100.0 - lab)]),
+    }
+}
+
+#[test]
+fn test_read_polls_csv() {
+    let polls = load_polls(FIXTURE, None).unwrap();
+
+    assert_eq!(polls.len(), 8);
+    assert_eq!(polls[0].pollster, "YouGov");
+    assert_eq!(polls[0].sample_size, 2000);
+    assert_eq!(polls[0].shares["LAB"], 38.0);
+    assert!(polls[..7].iter().all(|p| p.region.is_none()));
+    assert_eq!(polls[7].region.as_deref(), Some("Scotland"));
+}
+
+#[test]
+fn test_recent_and_larger_polls_count_for_more() {
+    let settings = PollAverageSettings {
+        adjust_house_effects: false,
+        ..PollAverageSettings::default()
+    };
+    let old = poll("A", "2024-06-01", 1000, 30.0);
+    let new = poll("A", "2024-06-15", 1000, 40.0);
+    let average = poll_average(&[&old, &new], date("2024-06-15"), &settings).unwrap();
+    // One half-life apart, so the new poll has twice the weight
+    assert!((average.shares["LAB"] - 36.6667).abs() < 0.001);
+
+    let small = poll("A", "2024-06-15", 250, 30.0);
+    let large = poll("B", "2024-06-15", 1000, 40.0);
+    let average = poll_average(&[&small, &large], date("2024-06-15"), &settings).unwrap();
+    assert!((average.shares["LAB"] - 36.6667).abs() < 0.001);
+
+    // Polls still in the field on the date are left out
+    assert!(poll_average(&[&new], date("2024-06-14"), &settings).is_none());
+}
+
+#[test]
+fn test_house_effects_stop_frequent_pollsters_dominating() {
+    let polls = [
+        poll("Frequent", "2024-06-15", 1000, 40.0),
+        poll("Frequent", "2024-06-15", 1000, 40.0),
+        poll("Frequent", "2024-06-15", 1000, 40.0),
+        poll("Occasional", "2024-06-15", 1000, 30.0),
+    ];
+    let polls: Vec<&Poll> = polls.iter().collect();
+
+    let unadjusted = PollAverageSettings {
+        adjust_house_effects: false,
+        ..PollAverageSettings::default()
+    };
+    let average = poll_average(&polls, date("2024-06-15"), &unadjusted).unwrap();
+    assert!((average.shares["LAB"] - 37.5).abs() < 0.001);
+
+    let average =
+        poll_average(&polls, date("2024-06-15"), &PollAverageSettings::default()).unwrap();
+    assert!((average.shares["LAB"] - 35.0).abs() < 0.001);
+    assert!((average.house_effects["Frequent"]["LAB"] - 5.0).abs() < 0.001);
+    assert!((average.house_effects["Occasional"]["LAB"] + 5.0).abs() < 0.001);
+}
+
+#[test]
+fn test_nowcast_from_polls() {
+    let baseline = load_election_data("data/uk_2024.json").unwrap();
+    let polls = load_polls(FIXTURE, None).unwrap();
+    let projected = nowcast(
+        &baseline,
+        &polls,
+        date("2024-07-03"),
+        &PollAverageSettings::default(),
+        SwingModel::Uniform,
+        &ElectoralSystem::FirstPastThePost,
+        None,
+    )
+    .unwrap();
+
+    // The Ipsos poll finishing on the 10th is after the nowcast date
+    assert_eq!(projected.polls_used, 7);
+    assert_eq!(projected.as_of, date("2024-07-03"));
+    assert!((projected.regional["Scotland"]["SNP"] - 31.0).abs() < 0.001);
+    assert_eq!(projected.seats.values().sum::<u32>(), 650);
+    // FPTP concentrates the seats in fewer parties than the votes
+    assert!(projected.indices.gallagher > 0.0);
+    assert!(
+        projected.indices.effective_parties_by_seats < projected.indices.effective_parties_by_votes
+    );
+
+    assert!(nowcast(
+        &baseline,
+        &polls,
+        date("2024-06-01"),
+        &PollAverageSettings::default(),
+        SwingModel::Uniform,
+        &ElectoralSystem::FirstPastThePost,
+        None,
+    )
+    .is_err());
+}
@@ -0,0 +1,95 @@
+use std::collections::HashMap;
+
+use psephulator::datasets::load_election;
+use psephulator::polls::read_polls_csv;
+use psephulator::registry::load_party_registry;
+use psephulator::schema::load_election_data;
+use psephulator::simulation::load_preference_flows;
+
+#[test]
+fn test_normalises_aliases_to_canonical_ids() {
+    let registry = load_party_registry("data/parties.json", "uk_2024").unwrap();
+
+    assert_eq!(registry.canonical_id("Labour Co-op"), Some("COOP"));
+    assert_eq!(registry.canonical_id("liberal democrats"), Some("LD"));
+    assert_eq!(registry.canonical_id("Monster Raving Loony"), None);
+    assert_eq!(registry.normalise("X"), "X");
+}
+
+#[test]
+fn test_normalising_merges_votes_of_aliases() {
+    let registry = load_party_registry("data/parties.json", "uk_2024").unwrap();
+    let votes: HashMap<String, u32> = [
+        ("Labour".to_string(), 100),
+        ("LAB".to_string(), 50),
+        ("Green".to_string(), 20),
+    ]
+    .into_iter()
+    .collect();
+
+    let normalised = registry.normalise_votes(&votes);
+
+    assert_eq!(normalised.get("LAB"), Some(&150));
+    assert_eq!(normalised.get("GRN"), Some(&20));
+    assert_eq!(normalised.len(), 2);
+}
+
+#[test]
+fn test_aggregates_seats_by_alliance() {
+    let registry = load_party_registry("data/parties.json", "uk_2024").unwrap();
+    let seats: HashMap<String, u32> = [
+        ("LAB".to_string(), 400),
+        ("COOP".to_string(), 11),
+        ("SDLP".to_string(), 2),
+        ("CON".to_string(), 121),
+    ]
+    .into_iter()
+    .collect();
+
+    let aggregated = registry.aggregate_by_alliance(&seats);
+
+    assert_eq!(aggregated.get("LAB_GROUP"), Some(&413));
+    assert_eq!(aggregated.get("CON"), Some(&121));
+}
+
+#[test]
+fn test_registry_covers_parties_in_2024_data() {
+    let registry = load_party_registry("data/parties.json", "uk_2024").unwrap();
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+
+    let unknown: Vec<_> = election_result
+        .overall_result
+        .keys()
+        .filter(|p| registry.canonical_id(p).is_none() && *p != "X")
+        .collect();
+    assert!(unknown.is_empty(), "unregistered parties: {:?}", unknown);
+}
+
+#[test]
+fn test_loading_a_bundled_election_returns_its_registry() {
+    let (election_result, registry) = load_election("data/uk_2024.json").unwrap();
+
+    assert!(registry.is_some());
+    assert!(election_result.overall_result.contains_key("LAB"));
+}
+
+#[test]
+fn test_loaders_normalise_flows_and_polls_through_the_registry() {
+    let registry = load_party_registry("data/parties.json", "uk_2024").unwrap();
+    let polls = "\
+pollster,fieldwork_start,fieldwork_end,sample_size,Labour,Conservative,Green
+YouGov,2024-06-10,2024-06-11,2000,38,18,8
+";
+
+    let flows_path = std::env::temp_dir().join("psephulator_aliased_flows.json");
+    std::fs::write(&flows_path, r#"{ "Labour": { "Green": 0.4, "GRN": 0.2 } }"#).unwrap();
+
+    let polls = read_polls_csv(polls.as_bytes(), Some(&registry)).unwrap();
+    let preference_flows = load_preference_flows(&flows_path, Some(&registry)).unwrap();
+
+    assert_eq!(polls[0].shares.get("LAB"), Some(&38.0));
+    assert_eq!(polls[0].shares.get("GRN"), Some(&8.0));
+    let labour = &preference_flows["LAB"];
+    assert!((labour["GRN"] - 0.6).abs() < 1e-6);
+    assert_eq!(labour.len(), 1);
+}
@@ -0,0 +1,94 @@
+use std::fs;
+
+use psephulator::scenario::{load_scenario, run_scenario, write_scenario_outputs, ScenarioOutput};
+use psephulator::schema::load_election_data;
+use psephulator::swing::{ProjectionModel, SwingModel};
+use psephulator::{simulate_election, ElectoralSystem};
+
+const EXAMPLE: &str = "data/scenarios/reform_surge_with_pacts.json";
+
+#[test]
+fn test_load_scenario_resolves_paths_against_the_file() {
+    let scenario = load_scenario(EXAMPLE).unwrap();
+
+    assert_eq!(
+        scenario.election,
+        std::path::Path::new("data/scenarios/../uk_2024.json")
+    );
+    assert_eq!(
+        scenario.systems,
+        [
+            ElectoralSystem::FirstPastThePost,
+            ElectoralSystem::AlternativeVote
+        ]
+    );
+    assert_eq!(scenario.swing.as_ref().unwrap().swings.national["REF"], 7.0);
+    assert!(matches!(scenario.outputs[0], ScenarioOutput::Summary));
+}
+
+#[test]
+fn test_run_example_scenario() {
+    let scenario = load_scenario(EXAMPLE).unwrap();
+    let report = run_scenario(&scenario).unwrap();
+
+    assert_eq!(
+        report.model,
+        Some(ProjectionModel::Swing(SwingModel::Uniform))
+    );
+    assert_eq!(report.outcomes.len(), 2);
+    let baseline = load_election_data("data/uk_2024.json").unwrap();
+    let fptp = simulate_election(&baseline, &ElectoralSystem::FirstPastThePost, None).unwrap();
+    assert_eq!(report.outcomes[0].baseline_seats["LAB"], fptp["LAB"]);
+    assert!(report.outcomes[0].seats["REF"] > fptp["REF"]);
+    assert_eq!(report.outcomes[0].seats.values().sum::<u32>(), 650);
+
+    // Running the same file twice gives the same answer
+    let again = run_scenario(&scenario).unwrap();
+    assert_eq!(
+        serde_json::to_string(&report).unwrap(),
+        serde_json::to_string(&again).unwrap()
+    );
+}
+
+#[test]
+fn test_scenario_writes_outputs() {
+    let dir = std::env::temp_dir().join("psephulator_scenario_test");
+    fs::create_dir_all(&dir).unwrap();
+    let data = fs::canonicalize("data").unwrap();
+    let scenario_json = format!(
+        r#"{{
+            "name": "Baseline",
+            "election": "{}",
+            "systems": ["fptp", "pr"],
+            "outputs": [{{"seats_csv": "seats.csv"}}, {{"report_json": "report.json"}}]
+        }}"#,
+        data.join("uk_2024.json").display()
+    );
+    let path = dir.join("scenario.json");
+    fs::write(&path, scenario_json).unwrap();
+
+    let scenario = load_scenario(&path).unwrap();
+    let report = run_scenario(&scenario).unwrap();
+    assert_eq!(report.model, None);
+    assert!(report.outcomes[0].changes.is_empty());
+    write_scenario_outputs(&scenario, &report).unwrap();
+
+    let seats = fs::read_to_string(dir.join("seats.csv")).unwrap();
+    assert!(seats.starts_with("system,party,baseline_seats,seats,change\n"));
+    assert!(seats.contains("fptp,LAB,411,411,0\n"));
+    assert!(fs::read_to_string(dir.join("report.json"))
+        .unwrap()
+        .contains("\"name\": \"Baseline\""));
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_scenario_without_flows_cannot_use_av() {
+    let mut scenario = load_scenario(EXAMPLE).unwrap();
+    scenario.pacts.clear();
+    scenario.preference_flows = None;
+    assert!(run_scenario(&scenario).is_err());
+
+    scenario.systems.clear();
+    assert!(run_scenario(&scenario).is_err());
+}
@@ -0,0 +1,111 @@
+use serde_json::json;
+
+use psephulator::datasets::{load_dataset_index, DATASET_INDEX};
+use psephulator::schema::{
+    detect_schema_version, load_election_data, migrate, parse_election_data, save_election_data,
+    CURRENT_SCHEMA_VERSION, UNKNOWN_SUBDIVISION,
+};
+
+#[test]
+fn test_detects_unversioned_files_by_shape() {
+    let without_subdivisions = json!({
+        "constituencies": [{ "constituency": { "name": "A", "candidates": [] }, "results": {} }]
+    });
+    let with_subdivisions = json!({
+        "constituencies": [{
+            "constituency": { "name": "A", "subdivision": { "name": "Wales" }, "candidates": [] },
+            "results": {}
+        }]
+    });
+
+    assert_eq!(detect_schema_version(&without_subdivisions).unwrap(), 1);
+    assert_eq!(detect_schema_version(&with_subdivisions).unwrap(), 2);
+}
+
+#[test]
+fn test_migrates_v1_file_to_current_model() {
+    let election_result = load_election_data("data/uk_2024_full.json").unwrap();
+
+    assert_eq!(election_result.schema_version, CURRENT_SCHEMA_VERSION);
+    assert_eq!(election_result.constituencies.len(), 650);
+    assert!(election_result
+        .constituencies
+        .iter()
+        .all(|c| c.constituency.subdivision.name == UNKNOWN_SUBDIVISION));
+}
+
+#[test]
+fn test_loads_v2_file_and_keeps_subdivisions() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+
+    let scottish_seats = election_result
+        .constituencies
+        .iter()
+        .filter(|c| c.constituency.subdivision.name == "Scotland")
+        .count();
+    assert_eq!(scottish_seats, 57);
+}
+
+#[test]
+fn test_rejects_newer_schema_versions() {
+    let mut data = json!({ "schema_version": CURRENT_SCHEMA_VERSION + 1, "constituencies": [] });
+
+    assert!(migrate(&mut data).is_err());
+    assert!(parse_election_data(&data.to_string()).is_err());
+}
+
+#[test]
+fn test_migrated_files_have_no_applied_patches() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+
+    assert!(election_result.metadata.applied_patches.is_empty());
+}
+
+#[test]
+fn test_old_scrape_timestamps_become_retrieval_times() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+
+    let retrieved_at = election_result.metadata.retrieved_at.unwrap();
+    assert_eq!(
+        retrieved_at.to_rfc3339(),
+        "2024-07-20T12:10:09.510186+00:00"
+    );
+    assert!(election_result.metadata.election_date.is_none());
+}
+
+#[test]
+fn test_saved_files_carry_a_verifiable_content_hash() {
+    let mut election_result = load_election_data("data/uk_2024.json").unwrap();
+    let path = std::env::temp_dir().join("psephulator_content_hash_test.json");
+
+    save_election_data(&path, &election_result).unwrap();
+    let saved = load_election_data(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        saved.metadata.content_hash,
+        Some(election_result.content_hash())
+    );
+    assert!(saved.verify_content_hash().is_ok());
+
+    election_result.metadata.content_hash = saved.metadata.content_hash;
+    election_result.constituencies[0]
+        .results
+        .insert("LAB".to_string(), 1);
+    assert!(election_result.verify_content_hash().is_err());
+}
+
+#[test]
+fn test_bundled_dataset_citation_names_the_election() {
+    let index = load_dataset_index(DATASET_INDEX).unwrap();
+    let election_result = index.load(index.dataset("uk_2024").unwrap()).unwrap();
+
+    assert_eq!(
+        election_result.datetime.date_naive().to_string(),
+        "2024-07-04"
+    );
+    let citation = election_result.citation();
+    assert!(citation.starts_with("United Kingdom House of Commons, election of 2024-07-04"));
+    assert!(citation.contains("source data/uk_2024.json"));
+    assert!(citation.contains("sha256 "));
+}
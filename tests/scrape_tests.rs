@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+use psephulator::scrape::{load_scrape_config, parse_results_page, scrape_path, ScrapeConfig};
+use psephulator::{simulate_election, ElectionResult, ElectoralSystem};
+
+const FIXTURE: &str = "tests/fixtures/wikipedia/uk_2024_results.html";
+// Holds only the 2024 results page, so other fixtures don't affect scraping it
+const FIXTURE_DIR: &str = "tests/fixtures/wikipedia";
+const SPEC: &str = "data/sources/uk_2024_wikipedia.json";
+
+fn uk_2024_spec() -> ScrapeConfig {
+    load_scrape_config(SPEC).unwrap()
+}
+
+// The 2024 spec, reading a single England table with no patches
+fn single_table_spec() -> ScrapeConfig {
+    ScrapeConfig {
+        subdivisions: vec!["England".to_string()],
+        patches: Vec::new(),
+        ..uk_2024_spec()
+    }
+}
+
+fn party_totals(election_result: &ElectionResult) -> HashMap<String, u32> {
+    let mut totals = HashMap::new();
+    for constituency_result in &election_result.constituencies {
+        // Columns the page leaves empty (zero votes) aren't significant
+        for (party, votes) in constituency_result.results.iter().filter(|(_, &v)| v > 0) {
+            *totals.entry(party.clone()).or_insert(0) += votes;
+        }
+    }
+    totals
+}
+
+#[test]
+fn test_parses_all_650_seats_from_saved_page() {
+    let election_result = scrape_path(FIXTURE, &uk_2024_spec()).unwrap();
+
+    assert_eq!(election_result.constituencies.len(), 650);
+    let mut seats_by_subdivision: HashMap<&str, u32> = HashMap::new();
+    for constituency_result in &election_result.constituencies {
+        *seats_by_subdivision
+            .entry(&constituency_result.constituency.subdivision.name)
+            .or_insert(0) += 1;
+    }
+    assert_eq!(seats_by_subdivision.get("England"), Some(&543));
+    assert_eq!(seats_by_subdivision.get("Scotland"), Some(&57));
+    assert_eq!(seats_by_subdivision.get("Wales"), Some(&32));
+    assert_eq!(seats_by_subdivision.get("Northern Ireland"), Some(&18));
+}
+
+// Votes and seats won in the 2024 general election as published by the House
+// of Commons Library, independently of the page and the bundled data
+const PUBLISHED_2024: [(&str, u32, u32); 12] = [
+    ("LAB", 9708716, 411),
+    ("CON", 6828925, 121),
+    ("REF", 4117610, 5),
+    ("LD", 3519143, 72),
+    ("SNP", 724758, 9),
+    ("SF", 210891, 7),
+    ("PC", 194811, 4),
+    ("DUP", 172058, 5),
+    ("APNI", 117191, 1),
+    ("UUP", 94779, 1),
+    ("SDLP", 86861, 2),
+    ("TUV", 48685, 1),
+];
+
+#[test]
+fn test_party_totals_match_published_2024_results() {
+    let scraped = scrape_path(FIXTURE, &uk_2024_spec()).unwrap();
+
+    let totals = party_totals(&scraped);
+    let seats = simulate_election(&scraped, &ElectoralSystem::FirstPastThePost, None).unwrap();
+    for (party, votes, won) in PUBLISHED_2024 {
+        // The results tables are off from the official count by a few
+        // thousand votes at most
+        let scraped_votes = totals.get(party).copied().unwrap_or(0);
+        assert!(
+            scraped_votes.abs_diff(votes) as f64 / (votes as f64) < 0.001,
+            "{} has {} votes, published {}",
+            party,
+            scraped_votes,
+            votes
+        );
+        assert_eq!(seats.get(party), Some(&won), "{} seats", party);
+    }
+}
+
+#[test]
+fn test_scrapes_every_page_in_a_directory() {
+    let election_result = scrape_path(FIXTURE_DIR, &uk_2024_spec()).unwrap();
+
+    assert_eq!(election_result.constituencies.len(), 650);
+}
+
+const REORDERED_TABLE: &str = r#"
+<table class="wikitable">
+<tr><th rowspan="2">Seat</th><th rowspan="2">Constituency</th><th colspan="3">Votes</th></tr>
+<tr><th>Grn</th><th>Lab</th><th>Con</th></tr>
+<tr><td>1</td><td>Brighton Pavilion</td><td>28,809</td><td>14,519</td><td>3,659</td></tr>
+</table>
+"#;
+
+#[test]
+fn test_detects_party_columns_from_headers() {
+    let config = single_table_spec();
+
+    let election_result = parse_results_page(REORDERED_TABLE, &config).unwrap();
+
+    let brighton = &election_result.constituencies[0];
+    assert_eq!(brighton.constituency.name, "Brighton Pavilion");
+    assert_eq!(brighton.results.get("GRN"), Some(&28809));
+    assert_eq!(brighton.results.get("CON"), Some(&3659));
+}
+
+#[test]
+fn test_ignores_footnotes_and_hidden_sort_keys() {
+    let config = single_table_spec();
+    let html = REORDERED_TABLE
+        .replace(
+            "<td>Brighton Pavilion</td>",
+            r##"<td><span data-sort-value="Brighton"><a href="/wiki/Brighton_Pavilion">Brighton Pavilion</a></span><sup class="reference"><a href="#cite_note-1">[1]</a></sup></td>"##,
+        )
+        .replace(
+            "<td>28,809</td>",
+            r#"<td><span class="sortkey">7000028809</span><span>28,809</span></td>"#,
+        )
+        .replace(
+            "<td>3,659</td>",
+            r#"<td><span style="display: none">0003659</span>3,659<sup class="reference">[a]</sup></td>"#,
+        );
+
+    let election_result = parse_results_page(&html, &config).unwrap();
+
+    let brighton = &election_result.constituencies[0];
+    assert_eq!(brighton.constituency.name, "Brighton Pavilion");
+    assert_eq!(brighton.results.get("GRN"), Some(&28809));
+    assert_eq!(brighton.results.get("CON"), Some(&3659));
+}
+
+#[test]
+fn test_fails_on_unknown_party_columns() {
+    let config = single_table_spec();
+    let html = REORDERED_TABLE.replace("<th>Grn</th>", "<th>Wkrs</th>");
+
+    let err = parse_results_page(&html, &config).unwrap_err();
+    assert!(err.to_string().contains("Wkrs"), "{}", err);
+}
+
+#[test]
+fn test_reads_empty_and_dashed_vote_cells_as_zero() {
+    let config = single_table_spec();
+    let html = REORDERED_TABLE
+        .replace("<td>14,519</td>", "<td></td>")
+        .replace("<td>3,659</td>", "<td>–</td>");
+
+    let election_result = parse_results_page(&html, &config).unwrap();
+
+    let brighton = &election_result.constituencies[0];
+    assert_eq!(brighton.results.get("LAB"), Some(&0));
+    assert_eq!(brighton.results.get("CON"), Some(&0));
+}
+
+#[test]
+fn test_fails_on_unreadable_vote_cells() {
+    let config = single_table_spec();
+    let html = REORDERED_TABLE.replace("<td>3,659</td>", "<td>3,659[a]</td>");
+
+    let err = parse_results_page(&html, &config).unwrap_err().to_string();
+    assert!(
+        err.contains("England table, row 3, column 5 (CON)"),
+        "{}",
+        err
+    );
+    assert!(err.contains("3,659[a]"), "{}", err);
+}
+
+#[test]
+fn test_fails_when_results_tables_are_missing() {
+    let err = parse_results_page(REORDERED_TABLE, &uk_2024_spec()).unwrap_err();
+
+    assert!(
+        err.to_string().contains("expected 4 results tables"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn test_applies_spec_patches() {
+    let election_result = scrape_path(FIXTURE, &uk_2024_spec()).unwrap();
+
+    let bradford = election_result
+        .constituencies
+        .iter()
+        .find(|c| c.constituency.name == "Bradford West")
+        .unwrap();
+    assert_eq!(bradford.results.get("Independent1"), Some(&11017));
+    assert!(!bradford.results.contains_key("OTH"));
+    assert_eq!(bradford.constituency.candidates[0].name, "Naz Shah");
+
+    let metadata = &election_result.metadata;
+    assert_eq!(metadata.election_date.unwrap().to_string(), "2024-07-04");
+    assert_eq!(metadata.source.as_deref(), Some(FIXTURE));
+    assert_eq!(metadata.chamber.as_deref(), Some("House of Commons"));
+
+    let applied = &election_result.metadata.applied_patches;
+    assert_eq!(applied.len(), 1);
+    assert!(applied[0].file.ends_with("uk_2024_patches.json"));
+    assert_eq!(applied[0].operations.len(), 2);
+}
+
+#[test]
+fn test_fails_on_patches_matching_no_constituency() {
+    let mut config = single_table_spec();
+    config.patches = uk_2024_spec().patches;
+
+    let err = parse_results_page(REORDERED_TABLE, &config).unwrap_err();
+    assert!(err.to_string().contains("Birmingham Hall Green"), "{}", err);
+}
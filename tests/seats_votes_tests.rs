@@ -0,0 +1,141 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use psephulator::schema::load_election_data;
+use psephulator::seats_votes::{default_majority, write_curve_csv, SeatTarget, SwingCalculator};
+use psephulator::swing::SwingModel;
+use psephulator::{ElectionResult, ElectoralSystem};
+
+mod common;
+use common::constituency;
+
+// A needs 5 points to take the second seat and 10 to take the third, less a
+// little as the votes are rounded
+fn election() -> ElectionResult {
+    ElectionResult::new(
+        Utc::now(),
+        vec![
+            constituency("One", "Region", &[("A", 400), ("B", 600)]),
+            constituency("Two", "Region", &[("A", 450), ("B", 550)]),
+            constituency("Three", "Region", &[("A", 700), ("B", 300)]),
+        ],
+        HashMap::new(),
+    )
+}
+
+fn seats(seats: &[(&str, u32)]) -> HashMap<String, u32> {
+    seats.iter().map(|(p, s)| (p.to_string(), *s)).collect()
+}
+
+#[test]
+fn test_seat_targets() {
+    let outcome = seats(&[("LAB", 312), ("CON", 308), ("SF", 7)]);
+    assert!(SeatTarget::LargestParty.is_met("LAB", &outcome));
+    assert!(!SeatTarget::LargestParty.is_met("CON", &outcome));
+    assert!(!SeatTarget::Seats(326).is_met("LAB", &outcome));
+    // 620 seats are taken up without Sinn Féin
+    assert!(SeatTarget::WorkingMajority(vec!["SF".to_string()]).is_met("LAB", &outcome));
+    assert!(!SeatTarget::WorkingMajority(vec![]).is_met("LAB", &outcome));
+    assert!(!SeatTarget::LargestParty.is_met("A", &seats(&[("A", 2), ("B", 2)])));
+}
+
+#[test]
+fn test_swing_needed() {
+    let election_result = election();
+    let calculator = SwingCalculator::new(
+        &election_result,
+        &ElectoralSystem::FirstPastThePost,
+        None,
+        SwingModel::Uniform,
+    )
+    .unwrap();
+    let swing =
+        |party: &str, target: &SeatTarget| calculator.swing_needed(party, target).unwrap().unwrap();
+
+    assert!((swing("A", &SeatTarget::LargestParty) - 5.0).abs() <= 0.1);
+    assert!((swing("A", &default_majority(&election_result)) - 5.0).abs() <= 0.1);
+    assert!((swing("A", &SeatTarget::Seats(3)) - 10.0).abs() <= 0.1);
+    // B is already the largest party, and would be until it lost 5 points
+    assert!((swing("B", &SeatTarget::LargestParty) + 5.0).abs() <= 0.1);
+    assert_eq!(
+        calculator.swing_needed("A", &SeatTarget::Seats(4)).unwrap(),
+        None
+    );
+    assert!(calculator
+        .swing_needed("C", &SeatTarget::LargestParty)
+        .is_err());
+
+    let thresholds = calculator.thresholds("A", &SeatTarget::Seats(3)).unwrap();
+    assert_eq!(thresholds.seats, 1);
+    assert!((thresholds.vote_share - 155.0 / 3.0).abs() < 0.01);
+}
+
+#[test]
+fn test_seats_votes_curve() {
+    let election_result = election();
+    let calculator = SwingCalculator::new(
+        &election_result,
+        &ElectoralSystem::FirstPastThePost,
+        None,
+        SwingModel::Uniform,
+    )
+    .unwrap();
+    let curve = calculator
+        .seats_votes_curve("A", -10.0, 10.0, 5.0, &SeatTarget::Seats(2))
+        .unwrap();
+    let seats: Vec<u32> = curve.iter().map(|p| p.seats).collect();
+    assert_eq!(seats, [1, 1, 1, 2, 3]);
+    assert!((curve[3].vote_share - (155.0 / 3.0 + 5.0)).abs() < 0.01);
+    assert!(curve[3].majority && curve[3].largest_party && !curve[2].majority);
+
+    assert!(calculator
+        .seats_votes_curve("A", 0.0, 10.0, 0.0, &SeatTarget::Seats(2))
+        .is_err());
+
+    let mut csv = Vec::new();
+    write_curve_csv(&mut csv, "A", &curve[3..4]).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "party,swing,vote_share,seats,seat_share,largest_party,majority\n\
+         A,5.00,56.67,2,0.6667,true,true\n"
+    );
+}
+
+#[test]
+fn test_bundled_swing_to_majority() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+    assert!(SwingCalculator::new(
+        &election_result,
+        &ElectoralSystem::ProportionalRepresentation,
+        None,
+        SwingModel::Uniform,
+    )
+    .is_err());
+
+    let calculator = SwingCalculator::new(
+        &election_result,
+        &ElectoralSystem::FirstPastThePost,
+        None,
+        SwingModel::Uniform,
+    )
+    .unwrap();
+    let con = calculator
+        .thresholds("CON", &SeatTarget::Seats(326))
+        .unwrap();
+    assert_eq!(con.seats, 121);
+    let (largest, majority) = (
+        con.largest_party_swing.unwrap(),
+        con.majority_swing.unwrap(),
+    );
+    assert!(largest > 0.0 && majority > largest);
+    // The swings reported are enough, not just close
+    let (_, seats) = calculator.outcome("CON", majority).unwrap();
+    assert!(SeatTarget::Seats(326).is_met("CON", &seats));
+    let (_, seats) = calculator.outcome("CON", largest).unwrap();
+    assert!(SeatTarget::LargestParty.is_met("CON", &seats));
+    // Labour can lose votes and keep its majority
+    let lab = calculator
+        .swing_needed("LAB", &SeatTarget::Seats(326))
+        .unwrap();
+    assert!(lab.unwrap() < 0.0);
+}
@@ -1,14 +1,15 @@
 use chrono::Utc;
 use std::collections::HashMap;
 
-#[path = "../src/main.rs"]
-mod main;
-
-use main::{
+use psephulator::simulation::{dhondt, single_transferable_vote_seats};
+use psephulator::{
     simulate_election, Candidate, Constituency, ConstituencyResult, ElectionResult,
-    ElectoralSystem, Party,
+    ElectoralSystem, Party, Subdivision,
 };
 
+mod common;
+use common::constituency;
+
 #[test]
 fn test_first_past_the_post_simulation() {
     let party1 = Party {
@@ -29,30 +30,182 @@ fn test_first_past_the_post_simulation() {
 
     let constituency = Constituency {
         name: String::from("Constituency 1"),
+        subdivision: Subdivision {
+            name: String::from("Subdivision 1"),
+        },
         candidates: vec![candidate1, candidate2],
     };
 
     let constituency_result = ConstituencyResult {
         constituency: constituency.clone(),
-        results: [(party1.clone(), 2), (party2.clone(), 1)]
+        results: [(party1.name.clone(), 2), (party2.name.clone(), 1)]
             .iter()
             .cloned()
             .collect(),
     };
 
-    let election_result = ElectionResult {
-        datetime: Utc::now(),
-        constituencies: vec![constituency_result],
-        overall_result: [(party1.clone(), 2), (party2.clone(), 1)]
+    let election_result = ElectionResult::new(
+        Utc::now(),
+        vec![constituency_result],
+        [(party1.name.clone(), 2), (party2.name.clone(), 1)]
             .iter()
             .cloned()
             .collect(),
-    };
+    );
 
     let electoral_system = ElectoralSystem::FirstPastThePost;
 
-    let simulated_result = simulate_election(&election_result, &electoral_system);
+    let simulated_result = simulate_election(&election_result, &electoral_system, None).unwrap();
 
-    let expected_result: HashMap<Party, u32> = [(party1, 1)].iter().cloned().collect();
+    let expected_result: HashMap<String, u32> = [(party1.name, 1)].iter().cloned().collect();
     assert_eq!(simulated_result, expected_result);
 }
+
+// A wins every seat in North on a plurality, B sweeps South
+fn four_seat_election() -> ElectionResult {
+    let mut election_result = ElectionResult::new(
+        Utc::now(),
+        vec![
+            constituency("N1", "North", &[("A", 4000), ("B", 3500), ("C", 2500)]),
+            constituency("N2", "North", &[("A", 4000), ("B", 3500), ("C", 2500)]),
+            constituency("N3", "North", &[("A", 4000), ("B", 3500), ("C", 2500)]),
+            constituency("S1", "South", &[("A", 1000), ("B", 6000), ("C", 3000)]),
+        ],
+        HashMap::new(),
+    );
+    election_result.recompute_overall_result();
+    election_result
+}
+
+fn votes(results: &[(&str, u32)]) -> HashMap<String, u32> {
+    results.iter().map(|(p, v)| (p.to_string(), *v)).collect()
+}
+
+#[test]
+fn test_dhondt_allocation() {
+    let seats = dhondt(
+        &votes(&[("A", 100), ("B", 80), ("C", 30)]),
+        8,
+        &HashMap::new(),
+    );
+    assert_eq!(seats, votes(&[("A", 4), ("B", 3), ("C", 1)]));
+
+    // Seats already held count towards the divisors
+    let top_up = dhondt(
+        &votes(&[("A", 100), ("B", 80), ("C", 30)]),
+        4,
+        &votes(&[("A", 4)]),
+    );
+    assert_eq!(top_up, votes(&[("B", 3), ("C", 1)]));
+}
+
+#[test]
+fn test_proportional_systems() {
+    let election_result = four_seat_election();
+    let fptp =
+        simulate_election(&election_result, &ElectoralSystem::FirstPastThePost, None).unwrap();
+    assert_eq!(fptp, votes(&[("A", 3), ("B", 1)]));
+
+    // 13000 A, 16500 B, 10500 C nationally
+    let pr = simulate_election(
+        &election_result,
+        &ElectoralSystem::ProportionalRepresentation,
+        None,
+    )
+    .unwrap();
+    assert_eq!(pr, votes(&[("A", 1), ("B", 2), ("C", 1)]));
+
+    let regional_pr = simulate_election(
+        &election_result,
+        &ElectoralSystem::RegionalProportionalRepresentation,
+        None,
+    )
+    .unwrap();
+    assert_eq!(regional_pr, votes(&[("A", 1), ("B", 2), ("C", 1)]));
+
+    // A's three constituencies are all it deserves of eight seats, so the
+    // list seats go to B and C
+    let mmp = simulate_election(
+        &election_result,
+        &ElectoralSystem::MixedMemberProportional,
+        None,
+    )
+    .unwrap();
+    assert_eq!(mmp, votes(&[("A", 3), ("B", 3), ("C", 2)]));
+}
+
+#[test]
+fn test_single_transferable_vote_transfers_eliminated_votes() {
+    let flows = HashMap::from([("C".to_string(), HashMap::from([("B".to_string(), 1.0_f32)]))]);
+    // Quota for two seats from 100 votes is 34; A is elected, then C is
+    // eliminated and its votes take B past A's surplus
+    let votes = HashMap::from([
+        ("A".to_string(), 50.0),
+        ("B".to_string(), 25.0),
+        ("C".to_string(), 25.0),
+    ]);
+    let seats = single_transferable_vote_seats(votes, 2, &flows);
+    assert_eq!(
+        seats,
+        HashMap::from([("A".to_string(), 1), ("B".to_string(), 1)])
+    );
+
+    let election_result = four_seat_election();
+    let stv = simulate_election(
+        &election_result,
+        &ElectoralSystem::SingleTransferableVote,
+        Some(flows),
+    )
+    .unwrap();
+    assert_eq!(stv.values().sum::<u32>(), 4);
+}
+
+#[test]
+fn test_alternative_vote_breaks_ties_by_party_name() {
+    // C and D tie for last. Eliminating D first puts C ahead of A; had C gone
+    // first, B would have won.
+    let flows = HashMap::from([
+        ("C".to_string(), HashMap::from([("B".to_string(), 1.0_f32)])),
+        ("D".to_string(), HashMap::from([("C".to_string(), 1.0_f32)])),
+    ]);
+    let election = |results: &[(&str, u32)]| {
+        let mut election_result = ElectionResult::new(
+            Utc::now(),
+            vec![constituency("Seat", "North", results)],
+            HashMap::new(),
+        );
+        election_result.recompute_overall_result();
+        election_result
+    };
+
+    // Each new HashMap iterates in its own order, so a tie broken by that
+    // order would flip between runs
+    for _ in 0..20 {
+        let av = simulate_election(
+            &election(&[("A", 35), ("B", 30), ("C", 20), ("D", 20)]),
+            &ElectoralSystem::AlternativeVote,
+            Some(flows.clone()),
+        )
+        .unwrap();
+        assert_eq!(av, votes(&[("C", 1)]));
+        let av = simulate_election(
+            &election(&[("A", 50), ("B", 50)]),
+            &ElectoralSystem::AlternativeVote,
+            Some(flows.clone()),
+        )
+        .unwrap();
+        assert_eq!(av, votes(&[("A", 1)]));
+    }
+}
+
+#[test]
+fn test_transfer_systems_without_preference_flows_are_an_error() {
+    let election_result = four_seat_election();
+    for system in [
+        ElectoralSystem::AlternativeVote,
+        ElectoralSystem::SingleTransferableVote,
+    ] {
+        let err = simulate_election(&election_result, &system, None).unwrap_err();
+        assert!(err.to_string().contains("needs preference flows"));
+    }
+}
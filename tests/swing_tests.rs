@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use psephulator::schema::load_election_data;
+use psephulator::swing::{apply_swing, project, ProjectionModel, SwingModel, Swings};
+use psephulator::{ElectionResult, ElectoralSystem};
+
+mod common;
+use common::three_seat_election;
+
+fn party_swings(swings: &[(&str, f64)]) -> HashMap<String, f64> {
+    swings.iter().map(|(p, s)| (p.to_string(), *s)).collect()
+}
+
+fn swings(swings: &[(&str, f64)]) -> Swings {
+    party_swings(swings).into()
+}
+
+#[test]
+fn test_uniform_swing_moves_every_constituency_by_the_same_points() {
+    let election_result = three_seat_election();
+    let projected = apply_swing(
+        &election_result,
+        SwingModel::Uniform,
+        &swings(&[("LAB", -5.0), ("CON", 5.0)]),
+    )
+    .unwrap();
+
+    let safe = &projected.constituencies[0].results;
+    assert_eq!(safe["LAB"], 5500);
+    assert_eq!(safe["CON"], 3500);
+    assert_eq!(safe["LD"], 1000);
+    for (before, after) in election_result
+        .constituencies
+        .iter()
+        .zip(&projected.constituencies)
+    {
+        let before: u32 = before.results.values().sum();
+        let after: u32 = after.results.values().sum();
+        assert_eq!(before, after);
+    }
+}
+
+#[test]
+fn test_uniform_swing_clamps_at_zero_and_redistributes() {
+    let election_result = three_seat_election();
+    let projected = apply_swing(
+        &election_result,
+        SwingModel::Uniform,
+        &swings(&[("LD", -10.0)]),
+    )
+    .unwrap();
+
+    // The Lib Dems only had ~4% in Tory, so lose 4 points there, not 10
+    let tory = &projected.constituencies[2].results;
+    assert_eq!(tory["LD"], 0);
+    assert_eq!(tory.values().sum::<u32>(), 7300);
+    assert!(tory["CON"] > 5000 && tory["LAB"] > 2000);
+}
+
+#[test]
+fn test_uniform_swing_skips_parties_with_no_votes() {
+    let mut election_result = three_seat_election();
+    election_result.constituencies[1]
+        .results
+        .insert("REF".to_string(), 0);
+    election_result.constituencies[2]
+        .results
+        .insert("REF".to_string(), 1000);
+    election_result.recompute_overall_result();
+    let projected = apply_swing(
+        &election_result,
+        SwingModel::Uniform,
+        &swings(&[("REF", 5.0)]),
+    )
+    .unwrap();
+
+    // REF didn't stand in Marginal, so nothing changes there
+    assert_eq!(
+        projected.constituencies[1].results,
+        election_result.constituencies[1].results
+    );
+    assert!(projected.constituencies[2].results["REF"] > 1000);
+}
+
+#[test]
+fn test_projection_reports_seats_that_changed_hands() {
+    let election_result = three_seat_election();
+    let projection = project(
+        &election_result,
+        SwingModel::Uniform,
+        &swings(&[("LAB", -2.0), ("CON", 2.0)]),
+        &ElectoralSystem::FirstPastThePost,
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(projection.baseline_seats["LAB"], 2);
+    assert_eq!(projection.seats["LAB"], 1);
+    assert_eq!(projection.seats["CON"], 2);
+    assert_eq!(projection.changes.len(), 1);
+    let change = &projection.changes[0];
+    assert_eq!(change.constituency, "Marginal");
+    assert_eq!((change.from.as_str(), change.to.as_str()), ("LAB", "CON"));
+}
+
+#[test]
+fn test_swing_for_a_party_that_did_not_stand_is_an_error() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+    assert!(apply_swing(
+        &election_result,
+        SwingModel::Uniform,
+        &swings(&[("Labour", -4.0)])
+    )
+    .is_err());
+    assert!(project(
+        &election_result,
+        SwingModel::Uniform,
+        &swings(&[("LAB", -4.0)]),
+        &ElectoralSystem::AlternativeVote,
+        None
+    )
+    .is_err());
+}
+
+fn national_share(election_result: &ElectionResult, party: &str) -> f64 {
+    let total: u32 = election_result.overall_result.values().sum();
+    election_result.overall_result[party] as f64 / total as f64 * 100.0
+}
+
+#[test]
+fn test_proportional_swing_scales_small_parties_without_going_negative() {
+    let election_result = three_seat_election();
+    let projected = apply_swing(
+        &election_result,
+        SwingModel::Proportional,
+        &swings(&[("LD", -5.0), ("CON", 5.0)]),
+    )
+    .unwrap();
+
+    // Under uniform swing the Lib Dems would be wiped out in Tory; here they
+    // keep the same fraction of their vote everywhere
+    let kept: Vec<f64> = election_result
+        .constituencies
+        .iter()
+        .zip(&projected.constituencies)
+        .map(|(before, after)| after.results["LD"] as f64 / before.results["LD"] as f64)
+        .collect();
+    assert!(kept.iter().all(|k| (k - kept[0]).abs() < 0.05 && *k > 0.5));
+    let ld_share = national_share(&projected, "LD");
+    assert!((ld_share - (national_share(&election_result, "LD") - 5.0)).abs() < 0.3);
+}
+
+#[test]
+fn test_strong_transition_takes_more_votes_where_losers_were_strong() {
+    let election_result = three_seat_election();
+    let projection = project(
+        &election_result,
+        SwingModel::StrongTransition,
+        &swings(&[("LAB", -6.0), ("CON", 6.0)]),
+        &ElectoralSystem::FirstPastThePost,
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        projection.model,
+        ProjectionModel::Swing(SwingModel::StrongTransition)
+    );
+    let safe = &projection.result.constituencies[0].results;
+    assert!(safe["LAB"].abs_diff(5194) <= 2);
+    assert!(safe["CON"].abs_diff(3806) <= 2);
+    let tory = &projection.result.constituencies[2].results;
+    assert!(tory["LAB"].abs_diff(1731) <= 2);
+}
+
+#[test]
+fn test_hybrid_swing_falls_between_uniform_and_proportional() {
+    let election_result = three_seat_election();
+    let swings = swings(&[("LD", -5.0), ("CON", 5.0)]);
+    let tory_ld = |model| {
+        let projected = apply_swing(&election_result, model, &swings).unwrap();
+        projected.constituencies[2].results["LD"]
+    };
+
+    // The Lib Dems' 4.1% in Tory goes negative under uniform swing, and
+    // scales to 2.3% under proportional swing; hybrid swing takes the mean
+    assert_eq!(tory_ld(SwingModel::Uniform), 0);
+    let proportional = tory_ld(SwingModel::Proportional);
+    let hybrid = tory_ld(SwingModel::Hybrid);
+    assert!(hybrid > 0 && hybrid < proportional);
+    assert!(hybrid.abs_diff(50) <= 5);
+}
+
+#[test]
+fn test_regional_swing_only_moves_its_subdivision() {
+    let election_result = three_seat_election();
+    let mut swings = swings(&[("LD", 1.0)]);
+    swings.regional.insert(
+        "South".to_string(),
+        party_swings(&[("LAB", 25.0), ("CON", -25.0)]),
+    );
+    let projected = apply_swing(&election_result, SwingModel::Uniform, &swings).unwrap();
+
+    // The South's own swing replaces the national one for LAB and CON, while
+    // the national LD swing still applies there
+    let tory = &projected.constituencies[2].results;
+    assert!(tory["LAB"] > tory["CON"]);
+    assert!(tory["LD"] > 300);
+    let safe = &projected.constituencies[0].results;
+    assert!(safe["LAB"].abs_diff(5940) <= 1 && safe["CON"].abs_diff(2970) <= 1);
+}
+
+#[test]
+fn test_scottish_swing_only_changes_scottish_seats() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+    let swings = Swings {
+        national: HashMap::new(),
+        regional: [(
+            "Scotland".to_string(),
+            party_swings(&[("SNP", 6.0), ("LAB", -6.0)]),
+        )]
+        .into_iter()
+        .collect(),
+    };
+    let projection = project(
+        &election_result,
+        SwingModel::Proportional,
+        &swings,
+        &ElectoralSystem::FirstPastThePost,
+        None,
+    )
+    .unwrap();
+
+    assert!(!projection.changes.is_empty());
+    assert!(projection
+        .changes
+        .iter()
+        .all(|c| c.subdivision == "Scotland"));
+    for (before, after) in election_result
+        .constituencies
+        .iter()
+        .zip(&projection.result.constituencies)
+        .filter(|(c, _)| c.constituency.subdivision.name != "Scotland")
+    {
+        assert_eq!(before.results, after.results);
+    }
+
+    let mut unknown = swings.clone();
+    unknown
+        .regional
+        .insert("Cornwall".to_string(), party_swings(&[("LD", 5.0)]));
+    assert!(apply_swing(&election_result, SwingModel::Uniform, &unknown).is_err());
+}
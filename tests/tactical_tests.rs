@@ -0,0 +1,112 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use psephulator::schema::load_election_data;
+use psephulator::tactical::{apply_tactical_voting, load_tactical_voting, TacticalVoting};
+use psephulator::{simulate_election, ElectionResult, ElectoralSystem};
+
+mod common;
+use common::constituency;
+
+fn two_seat_election() -> ElectionResult {
+    let mut election_result = ElectionResult::new(
+        Utc::now(),
+        vec![
+            // LD are in contention here, LAB are not
+            constituency(
+                "Wimbledon",
+                "England",
+                &[("CON", 4000), ("LD", 3500), ("LAB", 2500)],
+            ),
+            // Nobody but CON is in contention
+            constituency(
+                "Safe",
+                "England",
+                &[("CON", 6000), ("LD", 2000), ("LAB", 2000)],
+            ),
+        ],
+        HashMap::new(),
+    );
+    election_result.recompute_overall_result();
+    election_result
+}
+
+fn progressive_alliance(fraction: f64) -> TacticalVoting {
+    TacticalVoting {
+        fraction,
+        party_fractions: HashMap::new(),
+        margin: 10.0,
+        acceptability: HashMap::from([
+            ("LAB".to_string(), HashMap::from([("LD".to_string(), 0.8)])),
+            ("LD".to_string(), HashMap::from([("LAB".to_string(), 0.8)])),
+        ]),
+    }
+}
+
+#[test]
+fn test_voters_out_of_contention_switch_to_best_placed_acceptable_party() {
+    let election_result = two_seat_election();
+    let projected = apply_tactical_voting(&election_result, &progressive_alliance(0.5)).unwrap();
+
+    // Half of LAB's voters are willing, and 80% of those will vote LD
+    let wimbledon = &projected.constituencies[0].results;
+    assert_eq!(wimbledon["LAB"], 1500);
+    assert_eq!(wimbledon["LD"], 4500);
+    assert_eq!(wimbledon["CON"], 4000);
+
+    // LD is out of contention too, so there's nobody to switch to
+    assert_eq!(
+        projected.constituencies[1].results,
+        election_result.constituencies[1].results
+    );
+
+    let seats = simulate_election(&projected, &ElectoralSystem::FirstPastThePost, None).unwrap();
+    assert_eq!(seats["LD"], 1);
+    assert_eq!(projected.overall_result["LD"], 6500);
+}
+
+#[test]
+fn test_party_fractions_and_validation() {
+    let election_result = two_seat_election();
+    let mut tactical_voting = progressive_alliance(0.5);
+    tactical_voting
+        .party_fractions
+        .insert("LAB".to_string(), 0.0);
+    let projected = apply_tactical_voting(&election_result, &tactical_voting).unwrap();
+    assert_eq!(projected.constituencies[0].results["LAB"], 2500);
+
+    assert!(apply_tactical_voting(&election_result, &progressive_alliance(1.5)).is_err());
+    let mut self_acceptable = progressive_alliance(0.5);
+    self_acceptable
+        .acceptability
+        .insert("CON".to_string(), HashMap::from([("CON".to_string(), 1.0)]));
+    assert!(apply_tactical_voting(&election_result, &self_acceptable).is_err());
+}
+
+#[test]
+fn test_example_file_applies_to_bundled_data() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+    let tactical_voting = load_tactical_voting("data/tactical_progressive.json").unwrap();
+    let projected = apply_tactical_voting(&election_result, &tactical_voting).unwrap();
+
+    // Tactical voting moves votes between parties without changing turnout
+    let total = |er: &ElectionResult| {
+        er.constituencies
+            .iter()
+            .flat_map(|c| c.results.values())
+            .sum::<u32>()
+    };
+    assert_eq!(total(&projected), total(&election_result));
+    let con = |er: &ElectionResult| {
+        er.constituencies
+            .iter()
+            .filter_map(|c| c.results.get("CON"))
+            .sum::<u32>()
+    };
+    assert_eq!(con(&projected), con(&election_result));
+
+    let before =
+        simulate_election(&election_result, &ElectoralSystem::FirstPastThePost, None).unwrap();
+    let after = simulate_election(&projected, &ElectoralSystem::FirstPastThePost, None).unwrap();
+    assert!(after["CON"] <= before["CON"]);
+}
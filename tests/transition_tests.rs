@@ -0,0 +1,126 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use psephulator::schema::load_election_data;
+use psephulator::swing::ProjectionModel;
+use psephulator::transition::{
+    apply_transitions, load_transition_matrix, project_transitions, validate_transition_matrix,
+    DID_NOT_VOTE,
+};
+use psephulator::{ElectionResult, ElectoralSystem};
+
+mod common;
+use common::constituency;
+
+fn two_seat_election() -> ElectionResult {
+    let mut election_result = ElectionResult::new(
+        Utc::now(),
+        vec![
+            constituency(
+                "Clacton",
+                "England",
+                &[("CON", 5000), ("REF", 4500), ("LAB", 2000)],
+            ),
+            constituency("Ashfield", "England", &[("CON", 4000), ("LAB", 6000)]),
+        ],
+        HashMap::new(),
+    );
+    election_result.recompute_overall_result();
+    election_result
+}
+
+fn matrix(rows: &[(&str, &[(&str, f32)])]) -> HashMap<String, HashMap<String, f32>> {
+    rows.iter()
+        .map(|(from, row)| {
+            let row = row.iter().map(|(to, f)| (to.to_string(), *f)).collect();
+            (from.to_string(), row)
+        })
+        .collect()
+}
+
+#[test]
+fn test_rows_must_sum_to_one() {
+    assert!(validate_transition_matrix(&matrix(&[("CON", &[("REF", 0.2)])])).is_err());
+    assert!(
+        validate_transition_matrix(&matrix(&[("CON", &[("CON", 1.2), ("REF", -0.2)])])).is_err()
+    );
+    assert!(validate_transition_matrix(&matrix(&[("CON", &[("CON", 0.8), ("REF", 0.2)])])).is_ok());
+}
+
+#[test]
+fn test_transitions_move_a_fraction_of_each_partys_voters() {
+    let election_result = two_seat_election();
+    let transitions = matrix(&[("CON", &[("CON", 0.8), ("REF", 0.2)])]);
+    let projection = project_transitions(
+        &election_result,
+        &transitions,
+        0.6,
+        &ElectoralSystem::FirstPastThePost,
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(projection.model, ProjectionModel::TransitionMatrix);
+    let clacton = &projection.result.constituencies[0].results;
+    assert_eq!(clacton["CON"], 4000);
+    assert_eq!(clacton["REF"], 5500);
+    assert_eq!(clacton["LAB"], 2000);
+    assert_eq!(projection.changes.len(), 1);
+    assert_eq!(projection.changes[0].to, "REF");
+
+    // REF didn't stand in Ashfield, so CON voters there stay put
+    let ashfield = &projection.result.constituencies[1].results;
+    assert_eq!(ashfield["CON"], 4000);
+    assert!(!ashfield.contains_key("REF"));
+}
+
+#[test]
+fn test_did_not_vote_changes_turnout() {
+    let election_result = two_seat_election();
+    let transitions = matrix(&[
+        (DID_NOT_VOTE, &[(DID_NOT_VOTE, 0.9), ("LAB", 0.1)]),
+        ("CON", &[("CON", 0.9), (DID_NOT_VOTE, 0.1)]),
+    ]);
+    // At 50% turnout there are as many non-voters as voters
+    let projected = apply_transitions(&election_result, &transitions, 0.5).unwrap();
+
+    let ashfield = &projected.constituencies[1].results;
+    assert_eq!(ashfield["LAB"], 7000);
+    assert_eq!(ashfield["CON"], 3600);
+    assert!(!ashfield.contains_key(DID_NOT_VOTE));
+    assert!(apply_transitions(&election_result, &transitions, 0.0).is_err());
+}
+
+#[test]
+fn test_parties_must_have_stood() {
+    let election_result = two_seat_election();
+    let err =
+        apply_transitions(&election_result, &matrix(&[("GRN", &[("LAB", 1.0)])]), 0.6).unwrap_err();
+    assert!(err.to_string().contains("no GRN voters"));
+
+    let err = apply_transitions(
+        &election_result,
+        &matrix(&[("CON", &[("CON", 0.8), ("RFM", 0.2)])]),
+        0.6,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("go to RFM"));
+}
+
+#[test]
+fn test_example_matrix_applies_to_bundled_data() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+    let transitions = load_transition_matrix("data/transitions_example.json").unwrap();
+    let projected = apply_transitions(&election_result, &transitions, 0.6).unwrap();
+
+    assert!(projected.overall_result["REF"] > election_result.overall_result["REF"]);
+    assert!(projected.overall_result["CON"] < election_result.overall_result["CON"]);
+    assert_eq!(
+        projected.overall_result["SNP"],
+        election_result
+            .constituencies
+            .iter()
+            .filter_map(|c| c.results.get("SNP"))
+            .sum::<u32>()
+    );
+}
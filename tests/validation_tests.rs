@@ -0,0 +1,86 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use psephulator::validation::{validate_election, validate_election_file, Severity};
+use psephulator::{ConstituencyResult, ElectionResult};
+
+mod common;
+use common::constituency;
+
+fn election(constituencies: Vec<ConstituencyResult>) -> ElectionResult {
+    let mut election_result = ElectionResult::new(Utc::now(), constituencies, HashMap::new());
+    election_result.recompute_overall_result();
+    election_result
+}
+
+#[test]
+fn test_clean_election_has_no_issues() {
+    let election_result = election(vec![
+        constituency("Bath", "England", &[("LD", 5000), ("CON", 3000)]),
+        constituency("Wells", "England", &[("LD", 4000), ("CON", 4500)]),
+    ]);
+    assert!(validate_election(&election_result).is_empty());
+}
+
+#[test]
+fn test_errors_and_warnings() {
+    let mut election_result = election(vec![
+        constituency("Bath", "England", &[("LD", 5000), ("CON", 3000)]),
+        constituency("Bath", "England", &[("LD", 4000), ("CON", 4000)]),
+        constituency("Wells", "England", &[("LD", 0)]),
+    ]);
+    election_result.constituencies[0]
+        .results
+        .insert("GRN".to_string(), 100);
+    let issues = validate_election(&election_result);
+
+    let errors: Vec<&str> = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .map(|i| i.message.as_str())
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            "constituency appears more than once",
+            "constituency has no votes"
+        ]
+    );
+    let warnings: Vec<&str> = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Warning)
+        .map(|i| i.message.as_str())
+        .collect();
+    assert!(warnings.contains(&"GRN has votes but no candidate"));
+    assert!(warnings.contains(&"tie for first place"));
+    assert!(
+        warnings.contains(&"GRN has 100 votes in its constituencies but 0 in the national result")
+    );
+}
+
+#[test]
+fn test_content_hash_mismatch_is_an_error() {
+    let mut election_result = election(vec![constituency(
+        "Bath",
+        "England",
+        &[("LD", 5000), ("CON", 3000)],
+    )]);
+    election_result.metadata.content_hash = Some(election_result.content_hash());
+    assert!(validate_election(&election_result).is_empty());
+
+    election_result.constituencies[0]
+        .results
+        .insert("LD".to_string(), 5001);
+    election_result.recompute_overall_result();
+    let issues = validate_election(&election_result);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].severity, Severity::Error);
+}
+
+#[test]
+fn test_bundled_data_has_only_warnings() {
+    let issues = validate_election_file("data/uk_2024.json").unwrap();
+    assert!(issues[0].message.contains("schema version"));
+    assert!(issues.iter().all(|i| i.severity == Severity::Warning));
+    assert!(validate_election_file("data/missing.json").is_err());
+}
@@ -0,0 +1,86 @@
+use chrono::Utc;
+use std::collections::HashMap;
+
+use psephulator::schema::load_election_data;
+use psephulator::wasted::{analyse_wasted_votes, write_wasted_votes_csv};
+use psephulator::{ElectionResult, ElectoralSystem};
+
+mod common;
+use common::constituency;
+
+fn two_party_election() -> ElectionResult {
+    let mut election_result = ElectionResult::new(
+        Utc::now(),
+        vec![
+            constituency("N1", "North", &[("A", 600), ("B", 400)]),
+            constituency("N2", "North", &[("A", 550), ("B", 450)]),
+            constituency("S1", "South", &[("A", 100), ("B", 900)]),
+        ],
+        HashMap::new(),
+    );
+    election_result.recompute_overall_result();
+    election_result
+}
+
+#[test]
+fn test_wasted_votes_and_efficiency_gap() {
+    let election_result = two_party_election();
+    let analysis =
+        analyse_wasted_votes(&election_result, &ElectoralSystem::FirstPastThePost, None).unwrap();
+
+    let b = &analysis.national[0];
+    assert_eq!((b.party.as_str(), b.votes, b.seats), ("B", 1750, 1));
+    // B loses 850 votes in the North and wins the South with 799 to spare
+    assert_eq!(
+        (b.lost_votes, b.surplus_votes, b.wasted_votes),
+        (850, 799, 1649)
+    );
+    let a = &analysis.national[1];
+    assert_eq!((a.lost_votes, a.surplus_votes), (100, 199 + 99));
+    assert_eq!(a.votes_per_seat, Some(625.0));
+    assert_eq!(b.votes_per_seat, Some(1750.0));
+
+    // 2047 votes are wasted in all; B wastes 1649 against its 7/12 share
+    let gap = (1649.0 - 2047.0 * 1750.0 / 3000.0) / 3000.0;
+    assert!((b.efficiency_gap - gap).abs() < 1e-9);
+    assert!(b.efficiency_gap > 0.0 && a.efficiency_gap < 0.0);
+    assert!((a.efficiency_gap + b.efficiency_gap).abs() < 1e-9);
+
+    let south = &analysis.subdivisions["South"];
+    assert_eq!(south[0].party, "B");
+    assert_eq!(south[0].surplus_votes, 799);
+    assert_eq!(south[1].votes_per_seat, None);
+}
+
+#[test]
+fn test_wasted_votes_needs_a_single_winner_system() {
+    let election_result = two_party_election();
+    assert!(analyse_wasted_votes(
+        &election_result,
+        &ElectoralSystem::ProportionalRepresentation,
+        None
+    )
+    .is_err());
+    assert!(
+        analyse_wasted_votes(&election_result, &ElectoralSystem::AlternativeVote, None).is_err()
+    );
+}
+
+#[test]
+fn test_wasted_votes_on_bundled_data() {
+    let election_result = load_election_data("data/uk_2024.json").unwrap();
+    let analysis =
+        analyse_wasted_votes(&election_result, &ElectoralSystem::FirstPastThePost, None).unwrap();
+    let party = |name: &str| analysis.national.iter().find(|p| p.party == name).unwrap();
+    // Reform needed far more votes per MP than Labour
+    assert!(party("REF").votes_per_seat.unwrap() > 30.0 * party("LAB").votes_per_seat.unwrap());
+    assert_eq!(party("LAB").seats, 411);
+    assert_eq!(analysis.subdivisions.len(), 4);
+
+    let mut csv = Vec::new();
+    write_wasted_votes_csv(&mut csv, &analysis).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    assert!(csv.starts_with("area,party,votes,seats,lost_votes,surplus_votes,"));
+    assert!(csv.contains("\nnational,LAB,9705681,411,2110851,3186725,"));
+    assert!(csv.contains("\nScotland,SNP,"));
+}